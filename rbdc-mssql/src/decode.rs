@@ -4,7 +4,7 @@ use rbdc::datetime::DateTime;
 use rbdc::Error;
 use rbs::{to_value, Value};
 use tiberius::numeric::BigDecimal;
-use tiberius::ColumnData;
+use tiberius::{ColumnData, ColumnType};
 
 pub trait Decode {
     fn decode(row: &ColumnData<'static>) -> Result<Value, Error>;
@@ -160,12 +160,7 @@ impl Decode for Value {
                     match v {
                         Ok(v) => match v {
                             None => Value::Null,
-                            Some(v) => {
-                                let dt = DateTime(fastdate::DateTime::from_timestamp_nano(
-                                    v.timestamp_nanos_opt()
-                                        .expect("value can not be represented in a timestamp with nanosecond precision.") as i128 - (v.offset().utc_minus_local() * 60) as i128).set_offset(v.offset().utc_minus_local() * 60));
-                                to_value!(dt)
-                            }
+                            Some(v) => to_value!(DateTime(<fastdate::DateTime as DateTimeFromDateTimeFixedOffset>::from(v))),
                         },
                         Err(e) => {
                             return Err(Error::from(e.to_string()));
@@ -177,6 +172,25 @@ impl Decode for Value {
     }
 }
 
+/// decode by the type of the column, `money` and `smallmoney` are read by tiberius as floats
+/// and are decoded as `Decimal` with their 4 decimal places.
+pub fn decode_column(column_type: ColumnType, data: &ColumnData<'static>) -> Result<Value, Error> {
+    match (column_type, data) {
+        (ColumnType::Money | ColumnType::Money4, ColumnData::F64(Some(v))) => Ok(money(*v)),
+        (ColumnType::Money | ColumnType::Money4, ColumnData::F32(Some(v))) => Ok(money(*v as f64)),
+        _ => Value::decode(data),
+    }
+}
+
+/// money is sent as an integer of 1/10000 units, tiberius divides it into a float.
+/// the integer is recovered by rounding, it is exact up to 2^53 units (900 billion)
+fn money(v: f64) -> Value {
+    let units = (v * 1e4).round() as i64;
+    let sign = if units < 0 { "-" } else { "" };
+    let units = units.unsigned_abs();
+    Value::String(format!("{}{}.{:04}", sign, units / 10000, units % 10000)).into_ext("Decimal")
+}
+
 pub trait DateTimeFromNativeDatetime {
     fn from(arg: chrono::NaiveDateTime) -> Self;
 }
@@ -195,11 +209,12 @@ impl DateTimeFromNativeDatetime for fastdate::DateTime {
 }
 
 impl DateTimeFromDateTimeFixedOffset for fastdate::DateTime{
+    /// keeps the offset of the value
     fn from(arg: chrono::DateTime<FixedOffset>) -> Self {
         fastdate::DateTime::from_timestamp_nano(
             arg.timestamp_nanos_opt()
                 .expect("value can not be represented in a timestamp with nanosecond precision.") as i128,
-        ).set_offset(offset_sec())
+        ).set_offset(arg.offset().local_minus_utc())
     }
 }
 
@@ -228,4 +243,70 @@ mod test {
         println!("{}", de.to_string());
         assert_eq!(dt.to_string(),de.display_stand());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_decode_datetime_offset_fixture() {
+        use rbs::Value;
+        use tiberius::time::{Date, DateTime2, DateTimeOffset, Time};
+        use tiberius::ColumnData;
+        // 2023-10-20 11:23:55 UTC, stored with the offset +08:00 in minutes
+        let days = chrono::NaiveDate::from_ymd_opt(2023, 10, 20)
+            .unwrap()
+            .signed_duration_since(chrono::NaiveDate::from_ymd_opt(1, 1, 1).unwrap())
+            .num_days();
+        let data = ColumnData::DateTimeOffset(Some(DateTimeOffset::new(
+            DateTime2::new(
+                Date::new(days as u32),
+                Time::new((11 * 3600 + 23 * 60 + 55) * 10_000_000, 7),
+            ),
+            8 * 60,
+        )));
+        let v = <Value as crate::decode::Decode>::decode(&data).unwrap();
+        let dt: rbdc::datetime::DateTime = rbs::from_value(v).unwrap();
+        assert_eq!(dt.0.display(true), "2023-10-20T19:23:55+08:00");
+    }
+
+    #[test]
+    fn test_decode_money_xml_numeric_fixture() {
+        use crate::decode::{decode_column, Decode};
+        use rbs::Value;
+        use tiberius::numeric::Numeric;
+        use tiberius::xml::XmlData;
+        use tiberius::{ColumnData, ColumnType};
+        assert_eq!(
+            decode_column(ColumnType::Money, &ColumnData::F64(Some(12.3456))).unwrap(),
+            Value::String("12.3456".to_string()).into_ext("Decimal")
+        );
+        // the floats of tiberius: (high << 32 + low) / 1e4
+        assert_eq!(
+            decode_column(ColumnType::Money, &ColumnData::F64(Some(900000000000.1234))).unwrap(),
+            Value::String("900000000000.1234".to_string()).into_ext("Decimal")
+        );
+        assert_eq!(
+            decode_column(ColumnType::Money, &ColumnData::F64(Some(-0.0001))).unwrap(),
+            Value::String("-0.0001".to_string()).into_ext("Decimal")
+        );
+        assert_eq!(
+            decode_column(ColumnType::Money, &ColumnData::F64(Some(0.1 + 0.2))).unwrap(),
+            Value::String("0.3000".to_string()).into_ext("Decimal")
+        );
+        assert_eq!(
+            decode_column(ColumnType::Money, &ColumnData::F64(None)).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            decode_column(ColumnType::Float8, &ColumnData::F64(Some(1.5))).unwrap(),
+            Value::F64(1.5)
+        );
+        assert_eq!(
+            Value::decode(&ColumnData::Xml(Some(std::borrow::Cow::Owned(XmlData::new("<a/>")))))
+                .unwrap(),
+            Value::String("<a/>".to_string()).into_ext("Xml")
+        );
+        assert_eq!(
+            Value::decode(&ColumnData::Numeric(Some(Numeric::new_with_scale(12345678901234567890123456789012345678, 10))))
+                .unwrap(),
+            Value::String("1234567890123456789012345678.9012345678".to_string()).into_ext("Decimal")
+        );
+    }
+}
@@ -2,7 +2,6 @@ use crate::{MssqlConnectOptions, MssqlConnection};
 use futures_core::future::BoxFuture;
 use rbdc::db::{ConnectOptions, Connection, Driver, Placeholder};
 use rbdc::{impl_exchange, Error};
-use tiberius::Config;
 
 #[derive(Debug)]
 pub struct MssqlDriver {}
@@ -15,8 +14,8 @@ impl Driver for MssqlDriver {
     fn connect(&self, url: &str) -> BoxFuture<Result<Box<dyn Connection>, Error>> {
         let url = url.to_owned();
         Box::pin(async move {
-            let cfg = Config::from_jdbc_string(&url).map_err(|e| Error::from(e.to_string()))?;
-            let conn = MssqlConnection::establish(&cfg).await?;
+            let opt: MssqlConnectOptions = url.parse()?;
+            let conn = MssqlConnection::connect(&opt).await?;
             Ok(Box::new(conn) as Box<dyn Connection>)
         })
     }
@@ -27,13 +26,19 @@ impl Driver for MssqlDriver {
     ) -> BoxFuture<Result<Box<dyn Connection>, Error>> {
         let opt = opt.downcast_ref::<MssqlConnectOptions>().unwrap();
         Box::pin(async move {
-            let conn = MssqlConnection::establish(&opt.0).await?;
+            let conn = MssqlConnection::connect(opt).await?;
             Ok(Box::new(conn) as Box<dyn Connection>)
         })
     }
 
     fn default_option(&self) -> Box<dyn ConnectOptions> {
-        Box::new(MssqlConnectOptions(Config::new()))
+        Box::new(MssqlConnectOptions::new())
+    }
+
+    /// SQL Server takes at most 2100 parameters in a request,
+    /// `sp_executesql` takes 2 of them for the statement and the declaration of the params
+    fn max_params(&self) -> usize {
+        2098
     }
 }
 
@@ -1,8 +1,13 @@
 use rbdc::Error;
 use rbs::Value;
+use std::borrow::Cow;
 use std::str::FromStr;
-use tiberius::numeric::BigDecimal;
-use tiberius::{Query, Uuid};
+use tiberius::numeric::{BigDecimal, Numeric};
+use tiberius::xml::XmlData;
+use tiberius::{ColumnData, IntoSql, Query, Uuid};
+
+/// the max precision of `decimal`/`numeric` in SQL Server
+const MAX_PRECISION: u64 = 38;
 
 pub trait Encode {
     fn encode(self, q: &mut Query) -> Result<(), Error>;
@@ -10,99 +15,130 @@ pub trait Encode {
 
 impl Encode for Value {
     fn encode(self, q: &mut Query) -> Result<(), Error> {
-        match self {
-            Value::Null => {
-                q.bind(Option::<String>::None);
-                Ok(())
-            }
-            Value::Bool(v) => {
-                q.bind(v);
-                Ok(())
-            }
-            Value::I32(v) => {
-                q.bind(v);
-                Ok(())
-            }
-            Value::I64(v) => {
-                q.bind(v);
-                Ok(())
-            }
-            Value::U32(v) => {
-                q.bind(v as i32);
-                Ok(())
-            }
-            Value::U64(v) => {
-                q.bind(v as i64);
-                Ok(())
-            }
-            Value::F32(v) => {
-                q.bind(v);
-                Ok(())
-            }
-            Value::F64(v) => {
-                q.bind(v);
-                Ok(())
-            }
-            Value::String(v) => {
-                q.bind(v);
-                Ok(())
-            }
-            Value::Binary(v) => {
-                q.bind(v);
-                Ok(())
-            }
-            Value::Array(_) => Err(Error::from("unimplemented")),
-            Value::Map(_) => Err(Error::from("unimplemented")),
-            Value::Ext(t, v) => match t {
-                "Date" => {
-                    q.bind(
-                        chrono::NaiveDate::from_str(v.as_str().unwrap_or_default())
-                            .map_err(|e| Error::from(e.to_string()))?,
-                    );
-                    Ok(())
-                }
-                "DateTime" => {
-                    let date = fastdate::DateTime::from_str(&v.as_str().unwrap_or_default())?;
-                    q.bind(
-                        chrono::NaiveDateTime::from_str(&date.display(false))
-                            .map_err(|e| Error::from(e.to_string()))?,
-                    );
-                    Ok(())
-                }
-                "Time" => {
-                    q.bind(
-                        chrono::NaiveTime::from_str(v.as_str().unwrap_or_default())
-                            .map_err(|e| Error::from(e.to_string()))?,
-                    );
-                    Ok(())
-                }
-                "Decimal" => {
-                    q.bind(
-                        BigDecimal::from_str(&v.into_string().unwrap_or_default())
-                            .map_err(|e| Error::from(e.to_string()))?,
-                    );
-                    Ok(())
-                }
-                "Json" => Err(Error::from("unimplemented")),
-                "Timestamp" => {
-                    q.bind(v.as_i64().unwrap_or_default());
-                    Ok(())
-                }
-                "Uuid" => {
-                    q.bind(
-                        Uuid::from_str(&v.into_string().unwrap_or_default()).unwrap_or_default(),
-                    );
-                    Ok(())
+        q.bind(Param(to_column_data(self)?));
+        Ok(())
+    }
+}
+
+pub(crate) struct Param(pub(crate) ColumnData<'static>);
+
+impl<'a> IntoSql<'a> for Param {
+    fn into_sql(self) -> ColumnData<'a> {
+        self.0
+    }
+}
+
+/// convert a value to the parameter sent to SQL Server.
+///
+/// `Array` and `Map` are sent as JSON text, read an array of maps as a table with
+/// `select * from OPENJSON(@P1) with (id int, name nvarchar(50))`, as tiberius has no
+/// table-valued parameters.
+///
+/// `DateTime` is sent as a `datetime2` of its local date and time, see [`to_column_data_with`]
+/// to keep its offset.
+pub fn to_column_data(v: Value) -> Result<ColumnData<'static>, Error> {
+    to_column_data_with(v, false)
+}
+
+/// like [`to_column_data`], `DateTime` is sent as `datetimeoffset` when `datetime_offset` is true.
+///
+/// SQL Server keeps the local date and time of a `datetimeoffset` stored into a `datetime2`
+/// column, but converts a `datetime2` column to `datetimeoffset` with the offset `+00:00`
+/// to compare it with the parameter, so `where create_time > @P1` compares other instants.
+/// Enable it with [`MssqlConnectOptions::datetime_offset`](crate::MssqlConnectOptions::datetime_offset)
+/// for `datetimeoffset` columns.
+pub fn to_column_data_with(v: Value, datetime_offset: bool) -> Result<ColumnData<'static>, Error> {
+    Ok(match v {
+        Value::Null => ColumnData::String(None),
+        Value::Bool(v) => ColumnData::Bit(Some(v)),
+        Value::I32(v) => ColumnData::I32(Some(v)),
+        Value::I64(v) => ColumnData::I64(Some(v)),
+        Value::U32(v) => ColumnData::I64(Some(v as i64)),
+        Value::U64(v) => match i64::try_from(v) {
+            Ok(v) => ColumnData::I64(Some(v)),
+            Err(_) => ColumnData::Numeric(Some(Numeric::new_with_scale(v as i128, 0))),
+        },
+        Value::F32(v) => ColumnData::F32(Some(v)),
+        Value::F64(v) => ColumnData::F64(Some(v)),
+        Value::String(v) => ColumnData::String(Some(Cow::Owned(v))),
+        Value::Binary(v) => ColumnData::Binary(Some(Cow::Owned(v))),
+        Value::Array(v) => ColumnData::String(Some(Cow::Owned(Value::Array(v).to_string()))),
+        Value::Map(v) => ColumnData::String(Some(Cow::Owned(Value::Map(v).to_string()))),
+        Value::Ext(t, v) => match t {
+            "Date" => chrono::NaiveDate::from_str(v.as_str().unwrap_or_default())
+                .map_err(|e| Error::from(e.to_string()))?
+                .into_sql(),
+            "DateTime" => {
+                let date = fastdate::DateTime::from_str(v.as_str().unwrap_or_default())?;
+                if datetime_offset {
+                    to_datetime_offset(&date)?.into_sql()
+                } else {
+                    chrono::NaiveDateTime::from_str(&date.display(false))
+                        .map_err(|e| Error::from(e.to_string()))?
+                        .into_sql()
                 }
-                _ => Err(Error::from("unimplemented")),
-            },
-        }
+            }
+            "Time" => chrono::NaiveTime::from_str(v.as_str().unwrap_or_default())
+                .map_err(|e| Error::from(e.to_string()))?
+                .into_sql(),
+            "Decimal" => to_numeric(v.as_str().unwrap_or_default())?,
+            "Json" => ColumnData::String(Some(Cow::Owned(v.into_string().unwrap_or_default()))),
+            "Xml" => ColumnData::Xml(Some(Cow::Owned(XmlData::new(
+                v.into_string().unwrap_or_default(),
+            )))),
+            "Timestamp" => ColumnData::I64(Some(v.as_i64().unwrap_or_default())),
+            "Uuid" => ColumnData::Guid(Some(
+                Uuid::from_str(v.as_str().unwrap_or_default())
+                    .map_err(|e| Error::from(e.to_string()))?,
+            )),
+            _ => return Err(Error::from(format!("unsupported type Ext({})", t))),
+        },
+    })
+}
+
+fn to_datetime_offset(
+    date: &fastdate::DateTime,
+) -> Result<chrono::DateTime<chrono::FixedOffset>, Error> {
+    let nano = date.unix_timestamp_nano();
+    let offset = chrono::FixedOffset::east_opt(date.offset())
+        .ok_or_else(|| Error::from(format!("invalid offset {}", date.offset())))?;
+    let utc = chrono::DateTime::from_timestamp(
+        nano.div_euclid(1_000_000_000) as i64,
+        nano.rem_euclid(1_000_000_000) as u32,
+    )
+    .ok_or_else(|| Error::from(format!("DateTime out of range: {}", date)))?;
+    Ok(utc.with_timezone(&offset))
+}
+
+/// keeps the scale of the text, `1.50` is sent as `decimal(3, 2)`
+fn to_numeric(v: &str) -> Result<ColumnData<'static>, Error> {
+    let d = BigDecimal::from_str(v).map_err(|e| Error::from(e.to_string()))?;
+    // SQL Server has no negative scale, `1E+3` is sent as `1000`
+    let d = if d.as_bigint_and_exponent().1 < 0 {
+        d.with_scale(0)
+    } else {
+        d
+    };
+    let digits = d.digits();
+    let (int, scale) = d.into_bigint_and_exponent();
+    match i128::try_from(&int) {
+        Ok(value) if digits <= MAX_PRECISION && scale < MAX_PRECISION as i64 => Ok(
+            ColumnData::Numeric(Some(Numeric::new_with_scale(value, scale as u8))),
+        ),
+        _ => Err(Error::from(format!(
+            "Decimal {} exceeds the precision of {} digits of SQL Server",
+            v, MAX_PRECISION
+        ))),
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::decode::Decode;
+    use crate::encode::{to_column_data, to_column_data_with};
+    use rbs::Value;
     use std::str::FromStr;
+    use tiberius::ColumnData;
 
     #[test]
     fn test_from() {
@@ -112,4 +148,65 @@ mod test {
         assert_eq!(v.display(false),n.to_string().replace(" ","T").trim_end_matches("0"));
     }
 
+    #[test]
+    fn test_encode_map_json() {
+        let mut m = rbs::value::map::ValueMap::new();
+        m.insert(Value::String("id".to_string()), Value::I32(1));
+        let v = to_column_data(Value::Array(vec![Value::Map(m)])).unwrap();
+        assert_eq!(v, ColumnData::String(Some(r#"[{"id":1}]"#.into())));
+    }
+
+    #[test]
+    fn test_encode_decimal() {
+        let v = to_column_data(Value::String("1.50".to_string()).into_ext("Decimal")).unwrap();
+        match &v {
+            ColumnData::Numeric(Some(n)) => {
+                assert_eq!(n.value(), 150);
+                assert_eq!(n.scale(), 2);
+            }
+            _ => panic!("not numeric {:?}", v),
+        }
+        assert_eq!(
+            Value::decode(&v).unwrap(),
+            Value::String("1.50".to_string()).into_ext("Decimal")
+        );
+        let too_long = "1".repeat(39);
+        assert!(to_column_data(Value::String(too_long).into_ext("Decimal")).is_err());
+    }
+
+    #[test]
+    fn test_encode_datetime() {
+        let s = "2023-10-20T19:23:55.5+08:00";
+        let v = to_column_data(Value::String(s.to_string()).into_ext("DateTime")).unwrap();
+        assert!(matches!(v, ColumnData::DateTime2(Some(_))));
+        let decoded: rbdc::datetime::DateTime =
+            rbs::from_value(Value::decode(&v).unwrap()).unwrap();
+        assert_eq!(
+            decoded.0.display(false),
+            fastdate::DateTime::from_str(s).unwrap().display(false)
+        );
+    }
+
+    #[test]
+    fn test_encode_datetime_offset() {
+        let s = "2023-10-20T19:23:55+08:00";
+        let v = to_column_data_with(Value::String(s.to_string()).into_ext("DateTime"), true)
+            .unwrap();
+        assert!(matches!(v, ColumnData::DateTimeOffset(Some(_))));
+        let decoded: rbdc::datetime::DateTime =
+            rbs::from_value(Value::decode(&v).unwrap()).unwrap();
+        assert_eq!(decoded.0, fastdate::DateTime::from_str(s).unwrap());
+        assert_eq!(decoded.0.offset(), 8 * 3600);
+    }
+
+    #[test]
+    fn test_encode_uuid() {
+        let s = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let v = to_column_data(Value::String(s.to_string()).into_ext("Uuid")).unwrap();
+        assert_eq!(
+            Value::decode(&v).unwrap(),
+            Value::String(s.to_string()).into_ext("Uuid")
+        );
+        assert!(to_column_data(Value::String("x".to_string()).into_ext("Uuid")).is_err());
+    }
 }
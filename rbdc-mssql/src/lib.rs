@@ -1,60 +1,63 @@
 pub extern crate tiberius;
 
+pub mod bulk;
 pub mod decode;
 pub mod driver;
 pub mod encode;
+pub mod options;
 
 pub use crate::driver::MssqlDriver;
 pub use crate::driver::MssqlDriver as Driver;
+pub use crate::options::MssqlConnectOptions;
 
-use crate::decode::Decode;
-use crate::encode::Encode;
+use crate::decode::decode_column;
+use crate::encode::{to_column_data_with, Param};
 use futures_core::future::BoxFuture;
 use futures_core::Stream;
-use rbdc::db::{ConnectOptions, Connection, ExecResult, MetaData, Placeholder, Row};
+use rbdc::db::{Connection, ExecResult, MetaData, Placeholder, Row};
 use rbdc::Error;
 use rbs::Value;
 use std::sync::Arc;
-use tiberius::{Client, Column, ColumnData, Config, Query};
+use tiberius::{Client, Column, ColumnData, Config, Query, SqlBrowser};
 use tokio::net::TcpStream;
 use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
 
 pub struct MssqlConnection {
     inner: Option<Client<Compat<TcpStream>>>,
+    /// send `DateTime` params as `datetimeoffset`
+    datetime_offset: bool,
 }
 
 impl MssqlConnection {
-    /// let cfg = Config::from_jdbc_string(url).map_err(|e| Error::from(e.to_owned()))?;
+    /// connects to the server, the port of a named instance is resolved through SQL Browser.
+    ///
+    /// build the `cfg` with [`MssqlConnectOptions::config()`]
     pub async fn establish(cfg: &Config) -> Result<Self, Error> {
-        // let cfg = Config::from_jdbc_string(url).map_err(|e| Error::from(e.to_owned()))?;
-        let tcp = TcpStream::connect(cfg.get_addr())
+        let tcp = TcpStream::connect_named(cfg)
             .await
             .map_err(|e| Error::from(e.to_string()))?;
-        tcp.set_nodelay(true).unwrap();
+        tcp.set_nodelay(true)
+            .map_err(|e| Error::from(e.to_string()))?;
         let c = Client::connect(cfg.clone(), tcp.compat_write())
             .await
             .map_err(|e| Error::from(e.to_string()))?;
-        Ok(Self { inner: Some(c) })
+        Ok(Self {
+            inner: Some(c),
+            datetime_offset: false,
+        })
     }
-}
 
-#[derive(Debug)]
-pub struct MssqlConnectOptions(pub Config);
-
-impl ConnectOptions for MssqlConnectOptions {
-    fn connect(&self) -> BoxFuture<Result<Box<dyn Connection>, Error>> {
-        Box::pin(async move {
-            let v = MssqlConnection::establish(&self.0)
-                .await
-                .map_err(|e| Error::from(e.to_string()))?;
-            Ok(Box::new(v) as Box<dyn Connection>)
-        })
+    /// connects with the config and the encoding options of `options`
+    pub async fn connect(options: &MssqlConnectOptions) -> Result<Self, Error> {
+        let mut conn = Self::establish(&options.config()).await?;
+        conn.datetime_offset = options.datetime_offset;
+        Ok(conn)
     }
 
-    fn set_uri(&mut self, url: &str) -> Result<(), Error> {
-        *self = MssqlConnectOptions(
-            Config::from_jdbc_string(url).map_err(|e| Error::from(e.to_string()))?,
-        );
+    fn bind(&self, q: &mut Query, params: Vec<Value>) -> Result<(), Error> {
+        for x in params {
+            q.bind(Param(to_column_data_with(x, self.datetime_offset)?));
+        }
         Ok(())
     }
 }
@@ -88,7 +91,7 @@ impl Row for MssqlRow {
     }
 
     fn get(&mut self, i: usize) -> Result<Value, Error> {
-        Value::decode(&self.datas[i])
+        decode_column(self.columns[i].column_type(), &self.datas[i])
     }
 }
 
@@ -101,9 +104,7 @@ impl Connection for MssqlConnection {
         let sql = MssqlDriver {}.exchange(sql);
         Box::pin(async move {
             let mut q = Query::new(sql);
-            for x in params {
-                x.encode(&mut q)?;
-            }
+            self.bind(&mut q, params)?;
             let v = q
                 .query(
                     self.inner
@@ -142,9 +143,7 @@ impl Connection for MssqlConnection {
         let sql = MssqlDriver {}.exchange(sql);
         Box::pin(async move {
             let mut q = Query::new(sql);
-            for x in params {
-                x.encode(&mut q)?;
-            }
+            self.bind(&mut q, params)?;
             let v = q
                 .execute(
                     self.inner
@@ -166,6 +165,15 @@ impl Connection for MssqlConnection {
         })
     }
 
+    fn bulk_insert(
+        &mut self,
+        table: &str,
+        rows: Vec<Value>,
+    ) -> BoxFuture<Result<ExecResult, Error>> {
+        let table = table.to_string();
+        Box::pin(async move { MssqlConnection::bulk_insert(self, &table, rows).await })
+    }
+
     fn close(&mut self) -> BoxFuture<Result<(), Error>> {
         Box::pin(async move {
             //inner must be Option,so we can take owner and call close(self) method.
@@ -181,7 +189,7 @@ impl Connection for MssqlConnection {
         Box::pin(async move {
             self.inner
                 .as_mut()
-                .expect("MssqlConnection inner is none")
+                .ok_or_else(|| Error::from("MssqlConnection is close"))?
                 .query("select 1", &[])
                 .await
                 .map_err(|e| Error::from(e.to_string()))?;
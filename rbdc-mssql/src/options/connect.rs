@@ -0,0 +1,21 @@
+use crate::options::MssqlConnectOptions;
+use crate::MssqlConnection;
+use futures_core::future::BoxFuture;
+use rbdc::db::{ConnectOptions, Connection};
+use rbdc::Error;
+
+impl ConnectOptions for MssqlConnectOptions {
+    fn connect(&self) -> BoxFuture<Result<Box<dyn Connection>, Error>> {
+        Box::pin(async move {
+            let v = MssqlConnection::connect(self)
+                .await
+                .map_err(|e| Error::from(e.to_string()))?;
+            Ok(Box::new(v) as Box<dyn Connection>)
+        })
+    }
+
+    fn set_uri(&mut self, url: &str) -> Result<(), Error> {
+        *self = url.parse()?;
+        Ok(())
+    }
+}
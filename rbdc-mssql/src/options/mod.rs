@@ -0,0 +1,230 @@
+mod connect;
+mod parse;
+
+use connection_string::{AdoNetString, JdbcString};
+use rbdc::Error;
+use std::path::Path;
+use tiberius::{AuthMethod, Config, EncryptionLevel};
+
+/// Options and flags which can be used to configure a SQL Server connection.
+///
+/// A value of `MssqlConnectOptions` can be parsed from a connection URL, an
+/// [ADO.NET connection string](https://docs.microsoft.com/en-us/dotnet/framework/data/adonet/connection-strings)
+/// or a [JDBC connection string](https://docs.microsoft.com/en-us/sql/connect/jdbc/building-the-connection-url):
+///
+/// ```text
+/// mssql://[user[:password]@][host][:port][/database][?properties]
+/// server=tcp:host,1433;user=SA;password=pass;database=test
+/// jdbc:sqlserver://host:1433;user=SA;password={pass};database=test
+/// ```
+///
+/// Unknown properties are an error rather than ignored, a typo must not turn off `encrypt`.
+///
+/// ## Properties of the URL
+///
+/// |Parameter|Default|Description|
+/// |---------|-------|-----------|
+/// | `encrypt` | `required` with a tls feature | `true` or `required`, `on` (encrypt when the server supports it), `false` or `off` (encrypt the login only) and `not_supported`. See [`MssqlConnectOptions::encrypt`]. |
+/// | `trust_cert` | `false` | Accept the certificate of the server without validation. |
+/// | `trust_cert_ca` | `None` | Path to a `pem`, `crt` or `der` certificate to validate the server with. |
+/// | `instance` | `None` | The named instance, its port is resolved through SQL Browser. |
+/// | `application_name` | `None` | The name returned by `APP_NAME()`. |
+/// | `readonly` | `false` | Connect with the `ReadOnly` application intent. |
+/// | `datetime_offset` | `false` | Send `DateTime` params as `datetimeoffset`. See [`MssqlConnectOptions::datetime_offset`]. |
+///
+/// ## Migrating from `MssqlConnectOptions(Config)`
+///
+/// The options used to be a tuple struct of the tiberius [`Config`]. Build them from a config with
+/// `MssqlConnectOptions::from(config)`, and read the config back with [`config`](Self::config)
+/// or change it with [`config_mut`](Self::config_mut) instead of the field `.0`.
+///
+#[derive(Debug, Clone)]
+pub struct MssqlConnectOptions {
+    pub(crate) config: Config,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) datetime_offset: bool,
+}
+
+impl Default for MssqlConnectOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Config> for MssqlConnectOptions {
+    fn from(config: Config) -> Self {
+        Self {
+            config,
+            username: None,
+            password: None,
+            datetime_offset: false,
+        }
+    }
+}
+
+impl MssqlConnectOptions {
+    /// Creates a new, default set of options ready for configuration
+    pub fn new() -> Self {
+        Self::from(Config::new())
+    }
+
+    /// Parses an ADO.NET connection string, such as
+    /// `server=tcp:localhost,1433;user=SA;password=pass;TrustServerCertificate=true`
+    pub fn from_ado_string(s: &str) -> Result<Self, Error> {
+        let ado: AdoNetString = s.parse().map_err(|e| Error::from(format!("{}", e)))?;
+        parse::check_keys(ado.keys())?;
+        Ok(Self::from(
+            Config::from_ado_string(s).map_err(|e| Error::from(e.to_string()))?,
+        ))
+    }
+
+    /// Parses a JDBC connection string, such as
+    /// `jdbc:sqlserver://localhost:1433;user=SA;password={pass};database=test`
+    pub fn from_jdbc_string(s: &str) -> Result<Self, Error> {
+        let jdbc: JdbcString = s.parse().map_err(|e| Error::from(format!("{}", e)))?;
+        parse::check_keys(jdbc.properties().keys())?;
+        Ok(Self::from(
+            Config::from_jdbc_string(s).map_err(|e| Error::from(e.to_string()))?,
+        ))
+    }
+
+    /// Sets the name of the host to connect to.
+    ///
+    /// The default behavior when the host is not specified,
+    /// is to connect to localhost.
+    pub fn host(mut self, host: &str) -> Self {
+        self.config.host(host);
+        self
+    }
+
+    /// Sets the port to connect to at the server host.
+    ///
+    /// The default port for SQL Server is `1433`.
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.port(port);
+        self
+    }
+
+    /// Sets the named instance, such as `SQLEXPRESS`.
+    ///
+    /// The port of the instance is asked to the SQL Browser service on the UDP port `1434`
+    /// of the host, unless [`port`](Self::port) is set to the port of the SQL Browser.
+    pub fn instance_name(mut self, name: &str) -> Self {
+        self.config.instance_name(name);
+        self
+    }
+
+    /// Sets the username to connect as, with SQL Server authentication.
+    pub fn username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_owned());
+        self
+    }
+
+    /// Sets the password to connect with.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_owned());
+        self
+    }
+
+    /// Sets the database name.
+    pub fn database(mut self, database: &str) -> Self {
+        self.config.database(database);
+        self
+    }
+
+    /// Sets the name returned by `APP_NAME()`.
+    pub fn application_name(mut self, name: &str) -> Self {
+        self.config.application_name(name);
+        self
+    }
+
+    /// Sets whether the connection is encrypted with TLS.
+    ///
+    /// By default, the encryption is [`Required`](EncryptionLevel::Required) when the driver is built
+    /// with the `tls-rustls` or `tls-native-tls` feature, and
+    /// [`NotSupported`](EncryptionLevel::NotSupported) otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rbdc_mssql::options::MssqlConnectOptions;
+    /// # use rbdc_mssql::tiberius::EncryptionLevel;
+    /// let options = MssqlConnectOptions::new()
+    ///     .encrypt(EncryptionLevel::Required)
+    ///     .trust_cert_ca("path/to/ca.crt");
+    /// ```
+    pub fn encrypt(mut self, level: EncryptionLevel) -> Self {
+        self.config.encryption(level);
+        self
+    }
+
+    /// Accepts the certificate of the server without validating it.
+    ///
+    /// Use it for development only, prefer [`trust_cert_ca`](Self::trust_cert_ca) for a
+    /// self-signed certificate. It can not be used together with `trust_cert_ca`.
+    pub fn trust_cert(mut self) -> Self {
+        self.config.trust_cert();
+        self
+    }
+
+    /// Validates the certificate of the server against the `pem`, `crt` or `der` certificate
+    /// at `path`, in addition to the system trust store.
+    pub fn trust_cert_ca(mut self, path: impl AsRef<Path>) -> Self {
+        self.config
+            .trust_cert_ca(path.as_ref().to_string_lossy().to_string());
+        self
+    }
+
+    /// Connects with the `ReadOnly` application intent, to be routed to a readable secondary.
+    pub fn readonly(mut self, readonly: bool) -> Self {
+        self.config.readonly(readonly);
+        self
+    }
+
+    /// Sets the authentication method, such as Windows authentication.
+    ///
+    /// It replaces the [`username`](Self::username) and [`password`](Self::password).
+    pub fn authentication(mut self, auth: AuthMethod) -> Self {
+        self.username = None;
+        self.password = None;
+        self.config.authentication(auth);
+        self
+    }
+
+    /// Sends `DateTime` params as `datetimeoffset` to keep their offset, instead of a `datetime2`
+    /// of their local date and time. default false.
+    ///
+    /// Use it with `datetimeoffset` columns: SQL Server compares a `datetime2` column with a
+    /// `datetimeoffset` param as if the column was at `+00:00`.
+    pub fn datetime_offset(mut self, datetime_offset: bool) -> Self {
+        self.datetime_offset = datetime_offset;
+        self
+    }
+
+    /// the tiberius config to set what the builder has no method for.
+    ///
+    /// the [`username`](Self::username) and [`password`](Self::password) replace its authentication
+    ///
+    /// ```rust
+    /// # use rbdc_mssql::options::MssqlConnectOptions;
+    /// let mut options = MssqlConnectOptions::new();
+    /// options.config_mut().host("db.local");
+    /// assert_eq!(options.config().get_addr(), "db.local:1433");
+    /// ```
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// the tiberius config of the options
+    pub fn config(&self) -> Config {
+        let mut config = self.config.clone();
+        if let Some(username) = &self.username {
+            config.authentication(AuthMethod::sql_server(
+                username,
+                self.password.as_deref().unwrap_or_default(),
+            ));
+        }
+        config
+    }
+}
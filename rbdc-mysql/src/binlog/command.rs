@@ -0,0 +1,125 @@
+use crate::protocol::Capabilities;
+use rbdc::io::Encode;
+use rbdc::Error;
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_register_slave.html
+
+#[derive(Debug)]
+pub struct RegisterSlave<'a> {
+    pub server_id: u32,
+    pub hostname: &'a str,
+    pub user: &'a str,
+    pub password: &'a str,
+    pub port: u16,
+}
+
+impl Encode<'_, Capabilities> for RegisterSlave<'_> {
+    fn encode_with(&self, buf: &mut Vec<u8>, _: Capabilities) {
+        buf.push(0x15); // COM_REGISTER_SLAVE
+        buf.extend(&self.server_id.to_le_bytes());
+        for s in [self.hostname, self.user, self.password] {
+            buf.push(s.len() as u8);
+            buf.extend(s.as_bytes());
+        }
+        buf.extend(&self.port.to_le_bytes());
+        // replication rank, ignored
+        buf.extend(&0_u32.to_le_bytes());
+        // master id, filled by the server
+        buf.extend(&0_u32.to_le_bytes());
+    }
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump.html
+
+#[derive(Debug)]
+pub struct BinlogDump<'a> {
+    pub position: u32,
+    pub flags: u16,
+    pub server_id: u32,
+    pub file: &'a str,
+}
+
+impl Encode<'_, Capabilities> for BinlogDump<'_> {
+    fn encode_with(&self, buf: &mut Vec<u8>, _: Capabilities) {
+        buf.push(0x12); // COM_BINLOG_DUMP
+        buf.extend(&self.position.to_le_bytes());
+        buf.extend(&self.flags.to_le_bytes());
+        buf.extend(&self.server_id.to_le_bytes());
+        buf.extend(self.file.as_bytes());
+    }
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump_gtid.html
+
+#[derive(Debug)]
+pub struct BinlogDumpGtid<'a> {
+    pub flags: u16,
+    pub server_id: u32,
+    pub file: &'a str,
+    pub position: u64,
+    /// encoded with [`encode_gtid_set`]
+    pub gtid_set: &'a [u8],
+}
+
+impl Encode<'_, Capabilities> for BinlogDumpGtid<'_> {
+    fn encode_with(&self, buf: &mut Vec<u8>, _: Capabilities) {
+        buf.push(0x1e); // COM_BINLOG_DUMP_GTID
+        buf.extend(&self.flags.to_le_bytes());
+        buf.extend(&self.server_id.to_le_bytes());
+        buf.extend(&(self.file.len() as u32).to_le_bytes());
+        buf.extend(self.file.as_bytes());
+        buf.extend(&self.position.to_le_bytes());
+        buf.extend(&(self.gtid_set.len() as u32).to_le_bytes());
+        buf.extend(self.gtid_set);
+    }
+}
+
+/// encode a gtid set such as `3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7,...`
+/// to the binary format of COM_BINLOG_DUMP_GTID
+pub fn encode_gtid_set(set: &str) -> Result<Vec<u8>, Error> {
+    let sids: Vec<&str> = set
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .collect();
+    let mut buf = Vec::new();
+    buf.extend(&(sids.len() as u64).to_le_bytes());
+    for sid in sids {
+        let mut parts = sid.split(':');
+        let uuid = parts.next().unwrap_or_default().replace('-', "");
+        let uuid = hex::decode(&uuid)
+            .ok()
+            .filter(|x| x.len() == 16)
+            .ok_or_else(|| Error::from(format!("invalid gtid set uuid: {}", sid)))?;
+        buf.extend(&uuid);
+        let intervals: Vec<&str> = parts.collect();
+        buf.extend(&(intervals.len() as u64).to_le_bytes());
+        for interval in intervals {
+            let (start, end) = match interval.split_once('-') {
+                Some((start, end)) => (start.parse::<u64>()?, end.parse::<u64>()?),
+                None => {
+                    let v = interval.parse::<u64>()?;
+                    (v, v)
+                }
+            };
+            // the end of an interval is exclusive
+            buf.extend(&start.to_le_bytes());
+            buf.extend(&(end + 1).to_le_bytes());
+        }
+    }
+    Ok(buf)
+}
+
+#[test]
+fn test_encode_gtid_set() {
+    let v = encode_gtid_set("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7").unwrap();
+    assert_eq!(v.len(), 8 + 16 + 8 + 16 * 2);
+    assert_eq!(&v[..8], &1_u64.to_le_bytes());
+    assert_eq!(&v[8..24], &hex::decode("3e11fa4771ca11e19e33c80aa9429562").unwrap()[..]);
+    assert_eq!(&v[24..32], &2_u64.to_le_bytes());
+    assert_eq!(&v[32..40], &1_u64.to_le_bytes());
+    assert_eq!(&v[40..48], &6_u64.to_le_bytes());
+    assert_eq!(&v[48..56], &7_u64.to_le_bytes());
+    assert_eq!(&v[56..64], &8_u64.to_le_bytes());
+    assert!(encode_gtid_set("not-a-uuid:1").is_err());
+}
@@ -0,0 +1,563 @@
+use crate::binlog::value::{self, decode_value, take};
+use crate::binlog::{RowEvent, RowOp};
+use crate::io::MySqlBufExt;
+use bytes::{Buf, Bytes};
+use rbdc::{err_protocol, Error};
+use rbs::value::map::ValueMap;
+use rbs::Value;
+use std::collections::HashMap;
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/namespacemysql_1_1binlog_1_1event.html
+pub const QUERY_EVENT: u8 = 2;
+pub const ROTATE_EVENT: u8 = 4;
+pub const FORMAT_DESCRIPTION_EVENT: u8 = 15;
+pub const XID_EVENT: u8 = 16;
+pub const TABLE_MAP_EVENT: u8 = 19;
+pub const WRITE_ROWS_EVENT_V1: u8 = 23;
+pub const UPDATE_ROWS_EVENT_V1: u8 = 24;
+pub const DELETE_ROWS_EVENT_V1: u8 = 25;
+pub const WRITE_ROWS_EVENT: u8 = 30;
+pub const UPDATE_ROWS_EVENT: u8 = 31;
+pub const DELETE_ROWS_EVENT: u8 = 32;
+pub const GTID_EVENT: u8 = 33;
+
+const EVENT_HEADER_LEN: usize = 19;
+const CHECKSUM_LEN: usize = 4;
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_binlog_event_header
+#[derive(Debug, Clone)]
+pub struct EventHeader {
+    pub timestamp: u32,
+    pub event_type: u8,
+    pub server_id: u32,
+    pub event_size: u32,
+    /// position of the next event
+    pub log_pos: u32,
+    pub flags: u16,
+}
+
+impl EventHeader {
+    pub fn decode(buf: &mut Bytes) -> Result<Self, Error> {
+        let mut buf = take(buf, EVENT_HEADER_LEN)?;
+        Ok(Self {
+            timestamp: buf.get_u32_le(),
+            event_type: buf.get_u8(),
+            server_id: buf.get_u32_le(),
+            event_size: buf.get_u32_le(),
+            log_pos: buf.get_u32_le(),
+            flags: buf.get_u16_le(),
+        })
+    }
+}
+
+/// a column of a TABLE_MAP_EVENT
+#[derive(Debug, Clone, Default)]
+pub struct TableColumn {
+    pub r#type: u8,
+    pub meta: u16,
+    pub nullable: bool,
+    pub unsigned: bool,
+    /// only sent with `binlog_row_metadata = FULL`
+    pub name: Option<String>,
+    /// the names of an ENUM or SET, only sent with `binlog_row_metadata = FULL`
+    pub values: Option<Vec<String>>,
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Table__map__event.html
+#[derive(Debug, Clone)]
+pub struct TableMap {
+    pub table_id: u64,
+    pub schema: String,
+    pub table: String,
+    pub columns: Vec<TableColumn>,
+}
+
+fn get_str(buf: &mut Bytes, len: usize) -> Result<String, Error> {
+    String::from_utf8(take(buf, len)?.to_vec()).map_err(|e| err_protocol!("binlog: {}", e))
+}
+
+fn get_lenenc(buf: &mut Bytes) -> Result<u64, Error> {
+    if !buf.has_remaining() {
+        return Err(err_protocol!("binlog: unexpected end of event"));
+    }
+    Ok(buf.get_uint_lenenc())
+}
+
+fn get_str_lenenc(buf: &mut Bytes) -> Result<String, Error> {
+    let len = get_lenenc(buf)? as usize;
+    get_str(buf, len)
+}
+
+// bit of a row bitmap, the lowest bit first
+fn bit(bitmap: &[u8], i: usize) -> bool {
+    bitmap[i / 8] & (1 << (i % 8)) != 0
+}
+
+fn is_numeric(t: u8) -> bool {
+    matches!(
+        t,
+        value::TINY
+            | value::SHORT
+            | value::INT24
+            | value::LONG
+            | value::LONGLONG
+            | value::NEWDECIMAL
+            | value::FLOAT
+            | value::DOUBLE
+    )
+}
+
+// the type of an ENUM or SET column is STRING, the real type is in the metadata
+fn real_type(column: &TableColumn) -> u8 {
+    if column.r#type == value::STRING {
+        value::string_real_type(column.meta).0
+    } else {
+        column.r#type
+    }
+}
+
+impl TableMap {
+    pub fn decode(mut buf: Bytes) -> Result<Self, Error> {
+        let table_id = take(&mut buf, 6)?.get_uint_le(6);
+        let _flags = take(&mut buf, 2)?;
+        let len = take(&mut buf, 1)?[0] as usize;
+        let schema = get_str(&mut buf, len)?;
+        take(&mut buf, 1)?;
+        let len = take(&mut buf, 1)?[0] as usize;
+        let table = get_str(&mut buf, len)?;
+        take(&mut buf, 1)?;
+
+        let column_count = get_lenenc(&mut buf)? as usize;
+        let types = take(&mut buf, column_count)?;
+        let meta_len = get_lenenc(&mut buf)? as usize;
+        let mut meta = take(&mut buf, meta_len)?;
+        let mut columns = Vec::with_capacity(column_count);
+        for t in types.iter() {
+            let t = *t;
+            let m = match t {
+                value::FLOAT
+                | value::DOUBLE
+                | value::BLOB
+                | value::GEOMETRY
+                | value::JSON
+                | value::TIMESTAMP2
+                | value::DATETIME2
+                | value::TIME2 => take(&mut meta, 1)?[0] as u16,
+                value::VARCHAR | value::VAR_STRING | value::BIT => {
+                    take(&mut meta, 2)?.get_u16_le()
+                }
+                value::NEWDECIMAL | value::STRING | value::ENUM | value::SET => {
+                    take(&mut meta, 2)?.get_u16()
+                }
+                _ => 0,
+            };
+            columns.push(TableColumn {
+                r#type: t,
+                meta: m,
+                ..Default::default()
+            });
+        }
+        let nulls = take(&mut buf, column_count.div_ceil(8))?;
+        for (i, c) in columns.iter_mut().enumerate() {
+            c.nullable = bit(&nulls, i);
+        }
+
+        // optional metadata, written with binlog_row_metadata
+        // https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Table__map__event.html#Table_table_map_event_optional_metadata
+        while buf.has_remaining() {
+            let field_type = take(&mut buf, 1)?[0];
+            let len = get_lenenc(&mut buf)? as usize;
+            let mut field = take(&mut buf, len)?;
+            match field_type {
+                // SIGNEDNESS, one bit for each numeric column, the highest bit first
+                1 => {
+                    let numeric = columns.iter_mut().filter(|c| is_numeric(c.r#type));
+                    for (n, c) in numeric.enumerate() {
+                        if let Some(b) = field.get(n / 8) {
+                            c.unsigned = b & (0x80 >> (n % 8)) != 0;
+                        }
+                    }
+                }
+                // COLUMN_NAME
+                4 => {
+                    for c in columns.iter_mut() {
+                        c.name = Some(get_str_lenenc(&mut field)?);
+                    }
+                }
+                // SET_STR_VALUE and ENUM_STR_VALUE
+                5 | 6 => {
+                    let t = if field_type == 5 { value::SET } else { value::ENUM };
+                    for c in columns.iter_mut().filter(|c| real_type(c) == t) {
+                        let count = get_lenenc(&mut field)?;
+                        let mut values = Vec::with_capacity(count as usize);
+                        for _ in 0..count {
+                            values.push(get_str_lenenc(&mut field)?);
+                        }
+                        c.values = Some(values);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            table_id,
+            schema,
+            table,
+            columns,
+        })
+    }
+
+    fn decode_row(&self, buf: &mut Bytes, present: &[u8]) -> Result<Value, Error> {
+        let count = (0..self.columns.len()).filter(|i| bit(present, *i)).count();
+        let nulls = take(buf, count.div_ceil(8))?;
+        let mut map = ValueMap::with_capacity(count);
+        let mut index = 0;
+        for (i, column) in self.columns.iter().enumerate() {
+            if !bit(present, i) {
+                continue;
+            }
+            let v = if bit(&nulls, index) {
+                Value::Null
+            } else {
+                decode_value(buf, column)?
+            };
+            index += 1;
+            // without binlog_row_metadata = FULL the columns are named like `mysqlbinlog -v` does
+            let name = column
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("@{}", i + 1));
+            map.insert(Value::String(name), v);
+        }
+        Ok(Value::Map(map))
+    }
+}
+
+/// decode the events of a binlog stream to row changes.
+/// keeps the state between events: the current file, the checksum and the table maps
+#[derive(Debug, Default)]
+pub struct BinlogParser {
+    pub file: String,
+    pub checksum: bool,
+    pub tables: HashMap<u64, TableMap>,
+}
+
+impl BinlogParser {
+    pub fn new(file: &str, checksum: bool) -> Self {
+        Self {
+            file: file.to_string(),
+            checksum,
+            tables: HashMap::new(),
+        }
+    }
+
+    /// parse one event (without the leading OK byte of the packet),
+    /// return the row changes of a rows event, and nothing for the other events
+    pub fn parse(&mut self, mut event: Bytes) -> Result<Vec<RowEvent>, Error> {
+        let header = EventHeader::decode(&mut event)?;
+        if header.event_type == FORMAT_DESCRIPTION_EVENT {
+            // since 5.6.1 the event ends with the checksum algorithm and 4 bytes of checksum
+            if event.len() > CHECKSUM_LEN {
+                self.checksum = event[event.len() - 1 - CHECKSUM_LEN] == 1;
+            }
+            return Ok(vec![]);
+        }
+        if self.checksum {
+            if event.len() < CHECKSUM_LEN {
+                return Err(err_protocol!("binlog: event too short for checksum"));
+            }
+            event.truncate(event.len() - CHECKSUM_LEN);
+        }
+        match header.event_type {
+            ROTATE_EVENT => {
+                let _position = take(&mut event, 8)?;
+                let len = event.len();
+                self.file = get_str(&mut event, len)?;
+                Ok(vec![])
+            }
+            TABLE_MAP_EVENT => {
+                let table = TableMap::decode(event)?;
+                self.tables.insert(table.table_id, table);
+                Ok(vec![])
+            }
+            WRITE_ROWS_EVENT_V1 | WRITE_ROWS_EVENT => {
+                self.decode_rows(&header, event, RowOp::Insert)
+            }
+            UPDATE_ROWS_EVENT_V1 | UPDATE_ROWS_EVENT => {
+                self.decode_rows(&header, event, RowOp::Update)
+            }
+            DELETE_ROWS_EVENT_V1 | DELETE_ROWS_EVENT => {
+                self.decode_rows(&header, event, RowOp::Delete)
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Rows__event.html
+    fn decode_rows(
+        &self,
+        header: &EventHeader,
+        mut buf: Bytes,
+        op: RowOp,
+    ) -> Result<Vec<RowEvent>, Error> {
+        let table_id = take(&mut buf, 6)?.get_uint_le(6);
+        let _flags = take(&mut buf, 2)?;
+        if header.event_type >= WRITE_ROWS_EVENT {
+            // the length of extra data includes itself
+            let extra = take(&mut buf, 2)?.get_u16_le() as usize;
+            take(&mut buf, extra.saturating_sub(2))?;
+        }
+        let column_count = get_lenenc(&mut buf)? as usize;
+        let present = take(&mut buf, column_count.div_ceil(8))?;
+        let present_after = if op == RowOp::Update {
+            take(&mut buf, column_count.div_ceil(8))?
+        } else {
+            present.clone()
+        };
+        let table = match self.tables.get(&table_id) {
+            Some(v) => v,
+            // a dummy event, or a table we never saw the map of
+            None => return Ok(vec![]),
+        };
+        if table.columns.len() != column_count {
+            return Err(err_protocol!(
+                "binlog: rows event of {}.{} has {} columns, table map has {}",
+                table.schema,
+                table.table,
+                column_count,
+                table.columns.len()
+            ));
+        }
+        let mut rows = vec![];
+        while buf.has_remaining() {
+            let (before, after) = match op {
+                RowOp::Insert => (None, Some(table.decode_row(&mut buf, &present)?)),
+                RowOp::Delete => (Some(table.decode_row(&mut buf, &present)?), None),
+                RowOp::Update => (
+                    Some(table.decode_row(&mut buf, &present)?),
+                    Some(table.decode_row(&mut buf, &present_after)?),
+                ),
+            };
+            rows.push(RowEvent {
+                schema: table.schema.clone(),
+                table: table.table.clone(),
+                op,
+                before,
+                after,
+                file: self.file.clone(),
+                position: header.log_pos,
+                timestamp: header.timestamp,
+            });
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::binlog::event::*;
+    use crate::binlog::RowOp;
+    use bytes::Bytes;
+    use rbs::Value;
+
+    fn event(event_type: u8, body: &[u8]) -> Bytes {
+        let mut buf = vec![];
+        buf.extend(1700000000_u32.to_le_bytes());
+        buf.push(event_type);
+        buf.extend(1_u32.to_le_bytes());
+        buf.extend(((EVENT_HEADER_LEN + body.len()) as u32).to_le_bytes());
+        buf.extend(1234_u32.to_le_bytes());
+        buf.extend(0_u16.to_le_bytes());
+        buf.extend(body);
+        Bytes::from(buf)
+    }
+
+    // TABLE_MAP of `test`.`user` (id INT UNSIGNED NOT NULL, name VARCHAR(20) NULL)
+    // with binlog_row_metadata = FULL
+    fn table_map() -> Vec<u8> {
+        let mut b = vec![];
+        b.extend(&[0x6e, 0, 0, 0, 0, 0]);
+        b.extend(&[1, 0]);
+        b.push(4);
+        b.extend(b"test\0");
+        b.push(4);
+        b.extend(b"user\0");
+        b.push(2);
+        b.extend(&[0x03, 0x0f]);
+        b.push(2);
+        b.extend(&80_u16.to_le_bytes());
+        b.push(0b10);
+        // SIGNEDNESS
+        b.extend(&[1, 1, 0x80]);
+        // COLUMN_NAME
+        b.extend(&[4, 8, 2, b'i', b'd', 4, b'n', b'a', b'm', b'e']);
+        b
+    }
+
+    fn row(id: u32, name: Option<&str>) -> Vec<u8> {
+        let mut b = vec![];
+        match name {
+            Some(name) => {
+                b.push(0);
+                b.extend(id.to_le_bytes());
+                b.push(name.len() as u8);
+                b.extend(name.as_bytes());
+            }
+            None => {
+                b.push(0b10);
+                b.extend(id.to_le_bytes());
+            }
+        }
+        b
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let mut parser = BinlogParser::new("binlog.000001", false);
+        assert!(parser
+            .parse(event(TABLE_MAP_EVENT, &table_map()))
+            .unwrap()
+            .is_empty());
+
+        let mut insert = vec![0x6e, 0, 0, 0, 0, 0, 1, 0, 2, 0, 2, 0b11];
+        insert.extend(row(1, Some("a")));
+        insert.extend(row(4294967295, None));
+        let rows = parser.parse(event(WRITE_ROWS_EVENT, &insert)).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].schema, "test");
+        assert_eq!(rows[0].table, "user");
+        assert_eq!(rows[0].op, RowOp::Insert);
+        assert_eq!(rows[0].before, None);
+        assert_eq!(rows[0].position, 1234);
+        assert_eq!(rows[0].file, "binlog.000001");
+        let after = rows[0].after.clone().unwrap();
+        assert_eq!(after["id"], Value::U64(1));
+        assert_eq!(after["name"], Value::String("a".to_string()));
+        let after = rows[1].after.clone().unwrap();
+        assert_eq!(after["id"], Value::U64(4294967295));
+        assert_eq!(after["name"], Value::Null);
+
+        let mut update = vec![0x6e, 0, 0, 0, 0, 0, 1, 0, 2, 0, 2, 0b11, 0b11];
+        update.extend(row(1, Some("a")));
+        update.extend(row(1, Some("b")));
+        let rows = parser.parse(event(UPDATE_ROWS_EVENT, &update)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].op, RowOp::Update);
+        assert_eq!(
+            rows[0].before.clone().unwrap()["name"],
+            Value::String("a".to_string())
+        );
+        assert_eq!(
+            rows[0].after.clone().unwrap()["name"],
+            Value::String("b".to_string())
+        );
+
+        let mut delete = vec![0x6e, 0, 0, 0, 0, 0, 1, 0, 2, 0b11];
+        delete.extend(row(1, Some("b")));
+        let rows = parser.parse(event(DELETE_ROWS_EVENT_V1, &delete)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].op, RowOp::Delete);
+        assert_eq!(rows[0].after, None);
+        assert_eq!(rows[0].before.clone().unwrap()["id"], Value::U64(1));
+    }
+
+    #[test]
+    fn test_parse_rotate_and_checksum() {
+        let mut parser = BinlogParser::new("", true);
+        let mut body = 4_u64.to_le_bytes().to_vec();
+        body.extend(b"binlog.000002");
+        body.extend([0xde, 0xad, 0xbe, 0xef]);
+        parser.parse(event(ROTATE_EVENT, &body)).unwrap();
+        assert_eq!(parser.file, "binlog.000002");
+    }
+
+    // the events of a binlog file, after the `\xfebin` magic
+    fn file_events(file: &[u8]) -> Vec<Bytes> {
+        assert_eq!(&file[..4], b"\xfebin");
+        let mut events = vec![];
+        let mut pos = 4;
+        while pos < file.len() {
+            let size = u32::from_le_bytes(file[pos + 9..pos + 13].try_into().unwrap()) as usize;
+            events.push(Bytes::copy_from_slice(&file[pos..pos + size]));
+            pos += size;
+        }
+        events
+    }
+
+    // a binlog file laid out like MySQL 8.0 writes it with binlog_checksum = CRC32, gtid_mode = ON
+    // and binlog_row_metadata = FULL, the events of:
+    //   create table user (id int unsigned not null primary key, name varchar(32),
+    //     amount decimal(10,2), created datetime(3), status enum('active','closed'));
+    //   insert into user values (1,'tom',12.50,'2023-11-14 22:13:20.123','active'),(2,null,null,null,null);
+    //   update user set name = 'jerry', status = 'closed' where id = 1;
+    //   delete from user where id = 2;
+    //   flush binary logs;
+    #[test]
+    fn test_parse_fixture() {
+        let events = file_events(include_bytes!("../../tests/fixtures/binlog.000001"));
+        let mut parser = BinlogParser::new("binlog.000001", false);
+        let mut rows = vec![];
+        for event in events {
+            rows.extend(parser.parse(event).unwrap());
+        }
+        // FORMAT_DESCRIPTION_EVENT enables the checksum, ROTATE_EVENT changes the file
+        assert!(parser.checksum);
+        assert_eq!(parser.file, "binlog.000002");
+        assert_eq!(rows.len(), 4);
+
+        let tom = rows[0].after.clone().unwrap();
+        assert_eq!(rows[0].op, RowOp::Insert);
+        assert_eq!(rows[0].schema, "test");
+        assert_eq!(rows[0].table, "user");
+        assert_eq!(rows[0].timestamp, 1700000000);
+        assert_eq!(tom["id"], Value::U64(1));
+        assert_eq!(tom["name"], Value::String("tom".to_string()));
+        assert_eq!(
+            tom["amount"],
+            Value::Ext("Decimal", Box::new(Value::String("12.50".to_string())))
+        );
+        assert_eq!(
+            tom["created"],
+            Value::Ext(
+                "DateTime",
+                Box::new(Value::String("2023-11-14 22:13:20.123".to_string()))
+            )
+        );
+        assert_eq!(
+            tom["status"],
+            Value::Ext("Enum", Box::new(Value::String("active".to_string())))
+        );
+        let empty = rows[1].after.clone().unwrap();
+        assert_eq!(empty["id"], Value::U64(2));
+        assert_eq!(empty["name"], Value::Null);
+        assert_eq!(empty["created"], Value::Null);
+
+        assert_eq!(rows[2].op, RowOp::Update);
+        assert_eq!(rows[2].before.clone().unwrap(), tom);
+        let jerry = rows[2].after.clone().unwrap();
+        assert_eq!(jerry["name"], Value::String("jerry".to_string()));
+        assert_eq!(
+            jerry["status"],
+            Value::Ext("Enum", Box::new(Value::String("closed".to_string())))
+        );
+
+        assert_eq!(rows[3].op, RowOp::Delete);
+        assert_eq!(rows[3].before.clone().unwrap(), empty);
+        assert_eq!(rows[3].after, None);
+        // the position to resume from is the end of the rows event
+        assert!(rows[0].position < rows[2].position);
+        assert!(rows[2].position < rows[3].position);
+    }
+
+    #[test]
+    fn test_parse_unknown_table() {
+        let mut parser = BinlogParser::new("binlog.000001", false);
+        let mut insert = vec![0x6e, 0, 0, 0, 0, 0, 1, 0, 2, 0, 2, 0b11];
+        insert.extend(row(1, Some("a")));
+        assert!(parser
+            .parse(event(WRITE_ROWS_EVENT, &insert))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,586 @@
+use crate::binlog::event::TableColumn;
+use bytes::{Buf, Bytes};
+use rbdc::{err_protocol, Error};
+use rbs::Value;
+use std::fmt::Write;
+
+// column types of the binlog, some of them never appear in a result set
+// https://dev.mysql.com/doc/dev/mysql-server/latest/field__types_8h.html
+pub const DECIMAL: u8 = 0x00;
+pub const TINY: u8 = 0x01;
+pub const SHORT: u8 = 0x02;
+pub const LONG: u8 = 0x03;
+pub const FLOAT: u8 = 0x04;
+pub const DOUBLE: u8 = 0x05;
+pub const NULL: u8 = 0x06;
+pub const TIMESTAMP: u8 = 0x07;
+pub const LONGLONG: u8 = 0x08;
+pub const INT24: u8 = 0x09;
+pub const DATE: u8 = 0x0a;
+pub const TIME: u8 = 0x0b;
+pub const DATETIME: u8 = 0x0c;
+pub const YEAR: u8 = 0x0d;
+pub const NEWDATE: u8 = 0x0e;
+pub const VARCHAR: u8 = 0x0f;
+pub const BIT: u8 = 0x10;
+pub const TIMESTAMP2: u8 = 0x11;
+pub const DATETIME2: u8 = 0x12;
+pub const TIME2: u8 = 0x13;
+pub const JSON: u8 = 0xf5;
+pub const NEWDECIMAL: u8 = 0xf6;
+pub const ENUM: u8 = 0xf7;
+pub const SET: u8 = 0xf8;
+pub const TINY_BLOB: u8 = 0xf9;
+pub const MEDIUM_BLOB: u8 = 0xfa;
+pub const LONG_BLOB: u8 = 0xfb;
+pub const BLOB: u8 = 0xfc;
+pub const VAR_STRING: u8 = 0xfd;
+pub const STRING: u8 = 0xfe;
+pub const GEOMETRY: u8 = 0xff;
+
+pub(crate) fn take(buf: &mut Bytes, n: usize) -> Result<Bytes, Error> {
+    if buf.remaining() < n {
+        return Err(err_protocol!(
+            "binlog: unexpected end of event, need {} bytes but {} left",
+            n,
+            buf.remaining()
+        ));
+    }
+    Ok(buf.split_to(n))
+}
+
+fn uint_le(buf: &mut Bytes, n: usize) -> Result<u64, Error> {
+    Ok(take(buf, n)?.get_uint_le(n))
+}
+
+fn uint_be(buf: &mut Bytes, n: usize) -> Result<u64, Error> {
+    Ok(take(buf, n)?.get_uint(n))
+}
+
+/// the real type and metadata of a STRING column, which may be an ENUM or a SET
+pub fn string_real_type(meta: u16) -> (u8, u16) {
+    if meta >= 256 {
+        let b0 = (meta >> 8) as u8;
+        let b1 = meta & 0xff;
+        if b0 & 0x30 != 0x30 {
+            // the length of a CHAR longer than 255 is stored in the bits of the type
+            (b0 | 0x30, b1 | ((((b0 & 0x30) ^ 0x30) as u16) << 4))
+        } else {
+            (b0, b1)
+        }
+    } else {
+        (STRING, meta)
+    }
+}
+
+fn string_or_binary(data: Bytes) -> Value {
+    match String::from_utf8(data.to_vec()) {
+        Ok(s) => Value::String(s),
+        Err(e) => Value::Binary(e.into_bytes()),
+    }
+}
+
+/// decode the value of a column in a rows event
+pub fn decode_value(buf: &mut Bytes, column: &TableColumn) -> Result<Value, Error> {
+    let meta = column.meta;
+    let mut t = column.r#type;
+    let mut length = meta & 0xff;
+    if t == STRING {
+        (t, length) = string_real_type(meta);
+    }
+    Ok(match t {
+        NULL => Value::Null,
+        TINY => {
+            let v = uint_le(buf, 1)?;
+            if column.unsigned {
+                Value::U64(v)
+            } else {
+                Value::I32(v as u8 as i8 as i32)
+            }
+        }
+        SHORT => {
+            let v = uint_le(buf, 2)?;
+            if column.unsigned {
+                Value::U64(v)
+            } else {
+                Value::I32(v as u16 as i16 as i32)
+            }
+        }
+        INT24 => {
+            let v = uint_le(buf, 3)?;
+            if column.unsigned {
+                Value::U64(v)
+            } else {
+                // sign extend 24 bits
+                Value::I32(((v as u32) << 8) as i32 >> 8)
+            }
+        }
+        LONG => {
+            let v = uint_le(buf, 4)?;
+            if column.unsigned {
+                Value::U64(v)
+            } else {
+                Value::I64(v as u32 as i32 as i64)
+            }
+        }
+        LONGLONG => {
+            let v = uint_le(buf, 8)?;
+            if column.unsigned {
+                Value::U64(v)
+            } else {
+                Value::I64(v as i64)
+            }
+        }
+        FLOAT => Value::F32(take(buf, 4)?.get_f32_le()),
+        DOUBLE => Value::F64(take(buf, 8)?.get_f64_le()),
+        NEWDECIMAL => Value::Ext(
+            "Decimal",
+            Box::new(Value::String(decode_decimal(
+                buf,
+                (meta >> 8) as usize,
+                (meta & 0xff) as usize,
+            )?)),
+        ),
+        YEAR => {
+            let v = uint_le(buf, 1)?;
+            let year = if v == 0 { 0 } else { v + 1900 };
+            Value::Ext("Year", Box::new(Value::String(format!("{:04}", year))))
+        }
+        DATE | NEWDATE => {
+            let v = uint_le(buf, 3)?;
+            Value::Ext(
+                "Date",
+                Box::new(Value::String(format!(
+                    "{:04}-{:02}-{:02}",
+                    v >> 9,
+                    (v >> 5) & 0x0f,
+                    v & 0x1f
+                ))),
+            )
+        }
+        TIME => {
+            let v = uint_le(buf, 3)?;
+            Value::Ext(
+                "Time",
+                Box::new(Value::String(format!(
+                    "{:02}:{:02}:{:02}",
+                    v / 10000,
+                    (v % 10000) / 100,
+                    v % 100
+                ))),
+            )
+        }
+        TIME2 => {
+            let v = uint_be(buf, 3)? as i64 - 0x800000;
+            let micros = decode_fraction(buf, meta)?;
+            let sign = if v < 0 { "-" } else { "" };
+            let v = v.unsigned_abs();
+            let mut s = format!(
+                "{}{:02}:{:02}:{:02}",
+                sign,
+                (v >> 12) & 0x3ff,
+                (v >> 6) & 0x3f,
+                v & 0x3f
+            );
+            push_fraction(&mut s, micros, meta);
+            Value::Ext("Time", Box::new(Value::String(s)))
+        }
+        DATETIME => {
+            let v = uint_le(buf, 8)?;
+            let (d, t) = (v / 1000000, v % 1000000);
+            Value::Ext(
+                "DateTime",
+                Box::new(Value::String(format!(
+                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    d / 10000,
+                    (d % 10000) / 100,
+                    d % 100,
+                    t / 10000,
+                    (t % 10000) / 100,
+                    t % 100
+                ))),
+            )
+        }
+        DATETIME2 => {
+            let v = uint_be(buf, 5)?.wrapping_sub(0x8000000000);
+            let micros = decode_fraction(buf, meta)?;
+            let ym = (v >> 22) & 0x1ffff;
+            let mut s = format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                ym / 13,
+                ym % 13,
+                (v >> 17) & 0x1f,
+                (v >> 12) & 0x1f,
+                (v >> 6) & 0x3f,
+                v & 0x3f
+            );
+            push_fraction(&mut s, micros, meta);
+            Value::Ext("DateTime", Box::new(Value::String(s)))
+        }
+        TIMESTAMP => {
+            let secs = uint_le(buf, 4)?;
+            Value::Ext("Timestamp", Box::new(Value::U64(secs * 1000)))
+        }
+        TIMESTAMP2 => {
+            let secs = uint_be(buf, 4)?;
+            let micros = decode_fraction(buf, meta)?;
+            Value::Ext("Timestamp", Box::new(Value::U64(secs * 1000 + micros / 1000)))
+        }
+        BIT => {
+            let bits = (meta >> 8) * 8 + (meta & 0xff);
+            Value::U64(uint_be(buf, bits.div_ceil(8) as usize)?)
+        }
+        ENUM => {
+            let index = uint_le(buf, length as usize)?;
+            match &column.values {
+                // the index of an enum starts at 1, 0 is the empty string of an invalid value
+                Some(names) => Value::Ext(
+                    "Enum",
+                    Box::new(Value::String(
+                        names
+                            .get((index as usize).wrapping_sub(1))
+                            .cloned()
+                            .unwrap_or_default(),
+                    )),
+                ),
+                None => Value::Ext("Enum", Box::new(Value::U64(index))),
+            }
+        }
+        SET => {
+            let bits = uint_le(buf, length as usize)?;
+            match &column.values {
+                Some(names) => {
+                    let v: Vec<&str> = names
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| bits & (1 << i) != 0)
+                        .map(|(_, name)| name.as_str())
+                        .collect();
+                    Value::Ext("Set", Box::new(Value::String(v.join(","))))
+                }
+                None => Value::Ext("Set", Box::new(Value::U64(bits))),
+            }
+        }
+        VARCHAR | VAR_STRING | STRING => {
+            let max = if t == STRING { length } else { meta };
+            let len = uint_le(buf, if max < 256 { 1 } else { 2 })?;
+            string_or_binary(take(buf, len as usize)?)
+        }
+        BLOB | TINY_BLOB | MEDIUM_BLOB | LONG_BLOB => {
+            let len = uint_le(buf, meta as usize)?;
+            Value::Binary(take(buf, len as usize)?.to_vec())
+        }
+        GEOMETRY => {
+            let len = uint_le(buf, meta as usize)?;
+            Value::Ext("Geometry", Box::new(Value::Binary(take(buf, len as usize)?.to_vec())))
+        }
+        JSON => {
+            let len = uint_le(buf, meta as usize)?;
+            let data = take(buf, len as usize)?;
+            // an empty json value is json null
+            let s = if data.is_empty() {
+                "null".to_string()
+            } else {
+                let mut s = String::new();
+                write_json(&mut s, data[0], &data[1..])?;
+                s
+            };
+            Value::Ext("Json", Box::new(Value::String(s)))
+        }
+        _ => {
+            return Err(err_protocol!("binlog: unsupported column type 0x{:02x}", t));
+        }
+    })
+}
+
+// the fractional seconds of TIME2, DATETIME2 and TIMESTAMP2 in micro seconds
+fn decode_fraction(buf: &mut Bytes, fsp: u16) -> Result<u64, Error> {
+    Ok(match fsp {
+        1 | 2 => uint_be(buf, 1)? * 10000,
+        3 | 4 => uint_be(buf, 2)? * 100,
+        5 | 6 => uint_be(buf, 3)?,
+        _ => 0,
+    })
+}
+
+fn push_fraction(s: &mut String, micros: u64, fsp: u16) {
+    if fsp > 0 {
+        let frac = format!("{:06}", micros);
+        s.push('.');
+        s.push_str(&frac[..(fsp as usize).min(6)]);
+    }
+}
+
+/// decode the binary format of DECIMAL to its string form
+fn decode_decimal(buf: &mut Bytes, precision: usize, scale: usize) -> Result<String, Error> {
+    // bytes used by the leftover digits of a group of 9 digits
+    const DIG2BYTES: [usize; 10] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+    if scale > precision {
+        return Err(err_protocol!(
+            "binlog: invalid decimal({}, {})",
+            precision,
+            scale
+        ));
+    }
+    let intg = precision - scale;
+    let (intg0, intg0x) = (intg / 9, intg % 9);
+    let (frac0, frac0x) = (scale / 9, scale % 9);
+    let size = intg0 * 4 + DIG2BYTES[intg0x] + frac0 * 4 + DIG2BYTES[frac0x];
+    let mut data = take(buf, size)?.to_vec();
+    if data.is_empty() {
+        return Ok("0".to_string());
+    }
+    // the sign is the high bit of the first byte, negative values have every bit inverted
+    let negative = data[0] & 0x80 == 0;
+    data[0] ^= 0x80;
+    if negative {
+        for b in data.iter_mut() {
+            *b = !*b;
+        }
+    }
+    let mut data = Bytes::from(data);
+    let mut int_part = String::new();
+    if intg0x > 0 {
+        let v = data.get_uint(DIG2BYTES[intg0x]);
+        let _ = write!(int_part, "{:0width$}", v, width = intg0x);
+    }
+    for _ in 0..intg0 {
+        let _ = write!(int_part, "{:09}", data.get_u32());
+    }
+    let mut s = String::new();
+    if negative {
+        s.push('-');
+    }
+    let int_part = int_part.trim_start_matches('0');
+    s.push_str(if int_part.is_empty() { "0" } else { int_part });
+    if scale > 0 {
+        s.push('.');
+        for _ in 0..frac0 {
+            let _ = write!(s, "{:09}", data.get_u32());
+        }
+        if frac0x > 0 {
+            let v = data.get_uint(DIG2BYTES[frac0x]);
+            let _ = write!(s, "{:0width$}", v, width = frac0x);
+        }
+    }
+    Ok(s)
+}
+
+fn json_slice(data: &[u8], start: usize, len: usize) -> Result<&[u8], Error> {
+    data.get(start..start + len)
+        .ok_or_else(|| err_protocol!("binlog: json value out of bounds"))
+}
+
+fn json_uint(data: &[u8], start: usize, len: usize) -> Result<u64, Error> {
+    let mut v = 0_u64;
+    for (i, b) in json_slice(data, start, len)?.iter().enumerate() {
+        v |= (*b as u64) << (i * 8);
+    }
+    Ok(v)
+}
+
+// the variable length of a json string, 7 bits in each byte
+fn json_varlen(data: &[u8]) -> Result<(usize, usize), Error> {
+    let mut len = 0_usize;
+    for i in 0..5 {
+        let b = *data
+            .get(i)
+            .ok_or_else(|| err_protocol!("binlog: json length out of bounds"))?;
+        len |= ((b & 0x7f) as usize) << (7 * i);
+        if b & 0x80 == 0 {
+            return Ok((len, i + 1));
+        }
+    }
+    Err(err_protocol!("binlog: invalid json length"))
+}
+
+fn write_json_string(out: &mut String, data: &[u8]) {
+    out.push('"');
+    for c in String::from_utf8_lossy(data).chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// write the binary format of a JSON column as json text
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/json__binary_8h.html
+fn write_json(out: &mut String, t: u8, data: &[u8]) -> Result<(), Error> {
+    match t {
+        // small and large object, small and large array
+        0x00..=0x03 => {
+            let large = t == 0x01 || t == 0x03;
+            let is_object = t <= 0x01;
+            let size = if large { 4 } else { 2 };
+            let count = json_uint(data, 0, size)? as usize;
+            let key_entry = if is_object { size + 2 } else { 0 };
+            let value_entry = 1 + size;
+            out.push(if is_object { '{' } else { '[' });
+            for i in 0..count {
+                if i != 0 {
+                    out.push(',');
+                }
+                if is_object {
+                    let entry = 2 * size + i * key_entry;
+                    let offset = json_uint(data, entry, size)? as usize;
+                    let len = json_uint(data, entry + size, 2)? as usize;
+                    write_json_string(out, json_slice(data, offset, len)?);
+                    out.push(':');
+                }
+                let entry = 2 * size + count * key_entry + i * value_entry;
+                let value_type = json_slice(data, entry, 1)?[0];
+                let inline = match value_type {
+                    0x04..=0x06 => true,
+                    0x07 | 0x08 => large,
+                    _ => false,
+                };
+                if inline {
+                    write_json(out, value_type, json_slice(data, entry + 1, size)?)?;
+                } else {
+                    let offset = json_uint(data, entry + 1, size)? as usize;
+                    let value = data
+                        .get(offset..)
+                        .ok_or_else(|| err_protocol!("binlog: json value out of bounds"))?;
+                    write_json(out, value_type, value)?;
+                }
+            }
+            out.push(if is_object { '}' } else { ']' });
+        }
+        // literal
+        0x04 => match json_slice(data, 0, 1)?[0] {
+            0x00 => out.push_str("null"),
+            0x01 => out.push_str("true"),
+            _ => out.push_str("false"),
+        },
+        0x05 => {
+            let _ = write!(out, "{}", json_uint(data, 0, 2)? as u16 as i16);
+        }
+        0x06 => {
+            let _ = write!(out, "{}", json_uint(data, 0, 2)?);
+        }
+        0x07 => {
+            let _ = write!(out, "{}", json_uint(data, 0, 4)? as u32 as i32);
+        }
+        0x08 => {
+            let _ = write!(out, "{}", json_uint(data, 0, 4)?);
+        }
+        0x09 => {
+            let _ = write!(out, "{}", json_uint(data, 0, 8)? as i64);
+        }
+        0x0a => {
+            let _ = write!(out, "{}", json_uint(data, 0, 8)?);
+        }
+        0x0b => {
+            let _ = write!(out, "{}", f64::from_bits(json_uint(data, 0, 8)?));
+        }
+        // utf8 string
+        0x0c => {
+            let (len, n) = json_varlen(data)?;
+            write_json_string(out, json_slice(data, n, len)?);
+        }
+        // opaque value (decimal, date, time...), written as a string of its raw bytes
+        0x0f => {
+            let (len, n) = json_varlen(data.get(1..).unwrap_or_default())?;
+            write_json_string(out, json_slice(data, 1 + n, len)?);
+        }
+        _ => {
+            return Err(err_protocol!("binlog: unknown json type 0x{:02x}", t));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::binlog::event::TableColumn;
+    use crate::binlog::value::{decode_value, DATETIME2, JSON, NEWDECIMAL, STRING};
+    use bytes::Bytes;
+    use rbs::Value;
+
+    fn column(t: u8, meta: u16) -> TableColumn {
+        TableColumn {
+            r#type: t,
+            meta,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decode_decimal() {
+        // decimal(10,2) 1234.56 and -1234.56
+        let c = column(NEWDECIMAL, (10 << 8) | 2);
+        let mut buf = Bytes::from_static(&[0x80, 0x00, 0x04, 0xd2, 0x38]);
+        assert_eq!(
+            decode_value(&mut buf, &c).unwrap(),
+            Value::Ext("Decimal", Box::new(Value::String("1234.56".to_string())))
+        );
+        let mut buf = Bytes::from_static(&[0x7f, 0xff, 0xfb, 0x2d, 0xc7]);
+        assert_eq!(
+            decode_value(&mut buf, &c).unwrap(),
+            Value::Ext("Decimal", Box::new(Value::String("-1234.56".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_decode_datetime2() {
+        // 2023-01-02 03:04:05
+        let ym: u64 = 2023 * 13 + 1;
+        let v: u64 = 0x8000000000 | (ym << 22) | (2 << 17) | (3 << 12) | (4 << 6) | 5;
+        let mut buf = Bytes::from(v.to_be_bytes()[3..].to_vec());
+        assert_eq!(
+            decode_value(&mut buf, &column(DATETIME2, 0)).unwrap(),
+            Value::Ext(
+                "DateTime",
+                Box::new(Value::String("2023-01-02 03:04:05".to_string()))
+            )
+        );
+    }
+
+    #[test]
+    fn test_decode_enum() {
+        // ENUM('a','b') stored in 1 byte
+        let mut c = column(STRING, (0xf7 << 8) | 1);
+        let mut buf = Bytes::from_static(&[2]);
+        assert_eq!(
+            decode_value(&mut buf, &c).unwrap(),
+            Value::Ext("Enum", Box::new(Value::U64(2)))
+        );
+        c.values = Some(vec!["a".to_string(), "b".to_string()]);
+        let mut buf = Bytes::from_static(&[2]);
+        assert_eq!(
+            decode_value(&mut buf, &c).unwrap(),
+            Value::Ext("Enum", Box::new(Value::String("b".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_decode_json() {
+        // {"a":[1,"x"]} in the binary format, small object with one key
+        let mut data = vec![0x00];
+        // count, size
+        data.extend([1, 0, 24, 0]);
+        // key entry: offset 11, length 1
+        data.extend([11, 0, 1, 0]);
+        // value entry: small array at offset 12
+        data.extend([0x02, 12, 0]);
+        data.push(b'a');
+        // array: count 2, size 12, int16 1 inline, string at offset 10
+        data.extend([2, 0, 12, 0, 0x05, 1, 0, 0x0c, 10, 0, 1, b'x']);
+        let mut buf = vec![data.len() as u8];
+        buf.extend(data);
+        let mut buf = Bytes::from(buf);
+        assert_eq!(
+            decode_value(&mut buf, &column(JSON, 1)).unwrap(),
+            Value::Ext("Json", Box::new(Value::String(r#"{"a":[1,"x"]}"#.to_string())))
+        );
+    }
+}
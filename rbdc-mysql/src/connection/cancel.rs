@@ -0,0 +1,31 @@
+use crate::connection::MySqlConnection;
+use crate::options::MySqlConnectOptions;
+use futures_core::future::BoxFuture;
+use rbdc::db::{CancelHandle, Connection};
+use rbdc::Error;
+use std::sync::Arc;
+
+/// kills the running statement of a connection with `KILL QUERY` over a new connection
+#[derive(Clone, Debug)]
+pub struct MySqlCancelHandle {
+    pub(crate) options: Arc<MySqlConnectOptions>,
+    pub(crate) connection_id: u32,
+}
+
+impl MySqlCancelHandle {
+    pub async fn kill_query(&self) -> Result<(), Error> {
+        let mut conn = MySqlConnection::establish(&self.options).await?;
+        let result = conn
+            .exec(&format!("KILL QUERY {}", self.connection_id), vec![])
+            .await;
+        let _ = conn.close().await;
+        result.map(|_| ())
+    }
+}
+
+impl CancelHandle for MySqlCancelHandle {
+    fn cancel(&self) -> BoxFuture<'static, Result<(), Error>> {
+        let handle = self.clone();
+        Box::pin(async move { handle.kill_query().await })
+    }
+}
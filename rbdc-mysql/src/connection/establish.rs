@@ -1,4 +1,6 @@
-use crate::connection::{tls, DropBox, MySqlConnection, MySqlStream, MAX_PACKET_SIZE};
+use crate::connection::{
+    tls, DropBox, MySqlCancelHandle, MySqlConnection, MySqlStream, MAX_PACKET_SIZE,
+};
 use crate::options::{MySqlConnectOptions, MySqlSslMode};
 use crate::protocol::auth::AuthPlugin;
 use crate::protocol::connect::{
@@ -8,9 +10,10 @@ use crate::protocol::Capabilities;
 use bytes::buf::Buf;
 use bytes::Bytes;
 use rbdc::{err_protocol, Error};
+use std::sync::Arc;
 
 impl MySqlConnection {
-    pub(crate) async fn establish(options: &MySqlConnectOptions) -> Result<Self, Error> {
+    pub async fn establish(options: &MySqlConnectOptions) -> Result<Self, Error> {
         let mut stream: MySqlStream = MySqlStream::connect(options).await?;
 
         // https://dev.mysql.com/doc/dev/mysql-server/8.0.30/page_protocol_connection_phase.html
@@ -141,6 +144,12 @@ impl MySqlConnection {
                 inner: Some(stream),
             },
             cache_statement: rbdc::common::StatementCache::new(options.statement_cache_capacity),
+            local_infile_allow: options.local_infile_allow.clone(),
+            local_infile_handler: options.local_infile_handler.clone(),
+            cancel: MySqlCancelHandle {
+                options: Arc::new(options.clone()),
+                connection_id: handshake.connection_id,
+            },
         })
     }
 }
@@ -2,7 +2,7 @@ use super::MySqlStream;
 use crate::connection::stream::Waiting;
 use crate::connection::MySqlConnection;
 use crate::io::MySqlBufExt;
-use crate::protocol::response::Status;
+use crate::protocol::response::{LocalInfileRequest, Status};
 use crate::protocol::statement::{
     BinaryRow, Execute as StatementExecute, Prepare, PrepareOk, StmtClose,
 };
@@ -113,7 +113,10 @@ impl MySqlConnection {
                     })
                     .await?;
 
-                (metadata.column_names, MySqlValueFormat::Binary, false)
+                // a prepared `CALL` has no columns until it is executed,
+                // so its result sets need the full metadata
+                let needs_metadata = metadata.columns.is_empty();
+                (metadata.column_names, MySqlValueFormat::Binary, needs_metadata)
             } else {
                 // https://dev.mysql.com/doc/internals/en/com-query.html
                 self.stream.send_packet(Query(sql)).await?;
@@ -123,18 +126,28 @@ impl MySqlConnection {
 
             loop {
                 // query response is a meta-packet which may be one of:
-                //  Ok, Err, ResultSet, or (unhandled) LocalInfileRequest
+                //  Ok, Err, ResultSet, or LocalInfileRequest
                 let mut packet = self.stream.recv_packet().await?;
 
+                if packet[0] == 0xfb {
+                    // the server asks for the contents of a LOAD DATA LOCAL INFILE,
+                    // after sending it the next packet is OK or ERR
+                    let request: LocalInfileRequest = packet.decode()?;
+                    self.send_local_infile(&request.filename).await?;
+                    continue;
+                }
+
                 if packet[0] == 0x00 || packet[0] == 0xff {
                     // first packet in a query response is OK or ERR
                     // this indicates either a successful query with no rows at all or a failed query
                     let ok = packet.ok()?;
+                    self.stream.status = ok.status;
 
                     let rows_affected = ok.affected_rows;
                     let done = MySqlQueryResult {
                         rows_affected,
                         last_insert_id: ok.last_insert_id,
+                        result_set: false,
                     };
 
                     r#yield!(Either::Left(done));
@@ -169,10 +182,12 @@ impl MySqlConnection {
 
                     if packet[0] == 0xfe && packet.len() < 9 {
                         let eof = packet.eof(self.stream.capabilities)?;
+                        self.stream.status = eof.status;
 
                         r#yield!(Either::Left(MySqlQueryResult {
                             rows_affected: 0,
                             last_insert_id: 0,
+                            result_set: true,
                         }));
 
                         if eof.status.contains(Status::SERVER_MORE_RESULTS_EXISTS) {
@@ -1,3 +1,4 @@
+use crate::local_infile::LocalInfileHandler;
 use crate::protocol::statement::StmtClose;
 use crate::protocol::text::{Ping, Quit};
 use crate::stmt::MySqlStatementMetadata;
@@ -6,21 +7,25 @@ use futures_core::future::BoxFuture;
 use futures_core::stream::BoxStream;
 use futures_util::{FutureExt, StreamExt, TryStreamExt};
 use rbdc::common::StatementCache;
-use rbdc::db::{Connection, ExecResult, Row};
+use rbdc::db::{CancelHandle, Connection, ExecResult, Row};
 use rbdc::Error;
+use rbs::value::map::ValueMap;
 use rbs::Value;
 use std::fmt::{self, Debug, Formatter};
 use std::ops::{Deref, DerefMut};
 
 mod auth;
+mod cancel;
 mod establish;
 mod executor;
 mod stream;
 mod tls;
 
+use crate::protocol::response::Status;
 use crate::query::MysqlQuery;
 use crate::query_result::MySqlQueryResult;
 use crate::row::MySqlRow;
+pub use cancel::MySqlCancelHandle;
 pub(crate) use stream::MySqlStream;
 
 const MAX_PACKET_SIZE: u32 = 1024;
@@ -33,6 +38,12 @@ pub struct MySqlConnection {
     pub stream: DropBox<MySqlStream>,
     // cache by query string to the statement id and metadata
     pub cache_statement: StatementCache<(u32, MySqlStatementMetadata)>,
+    // file names the server may request with LOAD DATA LOCAL INFILE
+    pub(crate) local_infile_allow: Vec<String>,
+    pub(crate) local_infile_handler: Option<LocalInfileHandler>,
+    // the thread id of this connection and the options to open another one,
+    // used to kill the running statement
+    pub(crate) cancel: MySqlCancelHandle,
 }
 
 impl Debug for MySqlConnection {
@@ -148,6 +159,64 @@ impl Connection for MySqlConnection {
         })
     }
 
+    fn get_result_sets(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+    ) -> BoxFuture<Result<(Vec<Vec<Value>>, Vec<ExecResult>), Error>> {
+        let sql = sql.to_owned();
+        Box::pin(async move {
+            let mut many = {
+                if params.is_empty() {
+                    self.fetch_many(MysqlQuery {
+                        statement: Either::Left(sql),
+                        arguments: params,
+                        persistent: false,
+                    })
+                } else {
+                    let stmt = self.prepare_with(&sql, &[]).await?;
+                    self.fetch_many(MysqlQuery {
+                        statement: Either::Right(stmt),
+                        arguments: params,
+                        persistent: true,
+                    })
+                }
+            };
+            let mut sets = vec![];
+            let mut results = vec![];
+            let mut rows = vec![];
+            while let Some(step) = many.try_next().await? {
+                match step {
+                    Either::Left(done) => {
+                        if done.is_result_set() {
+                            results.push(ExecResult {
+                                rows_affected: rows.len() as u64,
+                                last_insert_id: Value::Null,
+                            });
+                            sets.push(std::mem::take(&mut rows));
+                        } else {
+                            results.push(ExecResult {
+                                rows_affected: done.rows_affected,
+                                last_insert_id: done.last_insert_id.into(),
+                            });
+                        }
+                    }
+                    Either::Right(mut row) => {
+                        let md = row.meta_data();
+                        let mut m = ValueMap::with_capacity(md.column_len());
+                        for mut i in 0..md.column_len() {
+                            i = md.column_len() - i - 1;
+                            let n = md.column_name(i);
+                            m.insert(Value::String(n), row.get(i)?);
+                        }
+                        rows.push(Value::Map(m));
+                    }
+                }
+            }
+            Ok((sets, results))
+        })
+    }
+
     fn exec(&mut self, sql: &str, params: Vec<Value>) -> BoxFuture<Result<ExecResult, Error>> {
         let sql = sql.to_owned();
         Box::pin(async move {
@@ -192,4 +261,16 @@ impl Connection for MySqlConnection {
         let c = self.do_ping();
         Box::pin(async move { c.await })
     }
+
+    fn reset(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        self.clear_cached_statements()
+    }
+
+    fn in_transaction(&self) -> Option<bool> {
+        Some(self.stream.status.contains(Status::SERVER_STATUS_IN_TRANS))
+    }
+
+    fn cancel_handle(&self) -> Option<Box<dyn CancelHandle>> {
+        Some(Box::new(self.cancel.clone()))
+    }
 }
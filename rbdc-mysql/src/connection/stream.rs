@@ -1,7 +1,7 @@
 use std::collections::VecDeque;
 use std::ops::{Deref, DerefMut};
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, Bytes, BytesMut};
 
 use crate::collation::{CharSet, Collation};
 use crate::error::MySqlDatabaseError;
@@ -13,6 +13,9 @@ use rbdc::io::{BufStream, Decode, Encode};
 use rbdc::net::{MaybeTlsStream, Socket};
 use rbdc::{err_protocol, Error};
 
+// the max payload of a packet, a larger payload continues in the next packet
+const MAX_PACKET_SIZE: usize = 0xFF_FF_FF;
+
 pub struct MySqlStream {
     stream: BufStream<MaybeTlsStream<Socket>>,
     pub(crate) server_version: (u16, u16, u16),
@@ -21,6 +24,8 @@ pub struct MySqlStream {
     pub(crate) waiting: VecDeque<Waiting>,
     pub(crate) charset: CharSet,
     pub(crate) collation: Collation,
+    // server status of the last OK/EOF packet
+    pub(crate) status: Status,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -33,7 +38,7 @@ pub(crate) enum Waiting {
 }
 
 impl MySqlStream {
-    pub(super) async fn connect(options: &MySqlConnectOptions) -> Result<Self, Error> {
+    pub(crate) async fn connect(options: &MySqlConnectOptions) -> Result<Self, Error> {
         let charset: CharSet = options.charset.parse()?;
         let collation: Collation = options
             .collation
@@ -64,6 +69,10 @@ impl MySqlStream {
             capabilities |= Capabilities::CONNECT_WITH_DB;
         }
 
+        if !options.local_infile_allow.is_empty() && options.local_infile_handler.is_some() {
+            capabilities |= Capabilities::LOCAL_FILES;
+        }
+
         Ok(Self {
             waiting: VecDeque::new(),
             capabilities,
@@ -71,6 +80,7 @@ impl MySqlStream {
             sequence_id: 0,
             collation,
             charset,
+            status: Status::empty(),
             stream: BufStream::new(MaybeTlsStream::Raw(socket)),
         })
     }
@@ -86,6 +96,7 @@ impl MySqlStream {
 
                 if !packet.is_empty() && packet[0] == 0xfe && packet.len() < 9 {
                     let eof = packet.eof(self.capabilities)?;
+                    self.status = eof.status;
 
                     if eof.status.contains(Status::SERVER_MORE_RESULTS_EXISTS) {
                         *self.waiting.front_mut().unwrap() = Waiting::Result;
@@ -100,6 +111,7 @@ impl MySqlStream {
 
                 if !packet.is_empty() && (packet[0] == 0x00 || packet[0] == 0xff) {
                     let ok = packet.ok()?;
+                    self.status = ok.status;
 
                     if !ok.status.contains(Status::SERVER_MORE_RESULTS_EXISTS) {
                         self.waiting.pop_front();
@@ -137,17 +149,24 @@ impl MySqlStream {
         // https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_basic_packets.html
         // https://mariadb.com/kb/en/library/0-packet/#standard-packet
 
-        let mut header: Bytes = self.stream.read(4).await?;
-
-        let packet_size = header.get_uint_le(3) as usize;
-        let sequence_id = header.get_u8();
-
-        self.sequence_id = sequence_id.wrapping_add(1);
-
-        let payload: Bytes = self.stream.read(packet_size).await?;
+        let (packet_size, mut payload) = self.recv_packet_part().await?;
+
+        // a payload of 0xFF_FF_FF bytes is continued in the next packet,
+        // a row (or binlog event) larger than 16MB is split over several packets
+        // https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_basic_packets.html#sect_protocol_basic_packets_sending_mt_16mb
+        if packet_size == MAX_PACKET_SIZE {
+            let mut joined = BytesMut::from(&payload[..]);
+            loop {
+                let (packet_size, part) = self.recv_packet_part().await?;
+                joined.extend_from_slice(&part);
+                if packet_size < MAX_PACKET_SIZE {
+                    break;
+                }
+            }
+            payload = joined.freeze();
+        }
 
         // TODO: packet compression
-        // TODO: packet joining
 
         if payload
             .get(0)
@@ -166,6 +185,19 @@ impl MySqlStream {
         Ok(Packet(payload))
     }
 
+    // read the header and payload of one packet
+    async fn recv_packet_part(&mut self) -> Result<(usize, Bytes), Error> {
+        let mut header: Bytes = self.stream.read(4).await?;
+
+        let packet_size = header.get_uint_le(3) as usize;
+        let sequence_id = header.get_u8();
+
+        self.sequence_id = sequence_id.wrapping_add(1);
+
+        let payload: Bytes = self.stream.read(packet_size).await?;
+        Ok((packet_size, payload))
+    }
+
     pub(crate) async fn recv<'de, T>(&mut self) -> Result<T, Error>
     where
         T: Decode<'de, Capabilities>,
@@ -174,7 +206,9 @@ impl MySqlStream {
     }
 
     pub(crate) async fn recv_ok(&mut self) -> Result<OkPacket, Error> {
-        self.recv_packet().await?.ok()
+        let ok = self.recv_packet().await?.ok()?;
+        self.status = ok.status;
+        Ok(ok)
     }
 
     pub(crate) async fn maybe_recv_eof(&mut self) -> Result<Option<EofPacket>, Error> {
@@ -211,3 +245,58 @@ impl DerefMut for MySqlStream {
         &mut self.stream
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::connection::stream::{MySqlStream, MAX_PACKET_SIZE};
+    use crate::options::MySqlConnectOptions;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    fn packet(sequence_id: u8, payload: &[u8]) -> Vec<u8> {
+        let mut buf = (payload.len() as u32).to_le_bytes()[..3].to_vec();
+        buf.push(sequence_id);
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_recv_packet_joining() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            // a payload of 2 * 0xFF_FF_FF + 3 bytes is sent as 3 packets
+            let mut payload = vec![0u8; MAX_PACKET_SIZE * 2 + 3];
+            payload[MAX_PACKET_SIZE] = 1;
+            payload[MAX_PACKET_SIZE * 2] = 2;
+            for (i, part) in payload.chunks(MAX_PACKET_SIZE).enumerate() {
+                socket.write_all(&packet(i as u8, part)).await.unwrap();
+            }
+            // a payload of exactly 0xFF_FF_FF bytes ends with an empty packet
+            socket
+                .write_all(&packet(0, &vec![3u8; MAX_PACKET_SIZE]))
+                .await
+                .unwrap();
+            socket.write_all(&packet(1, &[])).await.unwrap();
+            socket.write_all(&packet(0, &[4])).await.unwrap();
+            socket.flush().await.unwrap();
+        });
+        let options = MySqlConnectOptions::new().host("127.0.0.1").port(port);
+        let mut stream = MySqlStream::connect(&options).await.unwrap();
+
+        let payload = stream.recv_packet().await.unwrap().0;
+        assert_eq!(payload.len(), MAX_PACKET_SIZE * 2 + 3);
+        assert_eq!(payload[MAX_PACKET_SIZE], 1);
+        assert_eq!(payload[MAX_PACKET_SIZE * 2], 2);
+        assert_eq!(stream.sequence_id, 3);
+
+        let payload = stream.recv_packet().await.unwrap().0;
+        assert_eq!(payload.len(), MAX_PACKET_SIZE);
+        assert_eq!(payload[MAX_PACKET_SIZE - 1], 3);
+
+        let payload = stream.recv_packet().await.unwrap().0;
+        assert_eq!(&payload[..], &[4]);
+        server.await.unwrap();
+    }
+}
@@ -34,6 +34,11 @@ impl Driver for MysqlDriver {
     fn default_option(&self) -> Box<dyn ConnectOptions> {
         Box::new(MySqlConnectOptions::default())
     }
+
+    /// the count of parameters in `COM_STMT_PREPARE_OK` is a u16
+    fn max_params(&self) -> usize {
+        u16::MAX as usize
+    }
 }
 
 impl Placeholder for MysqlDriver {
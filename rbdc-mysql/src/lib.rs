@@ -4,11 +4,13 @@ pub mod meta_data;
 pub mod result_set;
 pub mod stmt;
 
+pub mod binlog;
 pub mod collation;
 pub mod connection;
 pub mod describe;
 pub mod error;
 pub mod io;
+pub mod local_infile;
 pub mod options;
 pub mod protocol;
 pub mod query;
@@ -0,0 +1,300 @@
+//! `LOAD DATA LOCAL INFILE` from memory.
+//!
+//! When the server asks for a local file, the driver never opens the filesystem.
+//! The file name must be in the allow-list of [`MySqlConnectOptions`](crate::options::MySqlConnectOptions),
+//! and the bytes come from the [`LocalInfile`] made by the handler of the options.
+//!
+//! ```rust
+//! use rbdc_mysql::local_infile::LocalInfile;
+//! use rbdc_mysql::options::MySqlConnectOptions;
+//! use rbs::Value;
+//!
+//! let options = MySqlConnectOptions::new()
+//!     .allow_local_infile("users.csv")
+//!     .local_infile_handler(|_name| {
+//!         Ok(LocalInfile::rows(vec![
+//!             vec![Value::I64(1), Value::String("alice".to_string())],
+//!             vec![Value::I64(2), Value::Null],
+//!         ]))
+//!     });
+//! // then exec "LOAD DATA LOCAL INFILE 'users.csv' INTO TABLE users"
+//! ```
+use crate::connection::MySqlConnection;
+use crate::protocol::response::Status;
+use futures_util::io::{AsyncRead, AsyncReadExt};
+use rbdc::Error;
+use rbs::Value;
+use std::fmt::{Debug, Formatter};
+use std::pin::Pin;
+use std::sync::Arc;
+
+// size of the data sent in one packet
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// the contents of a file requested with `LOAD DATA LOCAL INFILE`
+pub enum LocalInfile {
+    /// send the bytes of a reader as they are
+    Reader(Pin<Box<dyn AsyncRead + Send>>),
+    /// send rows in the default `LOAD DATA` format:
+    /// fields terminated by `\t`, lines terminated by `\n`, escaped by `\\` and `\N` for null
+    Rows(Box<dyn Iterator<Item = Vec<Value>> + Send>),
+}
+
+impl LocalInfile {
+    pub fn reader<R: AsyncRead + Send + 'static>(reader: R) -> Self {
+        Self::Reader(Box::pin(reader))
+    }
+
+    pub fn rows<I>(rows: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<Value>>,
+        I::IntoIter: Send + 'static,
+    {
+        Self::Rows(Box::new(rows.into_iter()))
+    }
+}
+
+impl Debug for LocalInfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalInfile::Reader(_) => f.write_str("LocalInfile::Reader"),
+            LocalInfile::Rows(_) => f.write_str("LocalInfile::Rows"),
+        }
+    }
+}
+
+pub type LocalInfileFn = dyn Fn(&str) -> Result<LocalInfile, Error> + Send + Sync;
+
+/// makes the [`LocalInfile`] for the file name requested by the server
+#[derive(Clone)]
+pub struct LocalInfileHandler(pub Arc<LocalInfileFn>);
+
+impl Debug for LocalInfileHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LocalInfileHandler")
+    }
+}
+
+/// append one row in the default `LOAD DATA` format
+pub fn encode_row(row: &[Value], buf: &mut Vec<u8>) {
+    for (i, v) in row.iter().enumerate() {
+        if i != 0 {
+            buf.push(b'\t');
+        }
+        encode_field(v, buf);
+    }
+    buf.push(b'\n');
+}
+
+fn encode_field(v: &Value, buf: &mut Vec<u8>) {
+    match v {
+        Value::Null => buf.extend_from_slice(b"\\N"),
+        Value::Bool(b) => buf.push(if *b { b'1' } else { b'0' }),
+        Value::String(s) => escape(s.as_bytes(), buf),
+        Value::Binary(b) => escape(b, buf),
+        Value::Ext(_, v) => encode_field(v, buf),
+        Value::Array(_) | Value::Map(_) => escape(v.to_string().as_bytes(), buf),
+        _ => buf.extend_from_slice(v.to_string().as_bytes()),
+    }
+}
+
+fn escape(data: &[u8], buf: &mut Vec<u8>) {
+    for b in data {
+        match b {
+            b'\\' => buf.extend_from_slice(b"\\\\"),
+            b'\t' => buf.extend_from_slice(b"\\t"),
+            b'\n' => buf.extend_from_slice(b"\\n"),
+            b'\r' => buf.extend_from_slice(b"\\r"),
+            0 => buf.extend_from_slice(b"\\0"),
+            _ => buf.push(*b),
+        }
+    }
+}
+
+impl MySqlConnection {
+    /// answer a LOCAL INFILE request of the server.
+    /// a file not in the allow-list (or without handler) is refused by sending an empty file.
+    /// a reader failing after some data was sent closes the connection, so nothing is loaded
+    pub(crate) async fn send_local_infile(&mut self, filename: &str) -> Result<(), Error> {
+        let source = match &self.local_infile_handler {
+            Some(handler) if self.local_infile_allow.iter().any(|x| x == filename) => {
+                Some((handler.0)(filename))
+            }
+            _ => None,
+        };
+        let source = match source {
+            Some(Ok(source)) => source,
+            Some(Err(e)) => {
+                self.refuse_local_infile().await?;
+                return Err(e);
+            }
+            None => {
+                self.refuse_local_infile().await?;
+                return Err(Error::from(format!(
+                    "LOAD DATA LOCAL INFILE '{}' is not allowed",
+                    filename
+                )));
+            }
+        };
+        match source {
+            LocalInfile::Reader(mut reader) => {
+                let mut buf = vec![0; CHUNK_SIZE];
+                let mut sent = false;
+                loop {
+                    let n = match reader.read(&mut buf).await {
+                        Ok(n) => n,
+                        Err(e) if !sent => {
+                            // end the empty file, the server waits for it before answering
+                            self.refuse_local_infile().await?;
+                            return Err(e.into());
+                        }
+                        Err(e) => {
+                            // ending the file now would load the rows sent so far,
+                            // close the connection so the server aborts the statement
+                            let _ = self.stream.shutdown().await;
+                            return Err(Error::from(format!(
+                                "LOAD DATA LOCAL INFILE '{}' aborted and the connection closed: {}",
+                                filename, e
+                            )));
+                        }
+                    };
+                    if n == 0 {
+                        break;
+                    }
+                    self.stream.write_packet(&buf[..n]);
+                    self.stream.flush().await?;
+                    sent = true;
+                }
+            }
+            LocalInfile::Rows(rows) => {
+                let mut buf = Vec::with_capacity(CHUNK_SIZE);
+                for row in rows {
+                    encode_row(&row, &mut buf);
+                    if buf.len() >= CHUNK_SIZE {
+                        // one row may be larger than the max packet size
+                        for chunk in buf.chunks(CHUNK_SIZE) {
+                            self.stream.write_packet(chunk);
+                        }
+                        self.stream.flush().await?;
+                        buf.clear();
+                    }
+                }
+                if !buf.is_empty() {
+                    self.stream.write_packet(&buf[..]);
+                }
+            }
+        }
+        // an empty packet marks the end of the file
+        self.stream.write_packet(&[][..]);
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    // send an empty packet to end the file and consume the answer of the server,
+    // so the connection is ready for the next query
+    async fn refuse_local_infile(&mut self) -> Result<(), Error> {
+        self.stream.write_packet(&[][..]);
+        self.stream.flush().await?;
+        if let Ok(packet) = self.stream.recv_packet().await {
+            let ok = packet.ok()?;
+            self.stream.status = ok.status;
+            if !ok.status.contains(Status::SERVER_MORE_RESULTS_EXISTS) {
+                self.stream.waiting.pop_front();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::connection::{DropBox, MySqlCancelHandle, MySqlConnection, MySqlStream};
+    use crate::local_infile::{encode_row, LocalInfile, LocalInfileHandler, CHUNK_SIZE};
+    use crate::options::MySqlConnectOptions;
+    use futures_util::io::AsyncRead;
+    use rbs::Value;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// fills the first read, fails the next one
+    struct FailingReader {
+        read: bool,
+    }
+
+    impl AsyncRead for FailingReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            if self.read {
+                return Poll::Ready(Err(std::io::Error::other("read failed")));
+            }
+            self.read = true;
+            buf.fill(b'1');
+            Poll::Ready(Ok(buf.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reader_fails_after_first_chunk() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            // every packet length the client sent, until it closed the connection
+            let mut packets = vec![];
+            let mut header = [0u8; 4];
+            while socket.read_exact(&mut header).await.is_ok() {
+                let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+                let mut payload = vec![0u8; len];
+                socket.read_exact(&mut payload).await.unwrap();
+                packets.push(len);
+            }
+            packets
+        });
+        let options = MySqlConnectOptions::new().host("127.0.0.1").port(port);
+        let stream = MySqlStream::connect(&options).await.unwrap();
+        let mut conn = MySqlConnection {
+            stream: DropBox {
+                inner: Some(stream),
+            },
+            cache_statement: rbdc::common::StatementCache::new(1),
+            local_infile_allow: vec!["data.csv".to_string()],
+            local_infile_handler: Some(LocalInfileHandler(Arc::new(|_| {
+                Ok(LocalInfile::reader(FailingReader { read: false }))
+            }))),
+            cancel: MySqlCancelHandle {
+                options: Arc::new(options),
+                connection_id: 0,
+            },
+        };
+        let e = conn.send_local_infile("data.csv").await.unwrap_err();
+        assert!(e.to_string().contains("read failed"));
+        // the first chunk and no empty packet ending the file
+        assert_eq!(server.await.unwrap(), vec![CHUNK_SIZE]);
+    }
+
+    #[test]
+    fn test_encode_row() {
+        let mut buf = vec![];
+        encode_row(
+            &[
+                Value::I64(1),
+                Value::String("a\tb\\c\nd".to_string()),
+                Value::Null,
+                Value::Bool(true),
+                Value::Ext("Decimal", Box::new(Value::String("1.5".to_string()))),
+            ],
+            &mut buf,
+        );
+        encode_row(&[Value::F64(2.5)], &mut buf);
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "1\ta\\tb\\\\c\\nd\t\\N\t1\t1.5\n2.5\n"
+        );
+    }
+}
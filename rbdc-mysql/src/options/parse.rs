@@ -44,6 +44,14 @@ impl FromStr for MySqlConnectOptions {
                     options = options.ssl_ca(&*value);
                 }
 
+                "ssl-cert" | "sslcert" => {
+                    options = options.ssl_client_cert(&*value);
+                }
+
+                "ssl-key" | "sslkey" => {
+                    options = options.ssl_client_key(&*value);
+                }
+
                 "charset" => {
                     options = options.charset(&*value);
                 }
@@ -64,6 +72,12 @@ impl FromStr for MySqlConnectOptions {
                     options = options.socket(&*value);
                 }
 
+                "allow-local-infile" => {
+                    for file_name in value.split(',').filter(|x| !x.is_empty()) {
+                        options = options.allow_local_infile(file_name);
+                    }
+                }
+
                 _ => {}
             }
         }
@@ -87,3 +101,20 @@ fn it_parses_password_with_non_ascii_chars_correctly() {
 
     assert_eq!(Some("p@ssw0rd".into()), opts.password);
 }
+
+#[test]
+fn it_parses_allow_local_infile() {
+    let uri = "mysql://root@localhost:3306/test?allow-local-infile=a.csv,b.csv";
+    let opts = MySqlConnectOptions::from_str(uri).unwrap();
+
+    assert_eq!(vec!["a.csv".to_string(), "b.csv".to_string()], opts.local_infile_allow);
+}
+
+#[test]
+fn it_parses_ssl_client_cert() {
+    let uri = "mysql://root@localhost:3306/test?ssl-cert=/certs/client.crt&ssl-key=/certs/client.key";
+    let opts = MySqlConnectOptions::from_str(uri).unwrap();
+
+    assert_eq!("file: /certs/client.crt", opts.ssl_client_cert.unwrap().to_string());
+    assert_eq!("file: /certs/client.key", opts.ssl_client_key.unwrap().to_string());
+}
@@ -0,0 +1,38 @@
+use bytes::{Buf, Bytes};
+
+use rbdc::io::Decode;
+use rbdc::{err_protocol, Error};
+
+/// Sent by the server when executing `LOAD DATA LOCAL INFILE`,
+/// asks the client to send the contents of the file.
+///
+/// <https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_local_infile_request.html>
+#[derive(Debug)]
+pub struct LocalInfileRequest {
+    pub filename: String,
+}
+
+impl Decode<'_> for LocalInfileRequest {
+    fn decode_with(mut buf: Bytes, _: ()) -> Result<Self, Error> {
+        let header = buf.get_u8();
+        if header != 0xfb {
+            return Err(err_protocol!(
+                "expected 0xfb (LOCAL INFILE Request) but found 0x{:02x}",
+                header
+            ));
+        }
+
+        let filename = String::from_utf8(buf.to_vec()).map_err(|e| err_protocol!("{}", e))?;
+
+        Ok(Self { filename })
+    }
+}
+
+#[test]
+fn test_decode_local_infile_request() {
+    const DATA: &[u8] = b"\xfbusers.csv";
+
+    let p = LocalInfileRequest::decode(DATA.into()).unwrap();
+
+    assert_eq!(p.filename, "users.csv");
+}
@@ -4,6 +4,8 @@ use std::iter::{Extend, IntoIterator};
 pub struct MySqlQueryResult {
     pub(super) rows_affected: u64,
     pub(super) last_insert_id: u64,
+    // true when this result terminates a result set (columns and rows), false for an OK packet
+    pub(super) result_set: bool,
 }
 
 impl MySqlQueryResult {
@@ -14,6 +16,11 @@ impl MySqlQueryResult {
     pub fn rows_affected(&self) -> u64 {
         self.rows_affected
     }
+
+    /// is this the end of a result set, rather than the OK of a statement without columns
+    pub fn is_result_set(&self) -> bool {
+        self.result_set
+    }
 }
 
 impl Extend<MySqlQueryResult> for MySqlQueryResult {
@@ -0,0 +1,55 @@
+use crate::options::PgConnectOptions;
+use futures_core::future::BoxFuture;
+use rbdc::db::CancelHandle;
+use rbdc::net::Socket;
+use rbdc::rt::AsyncWriteExt;
+use rbdc::Error;
+
+// https://www.postgresql.org/docs/current/protocol-flow.html#id-1.10.5.7.10
+const CANCEL_REQUEST_CODE: u32 = 80877102;
+
+/// sends a CancelRequest for the running statement of a backend over a new connection
+#[derive(Clone, Debug)]
+pub struct PgCancelHandle {
+    pub(crate) socket: Option<String>,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) process_id: u32,
+    pub(crate) secret_key: u32,
+}
+
+impl PgCancelHandle {
+    pub(crate) fn new(options: &PgConnectOptions, process_id: u32, secret_key: u32) -> Self {
+        Self {
+            socket: options.fetch_socket(),
+            host: options.host.clone(),
+            port: options.port,
+            process_id,
+            secret_key,
+        }
+    }
+
+    pub async fn cancel_request(&self) -> Result<(), Error> {
+        let mut socket = match self.socket {
+            Some(ref path) => Socket::connect_uds(path).await?,
+            None => Socket::connect_tcp(&self.host, self.port).await?,
+        };
+        let mut buf = Vec::with_capacity(16);
+        buf.extend_from_slice(&16_u32.to_be_bytes());
+        buf.extend_from_slice(&CANCEL_REQUEST_CODE.to_be_bytes());
+        buf.extend_from_slice(&self.process_id.to_be_bytes());
+        buf.extend_from_slice(&self.secret_key.to_be_bytes());
+        socket.write_all(&buf).await?;
+        socket.flush().await?;
+        // the server closes the connection without a response
+        socket.shutdown().await?;
+        Ok(())
+    }
+}
+
+impl CancelHandle for PgCancelHandle {
+    fn cancel(&self) -> BoxFuture<'static, Result<(), Error>> {
+        let handle = self.clone();
+        Box::pin(async move { handle.cancel_request().await })
+    }
+}
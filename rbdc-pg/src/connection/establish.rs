@@ -1,4 +1,4 @@
-use crate::connection::{sasl, stream::PgStream, tls, PgConnection};
+use crate::connection::{sasl, stream::PgStream, tls, PgCancelHandle, PgConnection};
 use crate::message::{
     Authentication, BackendKeyData, MessageFormat, Password, ReadyForQuery, Startup,
 };
@@ -137,8 +137,7 @@ impl PgConnection {
 
         Ok(PgConnection {
             stream,
-            process_id,
-            secret_key,
+            cancel: PgCancelHandle::new(options, process_id, secret_key),
             transaction_status,
             pending_ready_for_query_count: 0,
             next_statement_id: Oid(1),
@@ -17,6 +17,8 @@ use futures_core::stream::BoxStream;
 use futures_core::Stream;
 use futures_util::{pin_mut, TryStreamExt};
 use rbdc::{err_protocol, try_stream, Error};
+use rbs::Value;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 async fn prepare(
@@ -374,6 +376,182 @@ impl PgConnection {
         })
     }
 
+    /// execute many statements in a pipeline.
+    /// every statement is bound and executed behind a single `Sync`,
+    /// so the whole batch costs one round trip (plus one `Parse` round trip for each sql not in the statement cache).
+    ///
+    /// return one result for each statement. the batch runs in one implicit transaction (unless a transaction is open),
+    /// if a statement fails or fails to prepare, none of the batch is applied and
+    /// the other statements get `Error::batch_aborted()`
+    pub fn exec_pipeline(
+        &mut self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<'_, Result<Vec<Result<PgQueryResult, Error>>, Error>> {
+        Box::pin(async move {
+            self.wait_until_ready().await?;
+
+            // caching more distinct sql than the cache holds would evict (and close) statements
+            // of this batch before they are bound, prepare the ones not cached yet without caching
+            let distinct = batch
+                .iter()
+                .map(|(sql, _)| sql.as_str())
+                .collect::<HashSet<_>>()
+                .len();
+            let store_to_cache = distinct <= self.cache_statement.capacity();
+            let mut uncached = HashMap::<String, (Oid, Arc<PgStatementMetadata>)>::new();
+
+            // prepare statements and patch arguments first, both may need extra round trips
+            let len = batch.len();
+            let mut portals = Vec::with_capacity(len);
+            let mut failed = None;
+            for (sql, params) in batch {
+                match self
+                    .prepare_portal(sql, params, store_to_cache, &mut uncached)
+                    .await
+                {
+                    Ok(v) => portals.push(v),
+                    Err(e) => {
+                        // skip the [ReadyForQuery] of the failed `Parse`
+                        self.wait_until_ready().await?;
+                        failed = Some(e);
+                        break;
+                    }
+                }
+            }
+
+            let results = match failed {
+                // nothing of the batch runs
+                Some(e) => {
+                    let mut results = (0..portals.len())
+                        .map(|_| Err(Error::batch_aborted()))
+                        .collect::<Vec<_>>();
+                    results.push(Err(e));
+                    while results.len() < len {
+                        results.push(Err(Error::batch_aborted()));
+                    }
+                    Ok(results)
+                }
+                None => self.run_pipeline(&portals).await,
+            };
+
+            // close the statements prepared only for this batch
+            if !uncached.is_empty() {
+                self.wait_until_ready().await?;
+                for (id, _) in uncached.values() {
+                    self.stream.write(Close::Statement(*id));
+                }
+                self.write_sync();
+                self.stream.flush().await?;
+                self.wait_for_close_complete(uncached.len()).await?;
+                self.recv_ready_for_query().await?;
+            }
+            results
+        })
+    }
+
+    async fn prepare_portal(
+        &mut self,
+        sql: String,
+        params: Vec<Value>,
+        store_to_cache: bool,
+        uncached: &mut HashMap<String, (Oid, Arc<PgStatementMetadata>)>,
+    ) -> Result<(Oid, PgArguments), Error> {
+        let mut arguments = PgArguments::default();
+        for x in params {
+            arguments.add(x)?;
+        }
+        let (statement, metadata) = match uncached.get(&sql) {
+            Some(v) => v.clone(),
+            None if store_to_cache || self.cache_statement.contains_key(&sql) => {
+                self.get_or_prepare(&sql, &arguments.types, true, None)
+                    .await?
+            }
+            None => {
+                let v = prepare(self, &sql, &arguments.types, None).await?;
+                uncached.insert(sql, v.clone());
+                v
+            }
+        };
+        arguments.apply_patches(self, &metadata.parameters).await?;
+        self.wait_until_ready().await?;
+        Ok((statement, arguments))
+    }
+
+    async fn run_pipeline(
+        &mut self,
+        portals: &[(Oid, PgArguments)],
+    ) -> Result<Vec<Result<PgQueryResult, Error>>, Error> {
+        for (statement, arguments) in portals {
+            self.stream.write(Bind {
+                portal: None,
+                statement: *statement,
+                formats: &[PgValueFormat::Binary],
+                num_params: arguments.types.len() as i16,
+                params: &*arguments.buffer,
+                result_formats: &[PgValueFormat::Binary],
+            });
+            self.stream.write(message::Execute {
+                portal: None,
+                limit: 0,
+            });
+        }
+
+        // one [Sync] for the whole batch
+        self.write_sync();
+        self.stream.flush().await?;
+
+        let mut results = Vec::with_capacity(portals.len());
+        loop {
+            let message = match self.stream.recv().await {
+                Ok(v) => v,
+                Err(e) => {
+                    // the statement failed, postgres rolls back the implicit transaction of the batch
+                    // and skips the rest of it until the [Sync]. a connection that is gone fails the batch as a whole
+                    self.wait_until_ready().await?;
+                    let mut results = results
+                        .into_iter()
+                        .map(|_| Err(Error::batch_aborted()))
+                        .collect::<Vec<_>>();
+                    results.push(Err(e));
+                    while results.len() < portals.len() {
+                        results.push(Err(Error::batch_aborted()));
+                    }
+                    return Ok(results);
+                }
+            };
+            match message.format {
+                MessageFormat::BindComplete | MessageFormat::DataRow | MessageFormat::NoData => {
+                    // harmless messages to ignore
+                }
+
+                MessageFormat::CommandComplete => {
+                    let cc: CommandComplete = message.decode()?;
+                    results.push(Ok(PgQueryResult {
+                        rows_affected: cc.rows_affected(),
+                    }));
+                }
+
+                MessageFormat::EmptyQueryResponse => {
+                    // empty query string, keep one result for each statement
+                    results.push(Ok(PgQueryResult::default()));
+                }
+
+                MessageFormat::ReadyForQuery => {
+                    self.handle_ready_for_query(message)?;
+                    break;
+                }
+
+                _ => {
+                    return Err(err_protocol!(
+                        "pipeline: unexpected message: {:?}",
+                        message.format
+                    ));
+                }
+            }
+        }
+        Ok(results)
+    }
+
     pub fn prepare_with<'a>(
         &'a mut self,
         sql: String,
@@ -391,3 +569,115 @@ impl PgConnection {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::connection::PgConnection;
+    use crate::options::PgConnectOptions;
+    use rbdc::db::Connection;
+    use rbs::Value;
+    use std::str::FromStr;
+
+    /// runs against the server of `RBDC_PG_URL`, skipped when it is not set
+    #[tokio::test]
+    async fn test_exec_pipeline_more_sql_than_cache() {
+        let url = match std::env::var("RBDC_PG_URL") {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        let options = PgConnectOptions::from_str(&url)
+            .unwrap()
+            .statement_cache_capacity(2);
+        let mut conn = PgConnection::establish(&options).await.unwrap();
+        conn.exec("create temp table pipeline_test (id int, name text)", vec![])
+            .await
+            .unwrap();
+        let mut batch = vec![];
+        for i in 0..5 {
+            batch.push((
+                format!("insert into pipeline_test (id, name) values ($1, $2) -- {}", i),
+                vec![Value::I32(i), Value::String(i.to_string())],
+            ));
+        }
+        batch.push(("update pipeline_test set name = $1".to_string(), vec![Value::from("a")]));
+        let results = conn.exec_pipeline(batch.clone()).await.unwrap();
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[5].as_ref().unwrap().rows_affected, 5);
+        // the statements prepared for the batch are closed, the connection still works
+        let results = conn.exec_pipeline(batch).await.unwrap();
+        assert_eq!(results[5].as_ref().unwrap().rows_affected, 10);
+        assert!(conn.cached_statements_size() <= 2);
+    }
+
+    /// runs against the server of `RBDC_PG_URL`, skipped when it is not set
+    #[tokio::test]
+    async fn test_exec_pipeline_error() {
+        let url = match std::env::var("RBDC_PG_URL") {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        let options = PgConnectOptions::from_str(&url).unwrap();
+        let mut conn = PgConnection::establish(&options).await.unwrap();
+        conn.exec("create temp table pipeline_error (id int)", vec![])
+            .await
+            .unwrap();
+        let insert = "insert into pipeline_error (id) values ($1)".to_string();
+        // fails when executed
+        let results = conn
+            .exec_pipeline(vec![
+                (insert.clone(), vec![Value::I32(1)]),
+                (
+                    "insert into pipeline_error (id) values (1 / $1)".to_string(),
+                    vec![Value::I32(0)],
+                ),
+                (insert.clone(), vec![Value::I32(2)]),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].as_ref().unwrap_err().is_batch_aborted());
+        assert!(results[1].as_ref().unwrap_err().to_string().contains("division by zero"));
+        assert!(results[2].as_ref().unwrap_err().is_batch_aborted());
+        // fails to prepare
+        let results = conn
+            .exec_pipeline(vec![
+                (insert.clone(), vec![Value::I32(3)]),
+                ("insert into pipeline_missing (id) values ($1)".to_string(), vec![Value::I32(4)]),
+                (insert.clone(), vec![Value::I32(5)]),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].as_ref().unwrap_err().is_batch_aborted());
+        assert!(results[1].as_ref().unwrap_err().to_string().contains("pipeline_missing"));
+        assert!(results[2].as_ref().unwrap_err().is_batch_aborted());
+        // none of the batches is applied, the connection still works
+        conn.exec_pipeline(vec![(insert, vec![Value::I32(6)])])
+            .await
+            .unwrap();
+        let rows = conn
+            .get_values("select id from pipeline_error", vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    /// runs against the server of `RBDC_PG_URL`, skipped when it is not set
+    #[tokio::test]
+    async fn test_reset_after_discard_all() {
+        let url = match std::env::var("RBDC_PG_URL") {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        let options = PgConnectOptions::from_str(&url).unwrap();
+        let mut conn = PgConnection::establish(&options).await.unwrap();
+        let sql = "select $1::int4 as v";
+        conn.get_values(sql, vec![Value::I32(1)]).await.unwrap();
+        conn.exec("DISCARD ALL", vec![]).await.unwrap();
+        conn.reset().await.unwrap();
+        assert_eq!(conn.cached_statements_size(), 0);
+        // the statement is prepared again instead of using the deallocated one
+        let rows = conn.get_values(sql, vec![Value::I32(2)]).await.unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+}
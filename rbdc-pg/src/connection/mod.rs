@@ -13,7 +13,7 @@ use futures_core::future::BoxFuture;
 use futures_core::stream::BoxStream;
 use futures_util::{FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 use rbdc::common::StatementCache;
-use rbdc::db::{Connection, ExecResult, Placeholder, Row};
+use rbdc::db::{CancelHandle, Connection, ExecResult, Placeholder, Row};
 use rbdc::ext::ustr::UStr;
 use rbdc::io::Decode;
 use rbdc::Error;
@@ -22,8 +22,10 @@ use std::collections::HashMap;
 use std::fmt::{self, Debug, Formatter};
 use std::sync::Arc;
 
+pub use self::cancel::PgCancelHandle;
 pub use self::stream::PgStream;
 
+mod cancel;
 pub(crate) mod describe;
 mod establish;
 mod executor;
@@ -38,15 +40,9 @@ pub struct PgConnection {
     // wrapped in a buffered stream
     pub(crate) stream: PgStream,
 
-    // process id of this backend
+    // address, process id and secret key of this backend
     // used to send cancel requests
-    #[allow(dead_code)]
-    process_id: u32,
-
-    // secret key of this backend
-    // used to send cancel requests
-    #[allow(dead_code)]
-    secret_key: u32,
+    cancel: PgCancelHandle,
 
     // sequence of statement IDs for use in preparing statements
     // in PostgreSQL, the statement is prepared to a user-supplied identifier
@@ -184,6 +180,19 @@ impl Connection for PgConnection {
         self.exec("/* RBDC ping */", vec![]).map_ok(|_| ()).boxed()
     }
 
+    fn reset(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        // closing a statement that `DISCARD ALL` deallocated is not an error
+        self.clear_cached_statements()
+    }
+
+    fn in_transaction(&self) -> Option<bool> {
+        Some(!matches!(self.transaction_status, TransactionStatus::Idle))
+    }
+
+    fn cancel_handle(&self) -> Option<Box<dyn CancelHandle>> {
+        Some(Box::new(self.cancel.clone()))
+    }
+
     fn get_rows(
         &mut self,
         sql: &str,
@@ -267,4 +276,25 @@ impl Connection for PgConnection {
             });
         })
     }
+
+    fn exec_batch(
+        &mut self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<Result<Vec<Result<ExecResult, Error>>, Error>> {
+        let batch = batch
+            .into_iter()
+            .map(|(sql, params)| (PgDriver {}.exchange(&sql), params))
+            .collect();
+        Box::pin(async move {
+            let v = self.exec_pipeline(batch).await?;
+            Ok(v.into_iter()
+                .map(|v| {
+                    v.map(|v| ExecResult {
+                        rows_affected: v.rows_affected,
+                        last_insert_id: Value::Null,
+                    })
+                })
+                .collect())
+        })
+    }
 }
@@ -32,6 +32,11 @@ impl Driver for PgDriver {
     fn default_option(&self) -> Box<dyn ConnectOptions> {
         Box::new(PgConnectOptions::default())
     }
+
+    /// the count of parameters in a `Bind` message is 16 bits wide
+    fn max_params(&self) -> usize {
+        u16::MAX as usize
+    }
 }
 
 impl Placeholder for PgDriver {
@@ -64,6 +64,14 @@ impl FromStr for PgConnectOptions {
                     options = options.ssl_root_cert(&*value);
                 }
 
+                "sslcert" | "ssl-cert" => {
+                    options = options.ssl_client_cert(&*value);
+                }
+
+                "sslkey" | "ssl-key" => {
+                    options = options.ssl_client_key(&*value);
+                }
+
                 "statement-cache-capacity" => {
                     options = options.statement_cache_capacity(
                         value
@@ -267,4 +275,18 @@ mod test {
         let opts = PgConnectOptions::from_str(uri).unwrap();
         assert_eq!(PgSslMode::Disable, opts.ssl_mode);
     }
+
+    #[test]
+    fn it_parses_ssl_client_cert() {
+        let uri = "postgres:///?sslcert=/certs/client.crt&sslkey=/certs/client.key";
+        let opts = PgConnectOptions::from_str(uri).unwrap();
+        assert_eq!(
+            "file: /certs/client.crt",
+            opts.ssl_client_cert.unwrap().to_string()
+        );
+        assert_eq!(
+            "file: /certs/client.key",
+            opts.ssl_client_key.unwrap().to_string()
+        );
+    }
 }
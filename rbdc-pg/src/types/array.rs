@@ -11,41 +11,79 @@ use std::borrow::Cow;
 
 impl<T: Decode + TypeInfo> Decode for Vec<T> {
     fn decode(value: PgValue) -> Result<Self, Error> {
-        let format = value.format();
-        match format {
-            PgValueFormat::Binary => {
-                // https://github.com/postgres/postgres/blob/a995b371ae29de2d38c4b7881cf414b1560e9746/src/backend/utils/adt/arrayfuncs.c#L1548
+        let (dims, elements) = decode_elements(value)?;
+        if dims.len() > 1 {
+            return Err(format!("encountered an array of {} dimensions; decode into `Value` to read multi-dimensional arrays", dims.len()).into());
+        }
+        let mut result = Vec::with_capacity(elements.len());
+        for element in elements {
+            result.push(T::decode(element)?);
+        }
+        Ok(result)
+    }
+}
 
-                let mut buf = value.as_bytes()?;
+/// decode a pg array (of any dimensions) into `Value::Array`.
+/// multi-dimensional arrays are decoded into nested `Value::Array`
+pub(crate) fn decode_value_array(value: PgValue) -> Result<Value, Error> {
+    let (dims, elements) = decode_elements(value)?;
+    let mut values = Vec::with_capacity(elements.len());
+    for element in elements {
+        values.push(Value::decode(element)?);
+    }
+    Ok(Value::Array(nest(&dims, values)))
+}
 
-                // number of dimensions in the array
-                let ndim = buf.get_i32();
+/// split a flat element list into nested arrays by the array dimensions
+fn nest(dims: &[usize], values: Vec<Value>) -> Vec<Value> {
+    if dims.len() <= 1 {
+        return values;
+    }
+    let chunk: usize = dims[1..].iter().product();
+    let mut iter = values.into_iter();
+    let mut result = Vec::with_capacity(dims[0]);
+    for _ in 0..dims[0] {
+        let part: Vec<Value> = iter.by_ref().take(chunk).collect();
+        result.push(Value::Array(nest(&dims[1..], part)));
+    }
+    result
+}
 
-                if ndim == 0 {
-                    // zero dimensions is an empty array
-                    return Ok(Vec::new());
-                }
+/// read the dimensions and the flat (row-major) element list of a pg array
+fn decode_elements(value: PgValue) -> Result<(Vec<usize>, Vec<PgValue>), Error> {
+    let format = value.format();
+    match format {
+        PgValueFormat::Binary => {
+            // https://github.com/postgres/postgres/blob/a995b371ae29de2d38c4b7881cf414b1560e9746/src/backend/utils/adt/arrayfuncs.c#L1548
 
-                if ndim != 1 {
-                    return Err(format!("encountered an array of {} dimensions; only one-dimensional arrays are supported", ndim).into());
-                }
+            let mut buf = value.as_bytes()?;
+
+            // number of dimensions in the array
+            let ndim = buf.get_i32();
 
-                // appears to have been used in the past to communicate potential NULLS
-                // but reading source code back through our supported postgres versions (9.5+)
-                // this is never used for anything
-                let _flags = buf.get_i32();
-
-                // the OID of the element
-                let element_type_oid = Oid(buf.get_u32());
-                let element_type_info: PgTypeInfo = PgTypeInfo::try_from_oid(element_type_oid)
-                    .or_else(|| value.type_info.try_array_element().map(Cow::into_owned))
-                    .ok_or_else(|| {
-                        Error::from(format!(
-                            "failed to resolve array element type for oid {}",
-                            element_type_oid.0
-                        ))
-                    })?;
+            if ndim == 0 {
+                // zero dimensions is an empty array
+                return Ok((vec![], vec![]));
+            }
 
+            // appears to have been used in the past to communicate potential NULLS
+            // but reading source code back through our supported postgres versions (9.5+)
+            // this is never used for anything
+            let _flags = buf.get_i32();
+
+            // the OID of the element
+            let element_type_oid = Oid(buf.get_u32());
+            let element_type_info: PgTypeInfo = PgTypeInfo::try_from_oid(element_type_oid)
+                .or_else(|| value.type_info.try_array_element().map(Cow::into_owned))
+                .ok_or_else(|| {
+                    Error::from(format!(
+                        "failed to resolve array element type for oid {}",
+                        element_type_oid.0
+                    ))
+                })?;
+
+            let mut dims = Vec::with_capacity(ndim as usize);
+            for dim in 0..ndim {
                 // length of the array axis
                 let len = buf.get_i32();
 
@@ -53,141 +91,452 @@ impl<T: Decode + TypeInfo> Decode for Vec<T> {
                 let lower = buf.get_i32();
 
                 if lower != 1 {
-                    return Err(format!("encountered an array with a lower bound of {} in the first dimension; only arrays starting at one are supported", lower).into());
-                }
-
-                let mut elements = Vec::with_capacity(len as usize);
-
-                for _ in 0..len {
-                    elements.push(T::decode(PgValue::get(
-                        &mut buf,
-                        format,
-                        element_type_info.clone(),
-                    ))?)
+                    return Err(format!("encountered an array with a lower bound of {} in the dimension {}; only arrays starting at one are supported", lower, dim + 1).into());
                 }
+                dims.push(len as usize);
+            }
 
-                Ok(elements)
+            let len: usize = dims.iter().product();
+            let mut elements = Vec::with_capacity(len);
+            for _ in 0..len {
+                elements.push(PgValue::get(&mut buf, format, element_type_info.clone()));
             }
+            Ok((dims, elements))
+        }
 
-            PgValueFormat::Text => {
-                // no type is provided from the database for the element
-                let mut element_type_info = PgTypeInfo::UNKNOWN;
-                match value.type_info.kind() {
-                    PgTypeKind::Simple => {}
-                    PgTypeKind::Pseudo => {}
-                    PgTypeKind::Domain(_) => {}
-                    PgTypeKind::Composite(_) => {}
-                    PgTypeKind::Array(item) => {
-                        element_type_info = item.clone();
-                    }
-                    PgTypeKind::Enum(_) => {}
-                    PgTypeKind::Range(_) => {}
+        PgValueFormat::Text => {
+            // no type is provided from the database for the element
+            let mut element_type_info = PgTypeInfo::UNKNOWN;
+            match value.type_info.kind() {
+                PgTypeKind::Simple => {}
+                PgTypeKind::Pseudo => {}
+                PgTypeKind::Domain(_) => {}
+                PgTypeKind::Composite(_) => {}
+                PgTypeKind::Array(item) => {
+                    element_type_info = item.clone();
                 }
+                PgTypeKind::Enum(_) => {}
+                PgTypeKind::Range(_) => {}
+            }
 
-                let s = value.as_str()?;
-
-                // https://github.com/postgres/postgres/blob/a995b371ae29de2d38c4b7881cf414b1560e9746/src/backend/utils/adt/arrayfuncs.c#L718
-
-                // trim the wrapping braces
-                let s = &s[1..(s.len() - 1)];
+            let (dims, items) = parse_text_array(value.as_str()?)?;
+            let elements = items
+                .into_iter()
+                .map(|item| PgValue {
+                    value: item.map(|v| v.into_bytes()),
+                    type_info: element_type_info.clone(),
+                    format,
+                })
+                .collect();
+            Ok((dims, elements))
+        }
+    }
+}
 
-                if s.is_empty() {
-                    // short-circuit empty arrays up here
-                    return Ok(Vec::new());
-                }
+enum TextNode {
+    Array(Vec<TextNode>),
+    Item(Option<String>),
+}
 
-                // NOTE: Nearly *all* types use ',' as the sequence delimiter. Yes, there is one
-                //       that does not. The BOX (not PostGIS) type uses ';' as a delimiter.
+/// parse the text representation of an array, for example `{{1,2},{3,NULL}}`
+/// into dimensions and a flat element list. `None` means a `NULL` element.
+fn parse_text_array(s: &str) -> Result<(Vec<usize>, Vec<Option<String>>), Error> {
+    // https://github.com/postgres/postgres/blob/a995b371ae29de2d38c4b7881cf414b1560e9746/src/backend/utils/adt/arrayfuncs.c#L718
+    if s.starts_with('[') {
+        return Err(format!("encountered an array with explicit bounds {:?}; only arrays starting at one are supported", s).into());
+    }
+    let mut chars = s.chars().peekable();
+    if chars.next() != Some('{') {
+        return Err(format!("malformed array literal: {:?}", s).into());
+    }
+    let nodes = parse_text_nodes(&mut chars, s)?;
+
+    let mut dims = vec![];
+    let mut level = &nodes;
+    loop {
+        dims.push(level.len());
+        match level.first() {
+            Some(TextNode::Array(inner)) => level = inner,
+            _ => break,
+        }
+    }
+    if dims.contains(&0) {
+        return Ok((vec![], vec![]));
+    }
 
-                // TODO: When we add support for BOX we need to figure out some way to make the
-                //       delimiter selection
+    let mut items = Vec::with_capacity(dims.iter().product());
+    flatten_text_nodes(nodes, &mut items);
+    if items.len() != dims.iter().product::<usize>() {
+        return Err(format!("malformed array literal: {:?}; multidimensional arrays must have sub-arrays with matching dimensions", s).into());
+    }
+    Ok((dims, items))
+}
 
-                let delimiter = ',';
-                let mut done = false;
-                let mut in_quotes = false;
-                let mut in_escape = false;
+fn parse_text_nodes(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    s: &str,
+) -> Result<Vec<TextNode>, Error> {
+    // NOTE: Nearly *all* types use ',' as the sequence delimiter. Yes, there is one
+    //       that does not. The BOX (not PostGIS) type uses ';' as a delimiter.
+
+    // TODO: When we add support for BOX we need to figure out some way to make the
+    //       delimiter selection
+    let delimiter = ',';
+    let mut nodes = Vec::with_capacity(4);
+    loop {
+        match chars.peek() {
+            None => {
+                return Err(format!("malformed array literal: {:?}", s).into());
+            }
+            Some('}') => {
+                chars.next();
+                return Ok(nodes);
+            }
+            Some('{') => {
+                chars.next();
+                nodes.push(TextNode::Array(parse_text_nodes(chars, s)?));
+            }
+            Some(ch) if *ch == delimiter => {
+                chars.next();
+            }
+            Some('"') => {
+                chars.next();
                 let mut value = String::with_capacity(10);
-                let mut chars = s.chars();
-                let mut elements = Vec::with_capacity(4);
-
-                while !done {
-                    loop {
-                        match chars.next() {
-                            Some(ch) => match ch {
-                                _ if in_escape => {
-                                    value.push(ch);
-                                    in_escape = false;
-                                }
-
-                                '"' => {
-                                    in_quotes = !in_quotes;
-                                }
-
-                                '\\' => {
-                                    in_escape = true;
-                                }
-
-                                _ if ch == delimiter && !in_quotes => {
-                                    break;
-                                }
-
-                                _ => {
-                                    value.push(ch);
-                                }
-                            },
-
-                            None => {
-                                done = true;
-                                break;
-                            }
+                let mut in_escape = false;
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(format!("malformed array literal: {:?}", s).into());
                         }
+                        Some(ch) if in_escape => {
+                            value.push(ch);
+                            in_escape = false;
+                        }
+                        Some('\\') => in_escape = true,
+                        Some('"') => break,
+                        Some(ch) => value.push(ch),
                     }
-
-                    let value_opt = if value == "NULL" {
-                        None
+                }
+                // a quoted "NULL" is a string, not a null element
+                nodes.push(TextNode::Item(Some(value)));
+            }
+            Some(_) => {
+                let mut value = String::with_capacity(10);
+                let mut in_escape = false;
+                while let Some(ch) = chars.peek() {
+                    if !in_escape && (*ch == delimiter || *ch == '}') {
+                        break;
+                    }
+                    let ch = chars.next().unwrap_or_default();
+                    if in_escape {
+                        value.push(ch);
+                        in_escape = false;
+                    } else if ch == '\\' {
+                        in_escape = true;
                     } else {
-                        Some(value.as_bytes().to_vec())
-                    };
-                    elements.push(T::decode(PgValue {
-                        value: value_opt,
-                        type_info: element_type_info.clone(),
-                        format,
-                    })?);
-
-                    value.clear();
+                        value.push(ch);
+                    }
+                }
+                if value.eq_ignore_ascii_case("NULL") {
+                    nodes.push(TextNode::Item(None));
+                } else {
+                    nodes.push(TextNode::Item(Some(value)));
                 }
-
-                Ok(elements)
             }
         }
     }
 }
 
-fn element_type_info<T: TypeInfo>(arg: &Vec<T>) -> PgTypeInfo {
-    if arg.len() == 0 {
-        PgTypeInfo::UNKNOWN
-    } else {
-        arg[0].type_info()
+fn flatten_text_nodes(nodes: Vec<TextNode>, items: &mut Vec<Option<String>>) {
+    for node in nodes {
+        match node {
+            TextNode::Array(inner) => flatten_text_nodes(inner, items),
+            TextNode::Item(item) => items.push(item),
+        }
     }
 }
 
+/// the element type of an array, taken from the first non-null (innermost) element.
+/// returns `PgTypeInfo::UNKNOWN` for empty and all-null arrays
+pub(crate) fn element_type_info(arg: &[Value]) -> PgTypeInfo {
+    for v in arg {
+        let type_info = match v {
+            Value::Null => continue,
+            Value::Array(arr) => element_type_info(arr),
+            v => v.type_info(),
+        };
+        if type_info != PgTypeInfo::UNKNOWN {
+            return type_info;
+        }
+    }
+    PgTypeInfo::UNKNOWN
+}
+
+/// array type hints, for example `Value::Ext("UuidArray", Box::new(Value::Array(vec![])))`.
+/// the hint name is the pg array type name, it gives empty and all-null arrays
+/// an explicit element type
+pub(crate) fn array_type_by_name(type_name: &str) -> Option<PgTypeInfo> {
+    Some(match type_name {
+        "BoolArray" => PgTypeInfo::BOOL_ARRAY,
+        "ByteaArray" => PgTypeInfo::BYTEA_ARRAY,
+        "CharArray" => PgTypeInfo::CHAR_ARRAY,
+        "NameArray" => PgTypeInfo::NAME_ARRAY,
+        "Int2Array" => PgTypeInfo::INT2_ARRAY,
+        "Int4Array" => PgTypeInfo::INT4_ARRAY,
+        "Int8Array" => PgTypeInfo::INT8_ARRAY,
+        "TextArray" => PgTypeInfo::TEXT_ARRAY,
+        "BpcharArray" => PgTypeInfo::BPCHAR_ARRAY,
+        "VarcharArray" => PgTypeInfo::VARCHAR_ARRAY,
+        "OidArray" => PgTypeInfo::OID_ARRAY,
+        "JsonArray" => PgTypeInfo::JSON_ARRAY,
+        "JsonbArray" => PgTypeInfo::JSONB_ARRAY,
+        "JsonpathArray" => PgTypeInfo::JSONPATH_ARRAY,
+        "PointArray" => PgTypeInfo::POINT_ARRAY,
+        "LsegArray" => PgTypeInfo::LSEG_ARRAY,
+        "PathArray" => PgTypeInfo::PATH_ARRAY,
+        "BoxArray" => PgTypeInfo::BOX_ARRAY,
+        "PolygonArray" => PgTypeInfo::POLYGON_ARRAY,
+        "LineArray" => PgTypeInfo::LINE_ARRAY,
+        "CircleArray" => PgTypeInfo::CIRCLE_ARRAY,
+        "CidrArray" => PgTypeInfo::CIDR_ARRAY,
+        "InetArray" => PgTypeInfo::INET_ARRAY,
+        "MacaddrArray" => PgTypeInfo::MACADDR_ARRAY,
+        "Macaddr8Array" => PgTypeInfo::MACADDR8_ARRAY,
+        "Float4Array" => PgTypeInfo::FLOAT4_ARRAY,
+        "Float8Array" => PgTypeInfo::FLOAT8_ARRAY,
+        "NumericArray" => PgTypeInfo::NUMERIC_ARRAY,
+        "MoneyArray" => PgTypeInfo::MONEY_ARRAY,
+        "DateArray" => PgTypeInfo::DATE_ARRAY,
+        "TimeArray" => PgTypeInfo::TIME_ARRAY,
+        "TimestampArray" => PgTypeInfo::TIMESTAMP_ARRAY,
+        "TimestamptzArray" => PgTypeInfo::TIMESTAMPTZ_ARRAY,
+        "TimetzArray" => PgTypeInfo::TIMETZ_ARRAY,
+        "IntervalArray" => PgTypeInfo::INTERVAL_ARRAY,
+        "BitArray" => PgTypeInfo::BIT_ARRAY,
+        "VarbitArray" => PgTypeInfo::VARBIT_ARRAY,
+        "RecordArray" => PgTypeInfo::RECORD_ARRAY,
+        "UuidArray" => PgTypeInfo::UUID_ARRAY,
+        "Int4RangeArray" => PgTypeInfo::INT4_RANGE_ARRAY,
+        "NumRangeArray" => PgTypeInfo::NUM_RANGE_ARRAY,
+        "TsRangeArray" => PgTypeInfo::TS_RANGE_ARRAY,
+        "TstzRangeArray" => PgTypeInfo::TSTZ_RANGE_ARRAY,
+        "DateRangeArray" => PgTypeInfo::DATE_RANGE_ARRAY,
+        "Int8RangeArray" => PgTypeInfo::INT8_RANGE_ARRAY,
+        _ => return None,
+    })
+}
+
+/// the dimensions of a (nested) array, empty for an empty array
+fn array_dims(arr: &[Value]) -> Result<Vec<i32>, Error> {
+    if arr.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut dims = vec![arr.len() as i32];
+    // `[[],[]]` is nested too, its sub-arrays just have no dimensions
+    let nested = matches!(arr.first(), Some(Value::Array(_)));
+    if let Some(Value::Array(first)) = arr.first() {
+        dims.extend(array_dims(first)?);
+    }
+    for v in arr {
+        let matched = match v {
+            Value::Array(inner) => nested && array_dims(inner)? == dims[1..],
+            _ => !nested,
+        };
+        if !matched {
+            return Err(Error::from(
+                "multidimensional arrays must have sub-arrays with matching dimensions",
+            ));
+        }
+    }
+    Ok(dims)
+}
+
+fn flatten(arr: Vec<Value>, elements: &mut Vec<Value>) {
+    for v in arr {
+        match v {
+            Value::Array(inner) => flatten(inner, elements),
+            v => elements.push(v),
+        }
+    }
+}
+
+/// encode a (nested) array with the element type.
+/// if the element type is `UNKNOWN` it is patched from the parameter type postgres resolved
+pub(crate) fn encode_array(
+    arr: Vec<Value>,
+    element: PgTypeInfo,
+    buf: &mut PgArgumentBuffer,
+) -> Result<IsNull, Error> {
+    let mut dims = array_dims(&arr)?;
+    let mut elements = Vec::with_capacity(arr.len());
+    flatten(arr, &mut elements);
+    if elements.is_empty() {
+        dims.clear();
+    }
+    buf.extend(&(dims.len() as i32).to_be_bytes()); // number of dimensions
+    buf.extend(&0_i32.to_be_bytes()); // flags
+                                      // element type
+    match element.0 {
+        PgType::DeclareWithName(name) => buf.patch_type_by_name(&name),
+        PgType::Unknown => {
+            // empty or all-null array, e.g. `WHERE id = ANY($1)`
+            buf.patch(|buf, ty: &PgTypeInfo| {
+                if let Some(oid) = ty.try_array_element().and_then(|v| v.try_oid()) {
+                    buf[..4].copy_from_slice(&oid.0.to_be_bytes());
+                }
+            });
+            buf.extend(&0_u32.to_be_bytes());
+        }
+        ty => {
+            buf.extend(&ty.oid().0.to_be_bytes());
+        }
+    }
+    for len in dims {
+        buf.extend(&len.to_be_bytes()); // len
+        buf.extend(&1_i32.to_be_bytes()); // lower bound
+    }
+    for element in elements {
+        buf.encode(element)?;
+    }
+    Ok(IsNull::No)
+}
+
 impl Encode for Vec<Value> {
     fn encode(self, buf: &mut PgArgumentBuffer) -> Result<IsNull, Error> {
         let type_info = element_type_info(&self);
-        buf.extend(&1_i32.to_be_bytes()); // number of dimensions
-        buf.extend(&0_i32.to_be_bytes()); // flags
-                                          // element type
-        match type_info.0 {
-            PgType::DeclareWithName(name) => buf.patch_type_by_name(&name),
-            ty => {
-                buf.extend(&ty.oid().0.to_be_bytes());
-            }
-        }
-        buf.extend(&(self.len() as i32).to_be_bytes()); // len
-        buf.extend(&1_i32.to_be_bytes()); // lower bound
-        for element in self {
-            buf.encode(element)?;
-        }
-        Ok(IsNull::No)
+        encode_array(self, type_info, buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::arguments::PgArgumentBuffer;
+    use crate::type_info::PgTypeInfo;
+    use crate::types::array::{element_type_info, encode_array, parse_text_array};
+    use crate::types::decode::Decode;
+    use crate::types::encode::Encode;
+    use crate::types::TypeInfo;
+    use crate::value::{PgValue, PgValueFormat};
+    use rbs::Value;
+
+    fn read_i32(buf: &[u8], offset: usize) -> i32 {
+        i32::from_be_bytes([
+            buf[offset],
+            buf[offset + 1],
+            buf[offset + 2],
+            buf[offset + 3],
+        ])
+    }
+
+    #[test]
+    fn test_type_info() {
+        assert_eq!(Value::Binary(vec![1]).type_info(), PgTypeInfo::BYTEA);
+        let arr = Value::Array(vec![Value::Null, Value::I64(1)]);
+        assert_eq!(arr.type_info(), PgTypeInfo::INT8_ARRAY);
+        let arr = Value::Array(vec![Value::Array(vec![]), Value::Array(vec![
+            Value::Ext("Uuid", Box::new(Value::String(String::new()))),
+        ])]);
+        assert_eq!(arr.type_info(), PgTypeInfo::UUID_ARRAY);
+        assert_eq!(Value::Array(vec![]).type_info(), PgTypeInfo::UNKNOWN);
+        assert_eq!(
+            Value::Ext("UuidArray", Box::new(Value::Array(vec![]))).type_info(),
+            PgTypeInfo::UUID_ARRAY
+        );
+    }
+
+    #[test]
+    fn test_encode_multi_dimensional() {
+        let arr = vec![
+            Value::Array(vec![Value::I32(1), Value::I32(2), Value::I32(3)]),
+            Value::Array(vec![Value::I32(4), Value::Null, Value::I32(6)]),
+        ];
+        assert_eq!(element_type_info(&arr), PgTypeInfo::INT4);
+        let mut buf = PgArgumentBuffer::default();
+        arr.encode(&mut buf).unwrap();
+        assert_eq!(read_i32(&buf, 0), 2); //ndim
+        assert_eq!(read_i32(&buf, 8), 23); //int4
+        assert_eq!(read_i32(&buf, 12), 2);
+        assert_eq!(read_i32(&buf, 16), 1);
+        assert_eq!(read_i32(&buf, 20), 3);
+        assert_eq!(read_i32(&buf, 24), 1);
+        //6 elements, one of them is null
+        assert_eq!(buf.len(), 28 + 5 * 8 + 4);
+    }
+
+    #[test]
+    fn test_encode_ragged() {
+        let arr = vec![
+            Value::Array(vec![Value::I32(1)]),
+            Value::Array(vec![Value::I32(2), Value::I32(3)]),
+        ];
+        let mut buf = PgArgumentBuffer::default();
+        assert!(arr.encode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        let mut buf = PgArgumentBuffer::default();
+        encode_array(vec![], PgTypeInfo::UNKNOWN, &mut buf).unwrap();
+        assert_eq!(buf.len(), 12);
+        assert_eq!(read_i32(&buf, 0), 0);
+        assert_eq!(read_i32(&buf, 8), 0);
+    }
+
+    #[test]
+    fn test_encode_empty_nested() {
+        let arr = vec![Value::Array(vec![]), Value::Array(vec![])];
+        let mut buf = PgArgumentBuffer::default();
+        encode_array(arr, PgTypeInfo::INT4, &mut buf).unwrap();
+        assert_eq!(buf.len(), 12);
+        assert_eq!(read_i32(&buf, 0), 0);
+        let arr = vec![Value::Array(vec![]), Value::I32(1)];
+        let mut buf = PgArgumentBuffer::default();
+        assert!(encode_array(arr, PgTypeInfo::INT4, &mut buf).is_err());
+    }
+
+    #[test]
+    fn test_decode_multi_dimensional() {
+        let mut buf = PgArgumentBuffer::default();
+        vec![
+            Value::Array(vec![Value::I64(1), Value::I64(2)]),
+            Value::Array(vec![Value::I64(3), Value::Null]),
+        ]
+        .encode(&mut buf)
+        .unwrap();
+        let v = Value::decode(PgValue {
+            value: Some(buf.to_vec()),
+            type_info: PgTypeInfo::INT8_ARRAY,
+            format: PgValueFormat::Binary,
+        })
+        .unwrap();
+        assert_eq!(
+            v,
+            Value::Array(vec![
+                Value::Array(vec![Value::I64(1), Value::I64(2)]),
+                Value::Array(vec![Value::I64(3), Value::Null]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_decode_text() {
+        let v = Value::decode(PgValue {
+            value: Some(r#"{{"a,b",NULL},{"NULL","c\"d"}}"#.as_bytes().to_vec()),
+            type_info: PgTypeInfo::TEXT_ARRAY,
+            format: PgValueFormat::Text,
+        })
+        .unwrap();
+        assert_eq!(
+            v,
+            Value::Array(vec![
+                Value::Array(vec![Value::String("a,b".to_string()), Value::Null]),
+                Value::Array(vec![
+                    Value::String("NULL".to_string()),
+                    Value::String("c\"d".to_string())
+                ]),
+            ])
+        );
+        assert_eq!(parse_text_array("{}").unwrap().0.len(), 0);
+        assert!(parse_text_array("{{1,2},{3}}").is_err());
     }
 }
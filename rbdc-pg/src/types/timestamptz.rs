@@ -2,6 +2,7 @@ use crate::arguments::PgArgumentBuffer;
 use crate::types::decode::Decode;
 use crate::types::encode::{Encode, IsNull};
 use crate::value::{PgValue, PgValueFormat};
+use rbdc::timestamp::Timestamp;
 use rbdc::Error;
 use rbs::Value;
 use std::fmt::{Display, Formatter};
@@ -29,7 +30,8 @@ impl From<Timestamptz> for Value {
 
 impl Encode for Timestamptz {
     fn encode(self, buf: &mut PgArgumentBuffer) -> Result<IsNull, Error> {
-        self.0.encode(buf)
+        // Binary is UTC time, encoded as the microseconds since 2000-01-01
+        Timestamp(self.0).encode(buf)
     }
 }
 
@@ -69,7 +71,12 @@ impl Decode for Timestamptz {
 
 #[cfg(test)]
 mod test {
+    use crate::arguments::PgArgumentBuffer;
+    use crate::type_info::PgTypeInfo;
+    use crate::types::decode::Decode;
+    use crate::types::encode::Encode;
     use crate::types::timestamptz::Timestamptz;
+    use crate::value::{PgValue, PgValueFormat};
 
     #[test]
     fn test_de() {
@@ -79,4 +86,18 @@ mod test {
         let r: Timestamptz = rbs::from_value(v).unwrap();
         assert_eq!(r, tz);
     }
+
+    #[test]
+    fn test_encode_decode() {
+        let tz = Timestamptz(1696923600123, 0);
+        let mut buf = PgArgumentBuffer::default();
+        tz.clone().encode(&mut buf).unwrap();
+        let r = Timestamptz::decode(PgValue {
+            value: Some(buf.to_vec()),
+            type_info: PgTypeInfo::TIMESTAMPTZ,
+            format: PgValueFormat::Binary,
+        })
+        .unwrap();
+        assert_eq!(r, tz);
+    }
 }
\ No newline at end of file
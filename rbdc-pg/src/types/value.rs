@@ -1,6 +1,8 @@
 use crate::arguments::PgArgumentBuffer;
 use crate::type_info::PgType;
 use crate::type_info::PgTypeInfo;
+use crate::type_info::PgTypeKind;
+use crate::types::array;
 use crate::types::byte::Bytea;
 use crate::types::decode::Decode;
 use crate::types::encode::{Encode, IsNull};
@@ -33,17 +35,10 @@ impl TypeInfo for Value {
             Value::F32(_) => PgTypeInfo::FLOAT4,
             Value::F64(_) => PgTypeInfo::FLOAT8,
             Value::String(_) => PgTypeInfo::VARCHAR,
-            Value::Binary(_) => PgTypeInfo::BYTEA_ARRAY,
-            Value::Array(arr) => {
-                if arr.len() == 0 {
-                    return PgTypeInfo::UNKNOWN;
-                }
-                arr[0]
-                    .type_info()
-                    .clone()
-                    .to_array_type()
-                    .unwrap_or(PgTypeInfo::UNKNOWN)
-            }
+            Value::Binary(_) => PgTypeInfo::BYTEA,
+            Value::Array(arr) => array::element_type_info(arr)
+                .to_array_type()
+                .unwrap_or(PgTypeInfo::UNKNOWN),
             Value::Map(_) => PgTypeInfo::UNKNOWN,
             Value::Ext(type_name, _) => {
                 match *type_name {
@@ -103,7 +98,9 @@ impl TypeInfo for Value {
                     "Custom" => PgTypeInfo::UNKNOWN,
                     "DeclareWithName" => PgTypeInfo::UNKNOWN,
                     "DeclareWithOid" => PgTypeInfo::UNKNOWN,
-                    _ => PgTypeInfo::UNKNOWN,
+                    //enum label, the type is resolved by postgres
+                    "Enum" => PgTypeInfo::UNKNOWN,
+                    _ => array::array_type_by_name(type_name).unwrap_or(PgTypeInfo::UNKNOWN),
                 }
             }
         }
@@ -194,7 +191,7 @@ impl Decode for Value {
             ),
 
             PgType::Float4 => Value::F32(Decode::decode(arg)?),
-            PgType::Float8 => Value::F32(Decode::decode(arg)?),
+            PgType::Float8 => Value::F64(Decode::decode(arg)?),
             PgType::Unknown => Value::Null,
             PgType::Circle => Value::Ext(
                 "Circle",
@@ -371,15 +368,7 @@ impl Decode for Value {
                     }
                 })),
             ),
-            PgType::Custom(_) => Value::Ext(
-                "Custom",
-                Box::new(Value::Binary({
-                    match arg.format() {
-                        PgValueFormat::Binary => arg.as_bytes()?.to_owned(),
-                        PgValueFormat::Text => arg.as_str()?.as_bytes().to_vec(),
-                    }
-                })),
-            ),
+            PgType::Custom(_) => decode_custom(arg)?,
             PgType::DeclareWithName(_) => Value::Ext(
                 "DeclareWithName",
                 Box::new(Value::Binary({
@@ -398,55 +387,78 @@ impl Decode for Value {
                     }
                 })),
             ),
-            PgType::JsonArray => Value::Array(Decode::decode(arg)?),
-            PgType::LineArray => Value::Array(Decode::decode(arg)?),
-            PgType::CidrArray => Value::Array(Decode::decode(arg)?),
-            PgType::CircleArray => Value::Array(Decode::decode(arg)?),
-            PgType::Macaddr8Array => Value::Array(Decode::decode(arg)?),
-            PgType::BoolArray => Value::Array(Decode::decode(arg)?),
-            PgType::ByteaArray => Value::Array(Decode::decode(arg)?),
-            PgType::CharArray => Value::Array(Decode::decode(arg)?),
-            PgType::NameArray => Value::Array(Decode::decode(arg)?),
-            PgType::Int2Array => Value::Array(Decode::decode(arg)?),
-            PgType::Int4Array => Value::Array(Decode::decode(arg)?),
-            PgType::TextArray => Value::Array(Decode::decode(arg)?),
-            PgType::BpcharArray => Value::Array(Decode::decode(arg)?),
-            PgType::VarcharArray => Value::Array(Decode::decode(arg)?),
-            PgType::Int8Array => Value::Array(Decode::decode(arg)?),
-            PgType::PointArray => Value::Array(Decode::decode(arg)?),
-            PgType::LsegArray => Value::Array(Decode::decode(arg)?),
-            PgType::PathArray => Value::Array(Decode::decode(arg)?),
-            PgType::BoxArray => Value::Array(Decode::decode(arg)?),
-            PgType::Float4Array => Value::Array(Decode::decode(arg)?),
-            PgType::Float8Array => Value::Array(Decode::decode(arg)?),
-            PgType::PolygonArray => Value::Array(Decode::decode(arg)?),
-            PgType::OidArray => Value::Array(Decode::decode(arg)?),
-            PgType::MacaddrArray => Value::Array(Decode::decode(arg)?),
-            PgType::InetArray => Value::Array(Decode::decode(arg)?),
-            PgType::TimestampArray => Value::Array(Decode::decode(arg)?),
-            PgType::DateArray => Value::Array(Decode::decode(arg)?),
-            PgType::TimeArray => Value::Array(Decode::decode(arg)?),
-            PgType::TimestamptzArray => Value::Array(Decode::decode(arg)?),
-            PgType::IntervalArray => Value::Array(Decode::decode(arg)?),
-            PgType::NumericArray => Value::Array(Decode::decode(arg)?),
-            PgType::TimetzArray => Value::Array(Decode::decode(arg)?),
-            PgType::BitArray => Value::Array(Decode::decode(arg)?),
-            PgType::VarbitArray => Value::Array(Decode::decode(arg)?),
-            PgType::RecordArray => Value::Array(Decode::decode(arg)?),
-            PgType::UuidArray => Value::Array(Decode::decode(arg)?),
-            PgType::JsonbArray => Value::Array(Decode::decode(arg)?),
-            PgType::Int4RangeArray => Value::Array(Decode::decode(arg)?),
-            PgType::NumRangeArray => Value::Array(Decode::decode(arg)?),
-            PgType::TsRangeArray => Value::Array(Decode::decode(arg)?),
-            PgType::TstzRangeArray => Value::Array(Decode::decode(arg)?),
-            PgType::DateRangeArray => Value::Array(Decode::decode(arg)?),
-            PgType::Int8RangeArray => Value::Array(Decode::decode(arg)?),
-            PgType::JsonpathArray => Value::Array(Decode::decode(arg)?),
-            PgType::MoneyArray => Value::Array(Decode::decode(arg)?),
+            PgType::JsonArray => array::decode_value_array(arg)?,
+            PgType::LineArray => array::decode_value_array(arg)?,
+            PgType::CidrArray => array::decode_value_array(arg)?,
+            PgType::CircleArray => array::decode_value_array(arg)?,
+            PgType::Macaddr8Array => array::decode_value_array(arg)?,
+            PgType::BoolArray => array::decode_value_array(arg)?,
+            PgType::ByteaArray => array::decode_value_array(arg)?,
+            PgType::CharArray => array::decode_value_array(arg)?,
+            PgType::NameArray => array::decode_value_array(arg)?,
+            PgType::Int2Array => array::decode_value_array(arg)?,
+            PgType::Int4Array => array::decode_value_array(arg)?,
+            PgType::TextArray => array::decode_value_array(arg)?,
+            PgType::BpcharArray => array::decode_value_array(arg)?,
+            PgType::VarcharArray => array::decode_value_array(arg)?,
+            PgType::Int8Array => array::decode_value_array(arg)?,
+            PgType::PointArray => array::decode_value_array(arg)?,
+            PgType::LsegArray => array::decode_value_array(arg)?,
+            PgType::PathArray => array::decode_value_array(arg)?,
+            PgType::BoxArray => array::decode_value_array(arg)?,
+            PgType::Float4Array => array::decode_value_array(arg)?,
+            PgType::Float8Array => array::decode_value_array(arg)?,
+            PgType::PolygonArray => array::decode_value_array(arg)?,
+            PgType::OidArray => array::decode_value_array(arg)?,
+            PgType::MacaddrArray => array::decode_value_array(arg)?,
+            PgType::InetArray => array::decode_value_array(arg)?,
+            PgType::TimestampArray => array::decode_value_array(arg)?,
+            PgType::DateArray => array::decode_value_array(arg)?,
+            PgType::TimeArray => array::decode_value_array(arg)?,
+            PgType::TimestamptzArray => array::decode_value_array(arg)?,
+            PgType::IntervalArray => array::decode_value_array(arg)?,
+            PgType::NumericArray => array::decode_value_array(arg)?,
+            PgType::TimetzArray => array::decode_value_array(arg)?,
+            PgType::BitArray => array::decode_value_array(arg)?,
+            PgType::VarbitArray => array::decode_value_array(arg)?,
+            PgType::RecordArray => array::decode_value_array(arg)?,
+            PgType::UuidArray => array::decode_value_array(arg)?,
+            PgType::JsonbArray => array::decode_value_array(arg)?,
+            PgType::Int4RangeArray => array::decode_value_array(arg)?,
+            PgType::NumRangeArray => array::decode_value_array(arg)?,
+            PgType::TsRangeArray => array::decode_value_array(arg)?,
+            PgType::TstzRangeArray => array::decode_value_array(arg)?,
+            PgType::DateRangeArray => array::decode_value_array(arg)?,
+            PgType::Int8RangeArray => array::decode_value_array(arg)?,
+            PgType::JsonpathArray => array::decode_value_array(arg)?,
+            PgType::MoneyArray => array::decode_value_array(arg)?,
         })
     }
 }
 
+/// decode user-defined types: arrays of them, enum labels and domains over a base type
+fn decode_custom(arg: PgValue) -> Result<Value, Error> {
+    let kind = arg.type_info.kind().clone();
+    Ok(match kind {
+        PgTypeKind::Array(_) => array::decode_value_array(arg)?,
+        PgTypeKind::Enum(_) => Value::String(Decode::decode(arg)?),
+        PgTypeKind::Domain(base) => Value::decode(PgValue {
+            value: arg.value,
+            type_info: base,
+            format: arg.format,
+        })?,
+        _ => Value::Ext(
+            "Custom",
+            Box::new(Value::Binary({
+                match arg.format() {
+                    PgValueFormat::Binary => arg.as_bytes()?.to_owned(),
+                    PgValueFormat::Text => arg.as_str()?.as_bytes().to_vec(),
+                }
+            })),
+        ),
+    })
+}
+
 impl Encode for Value {
     fn encode(self, buf: &mut PgArgumentBuffer) -> Result<IsNull, Error> {
         Ok(match self {
@@ -491,9 +503,9 @@ impl Encode for Value {
                     "Bytea" => Bytea(v.as_u64().unwrap_or_default() as u8).encode(buf)?,
                     "Char" => v.into_string().unwrap_or_default().encode(buf)?,
                     "Name" => v.into_string().unwrap_or_default().encode(buf)?,
-                    "Int8" => (v.as_i64().unwrap_or_default() as i32).encode(buf)?,
-                    "Int2" => (v.as_i64().unwrap_or_default() as i8).encode(buf)?,
-                    "Int4" => (v.as_i64().unwrap_or_default() as i16).encode(buf)?,
+                    "Int8" => v.as_i64().unwrap_or_default().encode(buf)?,
+                    "Int2" => (v.as_i64().unwrap_or_default() as i16).encode(buf)?,
+                    "Int4" => (v.as_i64().unwrap_or_default() as i32).encode(buf)?,
                     "Text" => v.into_string().unwrap_or_default().encode(buf)?,
                     "Oid" => Oid::from(v.as_u64().unwrap_or_default() as u32).encode(buf)?,
                     "Json" => Json(v.into_string().unwrap_or_default()).encode(buf)?,
@@ -541,7 +553,24 @@ impl Encode for Value {
                     "Custom" => v.into_bytes().unwrap_or_default().encode(buf)?,
                     "DeclareWithName" => v.into_bytes().unwrap_or_default().encode(buf)?,
                     "DeclareWithOid" => v.into_bytes().unwrap_or_default().encode(buf)?,
-                    _ => IsNull::Yes,
+                    "Enum" => v.into_string().unwrap_or_default().encode(buf)?,
+                    _ => match array::array_type_by_name(type_name) {
+                        Some(array_type) => match *v {
+                            Value::Null => IsNull::Yes,
+                            Value::Array(arr) => array::encode_array(
+                                arr,
+                                array_type.try_array_element().unwrap().into_owned(),
+                                buf,
+                            )?,
+                            v => {
+                                return Err(Error::from(format!(
+                                    "{} must be an array, but got {}",
+                                    type_name, v
+                                )));
+                            }
+                        },
+                        None => IsNull::Yes,
+                    },
                 }
             }
         })
@@ -0,0 +1,427 @@
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::ptr::{null, null_mut};
+use std::slice;
+use std::time::Duration;
+
+use libsqlite3_sys::{
+    sqlite3, sqlite3_backup_finish, sqlite3_backup_init, sqlite3_backup_pagecount,
+    sqlite3_backup_remaining, sqlite3_backup_step, sqlite3_deserialize, sqlite3_free,
+    sqlite3_int64, sqlite3_malloc64, sqlite3_open_v2, sqlite3_serialize, sqlite3_sleep,
+    SQLITE_BUSY, SQLITE_DESERIALIZE_FREEONCLOSE, SQLITE_DESERIALIZE_RESIZEABLE, SQLITE_DONE,
+    SQLITE_LOCKED, SQLITE_OK, SQLITE_OPEN_CREATE, SQLITE_OPEN_NOMUTEX, SQLITE_OPEN_READWRITE,
+};
+use rbdc::err_protocol;
+use rbdc::error::Error;
+use rbs::Value;
+
+use crate::connection::handle::{ConnectionHandle, ConnectionHandleRaw};
+use crate::connection::ConnectionState;
+use crate::{SqliteConnection, SqliteError};
+use rbdc::db::Connection;
+
+// time to wait when the source or the destination is locked by another connection
+const BACKUP_BUSY_SLEEP_MS: c_int = 10;
+
+/// progress of [`SqliteConnection::backup_to_with_progress()`], reported after every step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupProgress {
+    /// pages still to copy
+    pub remaining: i32,
+    /// pages of the source database
+    pub page_count: i32,
+}
+
+/// the destination of a backup: a database file or another open connection
+#[derive(Debug)]
+pub enum BackupTarget<'a> {
+    Path(PathBuf),
+    Connection(&'a mut SqliteConnection),
+}
+
+impl From<&str> for BackupTarget<'_> {
+    fn from(value: &str) -> Self {
+        Self::Path(PathBuf::from(value))
+    }
+}
+
+impl From<String> for BackupTarget<'_> {
+    fn from(value: String) -> Self {
+        Self::Path(PathBuf::from(value))
+    }
+}
+
+impl From<&Path> for BackupTarget<'_> {
+    fn from(value: &Path) -> Self {
+        Self::Path(value.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for BackupTarget<'_> {
+    fn from(value: PathBuf) -> Self {
+        Self::Path(value)
+    }
+}
+
+impl<'a> From<&'a mut SqliteConnection> for BackupTarget<'a> {
+    fn from(value: &'a mut SqliteConnection) -> Self {
+        Self::Connection(value)
+    }
+}
+
+/// the destination, as sent to the worker thread of the source
+#[derive(Debug)]
+pub struct BackupDest(Dest);
+
+#[derive(Debug)]
+enum Dest {
+    Path(CString),
+    /// the handle is locked out of its own worker until the backup is done
+    Handle(ConnectionHandleRaw),
+}
+
+pub type BackupProgressFn = dyn FnMut(BackupProgress) + Send;
+
+impl SqliteConnection {
+    /// Copy the `main` database to a file or another connection with the
+    /// [online backup API](https://www.sqlite.org/backup.html), while this connection stays usable.
+    ///
+    /// The backup copies `pages_per_step` pages at a time (-1 for all pages at once)
+    /// and runs on the worker thread, so it does not block the async runtime.
+    /// A file that exists is overwritten.
+    ///
+    /// While the source or the destination is locked by another connection the step is retried,
+    /// the backup fails with `SQLITE_BUSY` when no page could be copied for
+    /// [`busy_timeout`](crate::SqliteConnectOptions::busy_timeout).
+    pub async fn backup_to<'a>(
+        &mut self,
+        target: impl Into<BackupTarget<'a>>,
+        pages_per_step: i32,
+    ) -> Result<(), Error> {
+        self.backup_to_with_progress(target, pages_per_step, |_| {})
+            .await
+    }
+
+    /// Like [`backup_to()`][Self::backup_to], calling `progress` after every step.
+    pub async fn backup_to_with_progress<'a>(
+        &mut self,
+        target: impl Into<BackupTarget<'a>>,
+        pages_per_step: i32,
+        progress: impl FnMut(BackupProgress) + Send + 'static,
+    ) -> Result<(), Error> {
+        match target.into() {
+            BackupTarget::Path(path) => {
+                let path = path
+                    .to_str()
+                    .ok_or_else(|| err_protocol!("invalid backup path: {:?}", path))?;
+                let path = CString::new(path)
+                    .map_err(|_| err_protocol!("invalid backup path: {:?}", path))?;
+                self.worker
+                    .backup(
+                        BackupDest(Dest::Path(path)),
+                        pages_per_step,
+                        Box::new(progress),
+                    )
+                    .await
+            }
+            BackupTarget::Connection(dest) => {
+                let mut locked = dest.lock_handle().await?;
+                // statements of the destination must be finalized before its pages are replaced
+                locked.guard.statements.clear();
+                let dest = BackupDest(Dest::Handle(locked.guard.handle.to_raw()));
+                let r = self
+                    .worker
+                    .backup(dest, pages_per_step, Box::new(progress))
+                    .await;
+                drop(locked);
+                r
+            }
+        }
+    }
+
+    /// Write a compacted copy of the `main` database to a new file with `VACUUM INTO`.
+    /// The file must not exist.
+    pub async fn vacuum_into(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path
+            .as_ref()
+            .to_str()
+            .ok_or_else(|| err_protocol!("invalid vacuum path: {:?}", path.as_ref()))?
+            .to_string();
+        self.exec("VACUUM INTO ?", vec![Value::String(path)])
+            .await?;
+        Ok(())
+    }
+
+    /// Copy the `main` database into bytes, the same bytes as the database file would have.
+    ///
+    /// See [`sqlite3_serialize()`](https://www.sqlite.org/c3ref/serialize.html).
+    pub async fn serialize(&mut self) -> Result<Vec<u8>, Error> {
+        self.worker.serialize().await
+    }
+
+    /// Replace the `main` database with the bytes made by [`serialize()`][Self::serialize].
+    /// The database is then held in memory, changes are not written to the original file.
+    ///
+    /// See [`sqlite3_deserialize()`](https://www.sqlite.org/c3ref/deserialize.html).
+    pub async fn deserialize(&mut self, data: Vec<u8>) -> Result<(), Error> {
+        self.worker.deserialize(data).await
+    }
+}
+
+pub(crate) fn backup(
+    src: &mut ConnectionHandle,
+    dest: BackupDest,
+    pages_per_step: i32,
+    busy_timeout: Duration,
+    progress: &mut BackupProgressFn,
+) -> Result<(), Error> {
+    match dest.0 {
+        Dest::Path(path) => {
+            let mut handle = null_mut();
+            // https://www.sqlite.org/c3ref/open.html
+            let status = unsafe {
+                sqlite3_open_v2(
+                    path.as_ptr(),
+                    &mut handle,
+                    SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE | SQLITE_OPEN_NOMUTEX,
+                    null(),
+                )
+            };
+            if handle.is_null() {
+                return Err(Error::from(
+                    "SQLite is unable to allocate memory to hold the sqlite3 object",
+                ));
+            }
+            // closes the destination on return
+            let handle = unsafe { ConnectionHandle::new(handle) };
+            if status != SQLITE_OK {
+                return Err(Error::from(SqliteError::new(handle.as_ptr())));
+            }
+            backup_handle(
+                src.as_ptr(),
+                handle.as_ptr(),
+                pages_per_step,
+                busy_timeout,
+                progress,
+            )
+        }
+        Dest::Handle(handle) => backup_handle(
+            src.as_ptr(),
+            handle.as_ptr(),
+            pages_per_step,
+            busy_timeout,
+            progress,
+        ),
+    }
+}
+
+fn backup_handle(
+    src: *mut sqlite3,
+    dest: *mut sqlite3,
+    pages_per_step: i32,
+    busy_timeout: Duration,
+    progress: &mut BackupProgressFn,
+) -> Result<(), Error> {
+    let main = c"main";
+    // https://www.sqlite.org/c3ref/backup_finish.html
+    let backup = unsafe { sqlite3_backup_init(dest, main.as_ptr(), src, main.as_ptr()) };
+    if backup.is_null() {
+        // the error is on the destination handle
+        return Err(Error::from(SqliteError::new(dest)));
+    }
+    let mut status;
+    // time slept since the last step that copied pages
+    let mut busy_ms: u128 = 0;
+    let mut timed_out = None;
+    loop {
+        status = unsafe { sqlite3_backup_step(backup, pages_per_step) };
+        match status {
+            SQLITE_OK | SQLITE_BUSY | SQLITE_LOCKED | SQLITE_DONE => {
+                progress(BackupProgress {
+                    remaining: unsafe { sqlite3_backup_remaining(backup) },
+                    page_count: unsafe { sqlite3_backup_pagecount(backup) },
+                });
+                if status == SQLITE_DONE {
+                    break;
+                }
+                if status == SQLITE_OK {
+                    busy_ms = 0;
+                    continue;
+                }
+                if busy_ms >= busy_timeout.as_millis() {
+                    timed_out = Some(if status == SQLITE_LOCKED {
+                        "SQLITE_LOCKED"
+                    } else {
+                        "SQLITE_BUSY"
+                    });
+                    break;
+                }
+                unsafe { sqlite3_sleep(BACKUP_BUSY_SLEEP_MS) };
+                busy_ms += BACKUP_BUSY_SLEEP_MS as u128;
+            }
+            _ => break,
+        }
+    }
+    // the error of a failed step is returned again by sqlite3_backup_finish()
+    let status = unsafe { sqlite3_backup_finish(backup) };
+    if let Some(reason) = timed_out {
+        return Err(Error::from(format!(
+            "backup timed out after {:?}: {}, the database is locked by another connection",
+            busy_timeout, reason
+        )));
+    }
+    if status == SQLITE_OK {
+        Ok(())
+    } else {
+        Err(Error::from(SqliteError::new(dest)))
+    }
+}
+
+pub(crate) fn serialize(handle: &mut ConnectionHandle) -> Result<Vec<u8>, Error> {
+    let mut size: sqlite3_int64 = 0;
+    let ptr = unsafe { sqlite3_serialize(handle.as_ptr(), c"main".as_ptr(), &mut size, 0) };
+    if ptr.is_null() {
+        // a database without pages serializes to nothing
+        if size == 0 {
+            return Ok(vec![]);
+        }
+        return Err(Error::from(SqliteError::new(handle.as_ptr())));
+    }
+    let data = unsafe { slice::from_raw_parts(ptr, size as usize) }.to_vec();
+    unsafe { sqlite3_free(ptr as *mut _) };
+    Ok(data)
+}
+
+pub(crate) fn deserialize(conn: &mut ConnectionState, data: Vec<u8>) -> Result<(), Error> {
+    // the schema changes under the prepared statements
+    conn.statements.clear();
+    let len = data.len() as sqlite3_int64;
+    // sqlite owns the buffer, it must be allocated by sqlite
+    let ptr = unsafe { sqlite3_malloc64(data.len().max(1) as u64) } as *mut u8;
+    if ptr.is_null() {
+        return Err(Error::from("SQLite is unable to allocate memory"));
+    }
+    unsafe { ptr.copy_from_nonoverlapping(data.as_ptr(), data.len()) };
+    // the buffer is freed by sqlite, also if this fails
+    let status = unsafe {
+        sqlite3_deserialize(
+            conn.handle.as_ptr(),
+            c"main".as_ptr(),
+            ptr,
+            len,
+            len,
+            (SQLITE_DESERIALIZE_FREEONCLOSE | SQLITE_DESERIALIZE_RESIZEABLE) as u32,
+        )
+    };
+    if status == SQLITE_OK {
+        Ok(())
+    } else {
+        Err(Error::from(SqliteError::new(conn.handle.as_ptr())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::connection::backup::BackupProgress;
+    use crate::SqliteConnectOptions;
+    use rbdc::db::Connection;
+    use rbs::Value;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_backup_serialize() {
+        let mut conn = SqliteConnectOptions::new().connect().await.unwrap();
+        conn.exec("create table t (id integer primary key, name text)", vec![])
+            .await
+            .unwrap();
+        for i in 0..100 {
+            conn.exec(
+                "insert into t (name) values (?)",
+                vec![Value::String(format!("name-{}", i))],
+            )
+            .await
+            .unwrap();
+        }
+
+        let data = conn.serialize().await.unwrap();
+        assert!(!data.is_empty());
+        let mut copy = SqliteConnectOptions::new().connect().await.unwrap();
+        copy.deserialize(data).await.unwrap();
+        let v = copy
+            .get_values("select count(1) as c from t", vec![])
+            .await
+            .unwrap();
+        assert_eq!(v[0]["c"], Value::I64(100));
+
+        let mut dest = SqliteConnectOptions::new().connect().await.unwrap();
+        let steps = Arc::new(Mutex::new(Vec::<BackupProgress>::new()));
+        let steps2 = steps.clone();
+        conn.backup_to_with_progress(&mut dest, 1, move |p| steps2.lock().unwrap().push(p))
+            .await
+            .unwrap();
+        let steps = steps.lock().unwrap().clone();
+        assert!(steps.len() > 1);
+        assert_eq!(steps.last().unwrap().remaining, 0);
+        let v = dest
+            .get_values("select name from t where id = 100", vec![])
+            .await
+            .unwrap();
+        assert_eq!(v[0]["name"], Value::String("name-99".to_string()));
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("rbdc-sqlite-backup-{}.db", std::process::id()));
+        let vacuum = dir.join(format!("rbdc-sqlite-vacuum-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&vacuum);
+        conn.backup_to(path.as_path(), -1).await.unwrap();
+        conn.vacuum_into(&vacuum).await.unwrap();
+        for p in [&path, &vacuum] {
+            let mut file = SqliteConnectOptions::new()
+                .filename(p)
+                .connect()
+                .await
+                .unwrap();
+            let v = file
+                .get_values("select count(1) as c from t", vec![])
+                .await
+                .unwrap();
+            assert_eq!(v[0]["c"], Value::I64(100));
+            file.close().await.unwrap();
+            let _ = std::fs::remove_file(p);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backup_busy_timeout() {
+        let path = std::env::temp_dir().join(format!(
+            "rbdc-sqlite-backup-busy-{}.db",
+            std::process::id()
+        ));
+        let mut lock = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .connect()
+            .await
+            .unwrap();
+        lock.exec("create table t (id integer primary key)", vec![])
+            .await
+            .unwrap();
+        lock.exec("begin exclusive", vec![]).await.unwrap();
+
+        let mut conn = SqliteConnectOptions::new()
+            .busy_timeout(Duration::from_millis(100))
+            .connect()
+            .await
+            .unwrap();
+        conn.exec("create table t (id integer primary key)", vec![])
+            .await
+            .unwrap();
+        let e = conn.backup_to(path.as_path(), -1).await.unwrap_err();
+        assert!(e.to_string().contains("backup timed out"), "{}", e);
+
+        lock.exec("rollback", vec![]).await.unwrap();
+        lock.close().await.unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+}
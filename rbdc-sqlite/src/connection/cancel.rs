@@ -0,0 +1,41 @@
+use crate::connection::worker::WorkerSharedState;
+use crate::connection::ConnectionHandleRaw;
+use futures_core::future::BoxFuture;
+use libsqlite3_sys::sqlite3_interrupt;
+use rbdc::db::CancelHandle;
+use rbdc::error::Error;
+use std::sync::Arc;
+
+/// interrupts the running statement of a connection with `sqlite3_interrupt`
+pub struct SqliteCancelHandle {
+    handle_raw: ConnectionHandleRaw,
+    // keeps the database handle open while the cancel handle exists
+    _shared: Arc<WorkerSharedState>,
+}
+
+// SAFETY: `sqlite3_interrupt` may be called from any thread
+// <https://www.sqlite.org/c3ref/interrupt.html>,
+// and `_shared` keeps the handle from being closed.
+unsafe impl Send for SqliteCancelHandle {}
+unsafe impl Sync for SqliteCancelHandle {}
+
+impl SqliteCancelHandle {
+    pub(crate) fn new(handle_raw: ConnectionHandleRaw, shared: Arc<WorkerSharedState>) -> Self {
+        Self {
+            handle_raw,
+            _shared: shared,
+        }
+    }
+
+    pub fn interrupt(&self) {
+        // SAFETY: the handle is open, see above
+        unsafe { sqlite3_interrupt(self.handle_raw.as_ptr()) }
+    }
+}
+
+impl CancelHandle for SqliteCancelHandle {
+    fn cancel(&self) -> BoxFuture<'static, Result<(), Error>> {
+        self.interrupt();
+        Box::pin(async { Ok(()) })
+    }
+}
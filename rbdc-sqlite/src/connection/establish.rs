@@ -1,14 +1,18 @@
 use crate::connection::handle::ConnectionHandle;
+use crate::connection::hook::{self, SqliteChangeSender};
+use crate::connection::worker_pool::SqliteWorkerPool;
 use crate::connection::{ConnectionState, Statements};
 use crate::{SqliteConnectOptions, SqliteError};
 use libsqlite3_sys::{
-    sqlite3_busy_timeout, sqlite3_extended_result_codes, sqlite3_open_v2, SQLITE_OK,
+    sqlite3_busy_timeout, sqlite3_db_config, sqlite3_extended_result_codes, sqlite3_free,
+    sqlite3_load_extension, sqlite3_open_v2, SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION, SQLITE_OK,
     SQLITE_OPEN_CREATE, SQLITE_OPEN_FULLMUTEX, SQLITE_OPEN_MEMORY, SQLITE_OPEN_NOMUTEX,
     SQLITE_OPEN_PRIVATECACHE, SQLITE_OPEN_READONLY, SQLITE_OPEN_READWRITE, SQLITE_OPEN_SHAREDCACHE,
 };
 use rbdc::error::Error;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::io;
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr::{null, null_mut};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
@@ -18,10 +22,15 @@ static THREAD_ID: AtomicU64 = AtomicU64::new(0);
 pub struct EstablishParams {
     filename: CString,
     open_flags: i32,
-    busy_timeout: Duration,
+    pub(crate) busy_timeout: Duration,
     statement_cache_capacity: usize,
+    change_sender: Option<SqliteChangeSender>,
+    extensions: Vec<(CString, Option<CString>)>,
     pub(crate) thread_name: String,
     pub(crate) command_channel_size: usize,
+    pub(crate) worker_pool: Option<SqliteWorkerPool>,
+    // the database file, to keep the connections of one database on different pool threads
+    pub(crate) database: Option<String>,
 }
 
 impl EstablishParams {
@@ -70,6 +79,17 @@ impl EstablishParams {
             flags |= libsqlite3_sys::SQLITE_OPEN_URI;
         }
 
+        // a private in-memory database is never opened by another connection
+        let database = if filename == ":memory:" || (options.in_memory && !options.shared_cache) {
+            None
+        } else {
+            Some(
+                std::fs::canonicalize(&filename)
+                    .map(|v| v.to_string_lossy().into_owned())
+                    .unwrap_or_else(|_| filename.clone()),
+            )
+        };
+
         let filename = CString::new(filename).map_err(|_| {
             io::Error::new(
                 io::ErrorKind::InvalidData,
@@ -77,13 +97,31 @@ impl EstablishParams {
             )
         })?;
 
+        let mut extensions = Vec::with_capacity(options.extensions.len());
+        for (path, entry_point) in &options.extensions {
+            let path = CString::new(path.as_str())
+                .map_err(|_| Error::from(format!("invalid extension path: {:?}", path)))?;
+            let entry_point =
+                match entry_point {
+                    Some(v) => Some(CString::new(v.as_str()).map_err(|_| {
+                        Error::from(format!("invalid extension entry point: {:?}", v))
+                    })?),
+                    None => None,
+                };
+            extensions.push((path, entry_point));
+        }
+
         Ok(Self {
             filename,
             open_flags: flags,
             busy_timeout: options.busy_timeout,
             statement_cache_capacity: options.statement_cache_capacity,
+            change_sender: options.change_sender.clone(),
+            extensions,
             thread_name: (options.thread_name)(THREAD_ID.fetch_add(1, Ordering::AcqRel)),
             command_channel_size: options.command_channel_size,
+            worker_pool: options.worker_pool.clone(),
+            database,
         })
     }
 
@@ -132,9 +170,77 @@ impl EstablishParams {
             return Err(Error::from(SqliteError::new(handle.as_ptr())));
         }
 
+        if !self.extensions.is_empty() {
+            self.load_extensions(&handle)?;
+        }
+
+        let change_sender = self.change_sender.clone().map(Box::new);
+        if let Some(sender) = &change_sender {
+            // SAFE: the sender is kept in the ConnectionState and dropped after the handle
+            unsafe { hook::register(handle.as_ptr(), &**sender) };
+        }
+
         Ok(ConnectionState {
             handle,
             statements: Statements::new(self.statement_cache_capacity),
+            change_sender,
         })
     }
+
+    fn load_extensions(&self, handle: &ConnectionHandle) -> Result<(), Error> {
+        // enable the C API only, not the SQL function load_extension()
+        // https://www.sqlite.org/c3ref/c_dbconfig_defensive.html#sqlitedbconfigenableloadextension
+        let status = unsafe {
+            sqlite3_db_config(
+                handle.as_ptr(),
+                SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION,
+                1 as c_int,
+                null_mut::<c_int>(),
+            )
+        };
+        if status != SQLITE_OK {
+            return Err(Error::from(SqliteError::new(handle.as_ptr())));
+        }
+
+        let mut result = Ok(());
+        for (path, entry_point) in &self.extensions {
+            let mut err: *mut c_char = null_mut();
+            // https://www.sqlite.org/c3ref/load_extension.html
+            let status = unsafe {
+                sqlite3_load_extension(
+                    handle.as_ptr(),
+                    path.as_ptr(),
+                    entry_point.as_ref().map_or(null(), |x| x.as_ptr()),
+                    &mut err,
+                )
+            };
+            if status != SQLITE_OK {
+                let msg = if err.is_null() {
+                    "unknown error".to_string()
+                } else {
+                    let msg = unsafe { CStr::from_ptr(err) }
+                        .to_string_lossy()
+                        .into_owned();
+                    unsafe { sqlite3_free(err as *mut c_void) };
+                    msg
+                };
+                result = Err(Error::from(format!(
+                    "failed to load extension {:?}: {}",
+                    path, msg
+                )));
+                break;
+            }
+        }
+
+        // disable loading again, whether the extensions loaded or not
+        unsafe {
+            sqlite3_db_config(
+                handle.as_ptr(),
+                SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION,
+                0 as c_int,
+                null_mut::<c_int>(),
+            )
+        };
+        result
+    }
 }
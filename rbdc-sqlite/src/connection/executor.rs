@@ -1,33 +1,42 @@
 use crate::query::SqliteQuery;
-use crate::{SqliteConnection, SqliteQueryResult, SqliteRow, SqliteStatement, SqliteTypeInfo};
+use crate::{
+    SqliteConnection, SqliteDateTimeFormat, SqliteQueryResult, SqliteRow, SqliteStatement,
+    SqliteTypeInfo,
+};
 use either::Either;
 use futures_core::future::BoxFuture;
 use futures_core::stream::BoxStream;
 use futures_util::{TryFutureExt, TryStreamExt};
 use rbdc::error::Error;
+use rbs::Value;
 
 impl SqliteConnection {
     pub fn fetch_many(
         &mut self,
-        query: SqliteQuery,
+        mut query: SqliteQuery,
     ) -> BoxStream<'_, Result<Either<SqliteQueryResult, SqliteRow>, Error>> {
         let sql = query.sql().to_string();
         let persistent = query.persistent() && !query.arguments.is_empty();
+        let format = self.datetime_format;
+        query.arguments = encode_datetimes(query.arguments, format);
         let arguments = query.take_arguments();
         Box::pin(
             self.worker
                 .execute(sql, arguments, self.row_channel_size, persistent)
                 .map_ok(flume::Receiver::into_stream)
-                .try_flatten_stream(),
+                .try_flatten_stream()
+                .map_ok(move |step| step.map_right(|row| with_format(row, format))),
         )
     }
 
     pub fn fetch_optional(
         &mut self,
-        query: SqliteQuery,
+        mut query: SqliteQuery,
     ) -> BoxFuture<'_, Result<Option<SqliteRow>, Error>> {
         let sql = query.sql().to_owned();
         let persistent = query.persistent() && !query.arguments.is_empty();
+        let format = self.datetime_format;
+        query.arguments = encode_datetimes(query.arguments, format);
         let arguments = query.take_arguments();
         Box::pin(async move {
             let stream = self
@@ -40,7 +49,7 @@ impl SqliteConnection {
 
             while let Some(res) = stream.try_next().await? {
                 if let Either::Right(row) = res {
-                    return Ok(Some(row));
+                    return Ok(Some(with_format(row, format)));
                 }
             }
 
@@ -63,3 +72,12 @@ impl SqliteConnection {
         })
     }
 }
+
+fn encode_datetimes(arguments: Vec<Value>, format: SqliteDateTimeFormat) -> Vec<Value> {
+    arguments.into_iter().map(|v| format.encode(v)).collect()
+}
+
+fn with_format(mut row: SqliteRow, format: SqliteDateTimeFormat) -> SqliteRow {
+    row.datetime_format = format;
+    row
+}
@@ -0,0 +1,391 @@
+use std::any::Any;
+use std::ffi::CString;
+use std::fmt::{self, Debug, Formatter};
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use libsqlite3_sys::{
+    sqlite3_aggregate_context, sqlite3_context, sqlite3_create_function_v2, sqlite3_result_blob64,
+    sqlite3_result_double, sqlite3_result_error, sqlite3_result_int64, sqlite3_result_null,
+    sqlite3_result_text64, sqlite3_user_data, sqlite3_value, sqlite3_value_blob,
+    sqlite3_value_bytes, sqlite3_value_double, sqlite3_value_int64, sqlite3_value_text,
+    sqlite3_value_type, SQLITE_BLOB, SQLITE_DETERMINISTIC, SQLITE_FLOAT, SQLITE_INTEGER, SQLITE_OK,
+    SQLITE_TEXT, SQLITE_TRANSIENT, SQLITE_UTF8,
+};
+use rbdc::err_protocol;
+use rbdc::error::Error;
+use rbs::Value;
+
+use crate::connection::handle::ConnectionHandle;
+use crate::SqliteError;
+
+pub type ScalarFn = dyn Fn(&[Value]) -> Result<Value, Error> + Send + Sync;
+
+type State = Box<dyn Any + Send>;
+
+type StepFn = dyn Fn(&mut State, &[Value]) -> Result<(), Error> + Send + Sync;
+
+// the type of the aggregate state is erased, see `Function::aggregate`
+struct Aggregate {
+    init: Box<dyn Fn() -> State + Send + Sync>,
+    step: Box<StepFn>,
+    finalize: Box<dyn Fn(State) -> Result<Value, Error> + Send + Sync>,
+}
+
+#[derive(Clone)]
+enum Kind {
+    Scalar(Arc<ScalarFn>),
+    Aggregate(Arc<Aggregate>),
+}
+
+/// A user defined SQL function, see [`SqliteConnectOptions::function()`](crate::SqliteConnectOptions::function)
+/// and [`SqliteConnectOptions::aggregate()`](crate::SqliteConnectOptions::aggregate).
+///
+/// The arguments are `Value::Null`, `Value::I64`, `Value::F64`, `Value::String` or `Value::Binary`.
+#[derive(Clone)]
+pub struct Function {
+    name: Arc<str>,
+    n_args: i32,
+    deterministic: bool,
+    kind: Kind,
+}
+
+impl Function {
+    /// a scalar function, `n_args` is -1 for any number of arguments
+    pub fn scalar<N, F>(name: N, n_args: i32, deterministic: bool, f: F) -> Self
+    where
+        N: Into<Arc<str>>,
+        F: Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        Function {
+            name: name.into(),
+            n_args,
+            deterministic,
+            kind: Kind::Scalar(Arc::new(f)),
+        }
+    }
+
+    /// an aggregate function. every group starts with the state made by `init`,
+    /// `step` is called for each row and `finalize` makes the result of the group
+    pub fn aggregate<N, S, I, T, F>(name: N, n_args: i32, init: I, step: T, finalize: F) -> Self
+    where
+        N: Into<Arc<str>>,
+        S: Send + 'static,
+        I: Fn() -> S + Send + Sync + 'static,
+        T: Fn(&mut S, &[Value]) -> Result<(), Error> + Send + Sync + 'static,
+        F: Fn(S) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        let aggregate = Aggregate {
+            init: Box::new(move || Box::new(init())),
+            step: Box::new(move |state, args| match state.downcast_mut::<S>() {
+                Some(state) => step(state, args),
+                None => Err(Error::from("invalid aggregate state")),
+            }),
+            finalize: Box::new(move |state| match state.downcast::<S>() {
+                Ok(state) => finalize(*state),
+                Err(_) => Err(Error::from("invalid aggregate state")),
+            }),
+        };
+        Function {
+            name: name.into(),
+            n_args,
+            deterministic: false,
+            kind: Kind::Aggregate(Arc::new(aggregate)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn create(&self, handle: &mut ConnectionHandle) -> Result<(), Error> {
+        let c_name = CString::new(&*self.name)
+            .map_err(|_| err_protocol!("invalid function name: {:?}", self.name))?;
+        let mut flags = SQLITE_UTF8;
+        if self.deterministic {
+            flags |= SQLITE_DETERMINISTIC;
+        }
+        // the user data is a boxed Arc of the function, freed by xDestroy
+        let r = match &self.kind {
+            Kind::Scalar(f) => unsafe {
+                // https://www.sqlite.org/c3ref/create_function.html
+                sqlite3_create_function_v2(
+                    handle.as_ptr(),
+                    c_name.as_ptr(),
+                    self.n_args,
+                    flags,
+                    Box::into_raw(Box::new(Arc::clone(f))) as *mut c_void,
+                    Some(call_scalar),
+                    None,
+                    None,
+                    Some(drop_user_data::<Arc<ScalarFn>>),
+                )
+            },
+            Kind::Aggregate(f) => unsafe {
+                sqlite3_create_function_v2(
+                    handle.as_ptr(),
+                    c_name.as_ptr(),
+                    self.n_args,
+                    flags,
+                    Box::into_raw(Box::new(Arc::clone(f))) as *mut c_void,
+                    None,
+                    Some(call_step),
+                    Some(call_final),
+                    Some(drop_user_data::<Arc<Aggregate>>),
+                )
+            },
+        };
+        if r == SQLITE_OK {
+            Ok(())
+        } else {
+            // Unlike sqlite3_create_collation_v2(), xDestroy is called if this fails.
+            Err(Error::from(SqliteError::new(handle.as_ptr())))
+        }
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .field("n_args", &self.n_args)
+            .field("deterministic", &self.deterministic)
+            .finish_non_exhaustive()
+    }
+}
+
+unsafe extern "C" fn drop_user_data<T>(p: *mut c_void) {
+    drop(Box::from_raw(p as *mut T));
+}
+
+unsafe fn user_data<'a, T: ?Sized>(ctx: *mut sqlite3_context) -> &'a T {
+    let p = sqlite3_user_data(ctx) as *const Arc<T>;
+    debug_assert!(!p.is_null());
+    &*p
+}
+
+unsafe extern "C" fn call_scalar(
+    ctx: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let f = user_data::<ScalarFn>(ctx);
+    let args = values(argc, argv);
+    // a panic must not unwind into sqlite
+    match catch_unwind(AssertUnwindSafe(|| f(&args))) {
+        Ok(r) => set_result(ctx, r),
+        Err(_) => set_error(ctx, "panic in user function"),
+    }
+}
+
+// the aggregate context holds a pointer to the boxed state of the group
+unsafe fn state_ptr(ctx: *mut sqlite3_context, create: bool) -> *mut *mut State {
+    let size = if create {
+        std::mem::size_of::<*mut State>() as c_int
+    } else {
+        0
+    };
+    sqlite3_aggregate_context(ctx, size) as *mut *mut State
+}
+
+unsafe extern "C" fn call_step(
+    ctx: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let aggregate = user_data::<Aggregate>(ctx);
+    let p = state_ptr(ctx, true);
+    if p.is_null() {
+        set_error(ctx, "out of memory");
+        return;
+    }
+    let args = values(argc, argv);
+    let r = catch_unwind(AssertUnwindSafe(|| {
+        if (*p).is_null() {
+            *p = Box::into_raw(Box::new((aggregate.init)()));
+        }
+        (aggregate.step)(&mut **p, &args)
+    }));
+    match r {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => set_error(ctx, &e.to_string()),
+        Err(_) => set_error(ctx, "panic in user function"),
+    }
+}
+
+unsafe extern "C" fn call_final(ctx: *mut sqlite3_context) {
+    let aggregate = user_data::<Aggregate>(ctx);
+    // the context is not allocated when the group has no rows
+    let p = state_ptr(ctx, false);
+    let state = if p.is_null() || (*p).is_null() {
+        None
+    } else {
+        let state = Box::from_raw(*p);
+        *p = ptr::null_mut();
+        Some(*state)
+    };
+    let r = catch_unwind(AssertUnwindSafe(|| {
+        let state = state.unwrap_or_else(|| (aggregate.init)());
+        (aggregate.finalize)(state)
+    }));
+    match r {
+        Ok(r) => set_result(ctx, r),
+        Err(_) => set_error(ctx, "panic in user function"),
+    }
+}
+
+unsafe fn values(argc: c_int, argv: *mut *mut sqlite3_value) -> Vec<Value> {
+    if argc <= 0 || argv.is_null() {
+        return vec![];
+    }
+    slice::from_raw_parts(argv, argc as usize)
+        .iter()
+        .map(|v| value(*v))
+        .collect()
+}
+
+unsafe fn value(v: *mut sqlite3_value) -> Value {
+    match sqlite3_value_type(v) {
+        SQLITE_INTEGER => Value::I64(sqlite3_value_int64(v)),
+        SQLITE_FLOAT => Value::F64(sqlite3_value_double(v)),
+        SQLITE_TEXT => {
+            let ptr = sqlite3_value_text(v);
+            let len = sqlite3_value_bytes(v) as usize;
+            if ptr.is_null() || len == 0 {
+                return Value::String(String::new());
+            }
+            let bytes = slice::from_raw_parts(ptr, len);
+            Value::String(String::from_utf8_lossy(bytes).into_owned())
+        }
+        SQLITE_BLOB => {
+            let len = sqlite3_value_bytes(v) as usize;
+            if len == 0 {
+                // sqlite3_value_blob is NULL for an empty blob
+                return Value::Binary(vec![]);
+            }
+            let ptr = sqlite3_value_blob(v) as *const u8;
+            Value::Binary(slice::from_raw_parts(ptr, len).to_vec())
+        }
+        _ => Value::Null,
+    }
+}
+
+unsafe fn set_result(ctx: *mut sqlite3_context, r: Result<Value, Error>) {
+    match r {
+        Ok(v) => set_value(ctx, v),
+        Err(e) => set_error(ctx, &e.to_string()),
+    }
+}
+
+unsafe fn set_value(ctx: *mut sqlite3_context, v: Value) {
+    match v {
+        Value::Null => sqlite3_result_null(ctx),
+        Value::Bool(v) => sqlite3_result_int64(ctx, v as i64),
+        Value::I32(v) => sqlite3_result_int64(ctx, v as i64),
+        Value::I64(v) => sqlite3_result_int64(ctx, v),
+        Value::U32(v) => sqlite3_result_int64(ctx, v as i64),
+        Value::U64(v) => sqlite3_result_int64(ctx, v as i64),
+        Value::F32(v) => sqlite3_result_double(ctx, v as f64),
+        Value::F64(v) => sqlite3_result_double(ctx, v),
+        Value::String(v) => set_text(ctx, &v),
+        Value::Binary(v) => sqlite3_result_blob64(
+            ctx,
+            v.as_ptr() as *const c_void,
+            v.len() as u64,
+            SQLITE_TRANSIENT(),
+        ),
+        // json, like the arguments of a query
+        Value::Array(_) | Value::Map(_) => set_text(ctx, &v.to_string()),
+        Value::Ext(_, v) => set_value(ctx, *v),
+    }
+}
+
+unsafe fn set_text(ctx: *mut sqlite3_context, v: &str) {
+    sqlite3_result_text64(
+        ctx,
+        v.as_ptr() as *const c_char,
+        v.len() as u64,
+        SQLITE_TRANSIENT(),
+        SQLITE_UTF8 as u8,
+    );
+}
+
+unsafe fn set_error(ctx: *mut sqlite3_context, msg: &str) {
+    // sqlite3_result_error copies the message
+    sqlite3_result_error(ctx, msg.as_ptr() as *const c_char, msg.len() as c_int);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SqliteConnectOptions;
+    use rbdc::db::Connection;
+    use rbs::Value;
+
+    #[tokio::test]
+    async fn test_function() {
+        let mut conn = SqliteConnectOptions::new()
+            .function("add_one", 1, true, |args| {
+                Ok(Value::I64(args[0].as_i64().unwrap_or_default() + 1))
+            })
+            .function("regexp", 2, true, |args| {
+                let pattern = args[0].as_str().unwrap_or_default();
+                let text = args[1].as_str().unwrap_or_default();
+                Ok(Value::Bool(text.contains(pattern)))
+            })
+            .function("fail", 0, false, |_| Err(rbdc::Error::from("failed")))
+            .aggregate(
+                "median",
+                1,
+                Vec::new,
+                |state: &mut Vec<f64>, args| {
+                    if let Some(v) = args[0].as_f64() {
+                        state.push(v);
+                    }
+                    Ok(())
+                },
+                |mut state| {
+                    if state.is_empty() {
+                        return Ok(Value::Null);
+                    }
+                    state.sort_by(|a, b| a.total_cmp(b));
+                    Ok(Value::F64(state[state.len() / 2]))
+                },
+            )
+            .connect()
+            .await
+            .unwrap();
+        let v = conn
+            .get_values(
+                "select add_one(?) as a, 'abc' regexp 'b' as b",
+                vec![Value::I64(1)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(v[0]["a"], Value::I64(2));
+        assert_eq!(v[0]["b"], Value::I64(1));
+
+        let v = conn
+            .get_values(
+                "select median(x) as m from (select 3.0 as x union all select 1.0 union all select 2.0)",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(v[0]["m"], Value::F64(2.0));
+
+        let v = conn
+            .get_values(
+                "select median(x) as m from (select 1.0 as x) where x > 1",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(v[0]["m"], Value::Null);
+
+        let e = conn.get_values("select fail()", vec![]).await;
+        assert!(e.unwrap_err().to_string().contains("failed"));
+    }
+}
@@ -0,0 +1,197 @@
+//! change notifications of `sqlite3_update_hook`, `sqlite3_commit_hook` and `sqlite3_rollback_hook`.
+//!
+//! ```rust
+//! use futures_util::StreamExt;
+//! use rbdc_sqlite::{change_channel, SqliteChangeOp, SqliteConnectOptions};
+//!
+//! # async fn run() {
+//! let (sender, mut changes) = change_channel();
+//! let options = SqliteConnectOptions::new().change_hook(sender);
+//! // connect with the options, then elsewhere:
+//! while let Some(change) = changes.next().await {
+//!     if change.op == SqliteChangeOp::Commit {
+//!         // refresh the caches of the tables changed before the commit
+//!     }
+//! }
+//! # }
+//! ```
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use libsqlite3_sys::{
+    sqlite3, sqlite3_commit_hook, sqlite3_int64, sqlite3_rollback_hook, sqlite3_update_hook,
+    SQLITE_DELETE, SQLITE_INSERT, SQLITE_UPDATE,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SqliteChangeOp {
+    Insert,
+    Update,
+    Delete,
+    /// a transaction is about to commit, `db` and `table` are empty
+    Commit,
+    /// a transaction was rolled back, `db` and `table` are empty
+    Rollback,
+}
+
+/// a row changed by a connection, or the end of a transaction.
+///
+/// Row changes are sent when the statement runs, so the changes of a transaction
+/// may still be rolled back: wait for the `Commit` before acting on them.
+/// Changes to `WITHOUT ROWID` tables and a `DELETE` without `WHERE` (the truncate optimization)
+/// are not reported by SQLite.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SqliteChange {
+    pub op: SqliteChangeOp,
+    /// the database name, `main` or the name of an attached database
+    pub db: String,
+    pub table: String,
+    pub rowid: i64,
+}
+
+/// create the sender for [`SqliteConnectOptions::change_hook()`](crate::SqliteConnectOptions::change_hook)
+/// and the stream of the changes.
+///
+/// The channel is unbounded, as the hooks run inside SQLite and can not wait.
+pub fn change_channel() -> (SqliteChangeSender, SqliteChangeReceiver) {
+    let (tx, rx) = flume::unbounded();
+    (
+        SqliteChangeSender(tx),
+        SqliteChangeReceiver(rx.into_stream()),
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct SqliteChangeSender(flume::Sender<SqliteChange>);
+
+impl SqliteChangeSender {
+    fn send(&self, op: SqliteChangeOp, db: String, table: String, rowid: i64) {
+        // the receiver may be dropped, the changes are then discarded
+        let _ = self.0.send(SqliteChange {
+            op,
+            db,
+            table,
+            rowid,
+        });
+    }
+}
+
+/// the changes of all connections using the sender
+pub struct SqliteChangeReceiver(flume::r#async::RecvStream<'static, SqliteChange>);
+
+impl Stream for SqliteChangeReceiver {
+    type Item = SqliteChange;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+}
+
+/// register the hooks on the handle. the sender must outlive the handle
+pub(crate) unsafe fn register(handle: *mut sqlite3, sender: *const SqliteChangeSender) {
+    let data = sender as *mut c_void;
+    // https://www.sqlite.org/c3ref/update_hook.html
+    sqlite3_update_hook(handle, Some(update_hook), data);
+    // https://www.sqlite.org/c3ref/commit_hook.html
+    sqlite3_commit_hook(handle, Some(commit_hook), data);
+    sqlite3_rollback_hook(handle, Some(rollback_hook), data);
+}
+
+unsafe extern "C" fn update_hook(
+    data: *mut c_void,
+    op: c_int,
+    db: *const c_char,
+    table: *const c_char,
+    rowid: sqlite3_int64,
+) {
+    let sender = &*(data as *const SqliteChangeSender);
+    let op = match op {
+        SQLITE_INSERT => SqliteChangeOp::Insert,
+        SQLITE_UPDATE => SqliteChangeOp::Update,
+        SQLITE_DELETE => SqliteChangeOp::Delete,
+        _ => return,
+    };
+    sender.send(op, to_string(db), to_string(table), rowid);
+}
+
+unsafe extern "C" fn commit_hook(data: *mut c_void) -> c_int {
+    let sender = &*(data as *const SqliteChangeSender);
+    sender.send(SqliteChangeOp::Commit, String::new(), String::new(), 0);
+    // zero lets the commit go on
+    0
+}
+
+unsafe extern "C" fn rollback_hook(data: *mut c_void) {
+    let sender = &*(data as *const SqliteChangeSender);
+    sender.send(SqliteChangeOp::Rollback, String::new(), String::new(), 0);
+}
+
+unsafe fn to_string(s: *const c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{change_channel, SqliteChangeOp, SqliteConnectOptions};
+    use futures_util::StreamExt;
+    use rbdc::db::Connection;
+    use rbs::Value;
+
+    #[tokio::test]
+    async fn test_change_hook() {
+        let (sender, mut changes) = change_channel();
+        let mut conn = SqliteConnectOptions::new()
+            .change_hook(sender)
+            .connect()
+            .await
+            .unwrap();
+        conn.exec("create table t (id integer primary key, name text)", vec![])
+            .await
+            .unwrap();
+        conn.exec("begin", vec![]).await.unwrap();
+        conn.exec(
+            "insert into t (id, name) values (?, ?)",
+            vec![Value::I64(7), Value::String("a".to_string())],
+        )
+        .await
+        .unwrap();
+        conn.exec("commit", vec![]).await.unwrap();
+        conn.exec("begin", vec![]).await.unwrap();
+        conn.exec("delete from t where id = 7", vec![])
+            .await
+            .unwrap();
+        conn.exec("rollback", vec![]).await.unwrap();
+
+        let mut ops = vec![];
+        while let Some(change) = changes.next().await {
+            if change.op == SqliteChangeOp::Insert {
+                assert_eq!(change.db, "main");
+                assert_eq!(change.table, "t");
+                assert_eq!(change.rowid, 7);
+            }
+            if change.table == "t" || change.table.is_empty() {
+                ops.push(change.op);
+            }
+            if change.op == SqliteChangeOp::Rollback {
+                break;
+            }
+        }
+        // the create table commits too
+        assert_eq!(
+            ops,
+            vec![
+                SqliteChangeOp::Commit,
+                SqliteChangeOp::Insert,
+                SqliteChangeOp::Commit,
+                SqliteChangeOp::Delete,
+                SqliteChangeOp::Rollback
+            ]
+        );
+    }
+}
@@ -9,19 +9,27 @@ use std::ptr::NonNull;
 pub(crate) use handle::{ConnectionHandle, ConnectionHandleRaw};
 
 use crate::connection::establish::EstablishParams;
+use crate::connection::function::Function;
+use crate::connection::hook::SqliteChangeSender;
 use crate::connection::worker::ConnectionWorker;
 use crate::statement::VirtualStatement;
-use crate::SqliteConnectOptions;
+use crate::{SqliteConnectOptions, SqliteDateTimeFormat};
 use rbdc::error::Error;
 use rbdc::StatementCache;
 
+pub(crate) mod backup;
+mod cancel;
 pub(crate) mod collation;
 mod establish;
 mod execute;
 mod executor;
+pub(crate) mod function;
 mod handle;
+pub(crate) mod hook;
 
 mod worker;
+pub(crate) mod worker_pool;
+pub use cancel::SqliteCancelHandle;
 pub use worker::Command;
 
 /// A connection to an open [Sqlite] database.
@@ -37,6 +45,7 @@ pub use worker::Command;
 pub struct SqliteConnection {
     pub(crate) worker: ConnectionWorker,
     pub(crate) row_channel_size: usize,
+    pub(crate) datetime_format: SqliteDateTimeFormat,
 }
 
 pub struct LockedSqliteHandle<'a> {
@@ -47,6 +56,9 @@ pub struct ConnectionState {
     pub(crate) handle: ConnectionHandle,
 
     pub(crate) statements: Statements,
+
+    // the data of the hooks, dropped after the handle is closed
+    pub(crate) change_sender: Option<Box<SqliteChangeSender>>,
 }
 
 pub(crate) struct Statements {
@@ -63,6 +75,7 @@ impl SqliteConnection {
         Ok(Self {
             worker,
             row_channel_size: options.row_channel_size,
+            datetime_format: options.datetime_format,
         })
     }
 
@@ -110,6 +123,14 @@ impl SqliteConnection {
         self.worker.create_collation(name, compare)
     }
 
+    /// Register a user defined function on the worker thread.
+    ///
+    /// See [`SqliteConnectOptions::function()`] for details, functions of the options are
+    /// registered on every new connection.
+    pub async fn create_function(&mut self, function: Function) -> Result<(), Error> {
+        self.worker.create_function(function).await
+    }
+
     /// Lock the SQLite database handle out from the worker thread so direct SQLite API calls can
     /// be made safely.
     ///
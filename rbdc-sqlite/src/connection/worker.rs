@@ -3,8 +3,10 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 
+use crate::connection::backup::{self, BackupDest, BackupProgressFn};
 use crate::connection::collation::create_collation;
 use crate::connection::establish::EstablishParams;
+use crate::connection::function::Function;
 use crate::connection::ConnectionState;
 use crate::connection::{execute, ConnectionHandleRaw};
 use crate::{SqliteArguments, SqliteQueryResult, SqliteRow, SqliteStatement};
@@ -13,11 +15,7 @@ use futures_channel::oneshot;
 use futures_intrusive::sync::{Mutex, MutexGuard};
 use rbdc::error::Error;
 
-// Each SQLite connection has a dedicated thread.
-
-// TODO: Tweak this so that we can use a thread pool per pool of SQLite3 connections to reduce
-//       OS resource usage. Low priority because a high concurrent load for SQLite3 is very
-//       unlikely.
+// Each SQLite connection has a dedicated thread, unless the options have a `SqliteWorkerPool`.
 
 pub(crate) struct ConnectionWorker {
     command_tx: flume::Sender<Command>,
@@ -47,6 +45,23 @@ pub enum Command {
         create_collation:
             Box<dyn FnOnce(&mut ConnectionState) -> Result<(), Error> + Send + Sync + 'static>,
     },
+    CreateFunction {
+        function: Function,
+        tx: oneshot::Sender<Result<(), Error>>,
+    },
+    Backup {
+        dest: BackupDest,
+        pages_per_step: i32,
+        progress: Box<BackupProgressFn>,
+        tx: oneshot::Sender<Result<(), Error>>,
+    },
+    Serialize {
+        tx: oneshot::Sender<Result<Vec<u8>, Error>>,
+    },
+    Deserialize {
+        data: Vec<u8>,
+        tx: oneshot::Sender<Result<(), Error>>,
+    },
     UnlockDb,
     ClearCache {
         tx: oneshot::Sender<()>,
@@ -63,102 +78,17 @@ impl ConnectionWorker {
     pub(crate) async fn establish(params: EstablishParams) -> Result<Self, Error> {
         let (establish_tx, establish_rx) = oneshot::channel();
 
-        thread::Builder::new()
-            .name(params.thread_name.clone())
-            .spawn(move || {
-                let (command_tx, command_rx) = flume::bounded(params.command_channel_size);
-
-                let conn = match params.establish() {
-                    Ok(conn) => conn,
-                    Err(e) => {
-                        establish_tx.send(Err(e)).ok();
-                        return;
-                    }
-                };
-
-                let shared = Arc::new(WorkerSharedState {
-                    cached_statements_size: AtomicUsize::new(0),
-                    // note: must be fair because in `Command::UnlockDb` we unlock the mutex
-                    // and then immediately try to relock it; an unfair mutex would immediately
-                    // grant us the lock even if another task is waiting.
-                    conn: Mutex::new(conn, true),
-                });
-                let mut conn = shared.conn.try_lock().unwrap();
-
-                if establish_tx
-                    .send(Ok(Self {
-                        command_tx,
-                        handle_raw: conn.handle.to_raw(),
-                        shared: Arc::clone(&shared),
-                    }))
-                    .is_err()
-                {
-                    return;
-                }
-
-                for cmd in command_rx {
-                    match cmd {
-                        Command::Prepare { query, tx } => {
-                            tx.send(prepare(&mut conn, &query).map(|prepared| {
-                                update_cached_statements_size(
-                                    &conn,
-                                    &shared.cached_statements_size,
-                                );
-                                prepared
-                            }))
-                            .ok();
-                        }
-                        Command::Execute {
-                            query,
-                            arguments,
-                            persistent,
-                            tx,
-                        } => {
-                            let iter = match execute::iter(&mut conn, &query, arguments, persistent)
-                            {
-                                Ok(iter) => iter,
-                                Err(e) => {
-                                    tx.send(Err(e)).ok();
-                                    continue;
-                                }
-                            };
-
-                            for res in iter {
-                                if tx.send(res).is_err() {
-                                    break;
-                                }
-                            }
-
-                            update_cached_statements_size(&conn, &shared.cached_statements_size);
-                        }
-                        Command::CreateCollation { create_collation } => {
-                            if let Err(e) = (create_collation)(&mut conn) {
-                                log::warn!("error applying collation in background worker: {}", e);
-                            }
-                        }
-                        Command::ClearCache { tx } => {
-                            conn.statements.clear();
-                            update_cached_statements_size(&conn, &shared.cached_statements_size);
-                            tx.send(()).ok();
-                        }
-                        Command::UnlockDb => {
-                            drop(conn);
-                            conn = futures_executor::block_on(shared.conn.lock());
-                        }
-                        Command::Ping { tx } => {
-                            tx.send(()).ok();
-                        }
-                        Command::Shutdown { tx } => {
-                            // drop the connection references before sending confirmation
-                            // and ending the command loop
-                            drop(conn);
-                            drop(shared);
-                            let _ = tx.send(());
-                            return;
-                        }
-                    }
-                }
-            })?;
+        match params.worker_pool.clone() {
+            Some(pool) => pool.spawn(
+                params.database.clone(),
+                Box::new(move || Box::pin(run(params, establish_tx))),
+            )?,
+            None => {
+                thread::Builder::new()
+                    .name(params.thread_name.clone())
+                    .spawn(move || futures_executor::block_on(run(params, establish_tx)))?;
+            }
+        }
 
         establish_rx
             .await
@@ -230,6 +160,35 @@ impl ConnectionWorker {
         Ok(())
     }
 
+    pub(crate) async fn create_function(&mut self, function: Function) -> Result<(), Error> {
+        self.oneshot_cmd(|tx| Command::CreateFunction { function, tx })
+            .await?
+    }
+
+    pub(crate) async fn backup(
+        &mut self,
+        dest: BackupDest,
+        pages_per_step: i32,
+        progress: Box<BackupProgressFn>,
+    ) -> Result<(), Error> {
+        self.oneshot_cmd(|tx| Command::Backup {
+            dest,
+            pages_per_step,
+            progress,
+            tx,
+        })
+        .await?
+    }
+
+    pub(crate) async fn serialize(&mut self) -> Result<Vec<u8>, Error> {
+        self.oneshot_cmd(|tx| Command::Serialize { tx }).await?
+    }
+
+    pub(crate) async fn deserialize(&mut self, data: Vec<u8>) -> Result<(), Error> {
+        self.oneshot_cmd(|tx| Command::Deserialize { data, tx })
+            .await?
+    }
+
     pub(crate) async fn clear_cache(&mut self) -> Result<(), Error> {
         self.oneshot_cmd(|tx| Command::ClearCache { tx }).await
     }
@@ -267,6 +226,128 @@ impl ConnectionWorker {
     }
 }
 
+// The command loop of a connection. It runs on a dedicated thread with `block_on`,
+// or as a task of a `SqliteWorkerPool` thread, where it yields to the other connections
+// of the thread while it waits for commands.
+async fn run(
+    params: EstablishParams,
+    establish_tx: oneshot::Sender<Result<ConnectionWorker, Error>>,
+) {
+    let (command_tx, command_rx) = flume::bounded(params.command_channel_size);
+
+    let conn = match params.establish() {
+        Ok(conn) => conn,
+        Err(e) => {
+            establish_tx.send(Err(e)).ok();
+            return;
+        }
+    };
+
+    let shared = Arc::new(WorkerSharedState {
+        cached_statements_size: AtomicUsize::new(0),
+        // note: must be fair because in `Command::UnlockDb` we unlock the mutex
+        // and then immediately try to relock it; an unfair mutex would immediately
+        // grant us the lock even if another task is waiting.
+        conn: Mutex::new(conn, true),
+    });
+    let mut conn = shared.conn.try_lock().unwrap();
+
+    if establish_tx
+        .send(Ok(ConnectionWorker {
+            command_tx,
+            handle_raw: conn.handle.to_raw(),
+            shared: Arc::clone(&shared),
+        }))
+        .is_err()
+    {
+        return;
+    }
+
+    while let Ok(cmd) = command_rx.recv_async().await {
+        match cmd {
+            Command::Prepare { query, tx } => {
+                tx.send(prepare(&mut conn, &query).map(|prepared| {
+                    update_cached_statements_size(&conn, &shared.cached_statements_size);
+                    prepared
+                }))
+                .ok();
+            }
+            Command::Execute {
+                query,
+                arguments,
+                persistent,
+                tx,
+            } => {
+                let iter = match execute::iter(&mut conn, &query, arguments, persistent) {
+                    Ok(iter) => iter,
+                    Err(e) => {
+                        tx.send(Err(e)).ok();
+                        continue;
+                    }
+                };
+
+                for res in iter {
+                    if tx.send_async(res).await.is_err() {
+                        break;
+                    }
+                }
+
+                update_cached_statements_size(&conn, &shared.cached_statements_size);
+            }
+            Command::CreateCollation { create_collation } => {
+                if let Err(e) = (create_collation)(&mut conn) {
+                    log::warn!("error applying collation in background worker: {}", e);
+                }
+            }
+            Command::CreateFunction { function, tx } => {
+                tx.send(function.create(&mut conn.handle)).ok();
+            }
+            Command::Backup {
+                dest,
+                pages_per_step,
+                mut progress,
+                tx,
+            } => {
+                tx.send(backup::backup(
+                    &mut conn.handle,
+                    dest,
+                    pages_per_step,
+                    params.busy_timeout,
+                    &mut *progress,
+                ))
+                .ok();
+            }
+            Command::Serialize { tx } => {
+                tx.send(backup::serialize(&mut conn.handle)).ok();
+            }
+            Command::Deserialize { data, tx } => {
+                tx.send(backup::deserialize(&mut conn, data)).ok();
+                update_cached_statements_size(&conn, &shared.cached_statements_size);
+            }
+            Command::ClearCache { tx } => {
+                conn.statements.clear();
+                update_cached_statements_size(&conn, &shared.cached_statements_size);
+                tx.send(()).ok();
+            }
+            Command::UnlockDb => {
+                drop(conn);
+                conn = shared.conn.lock().await;
+            }
+            Command::Ping { tx } => {
+                tx.send(()).ok();
+            }
+            Command::Shutdown { tx } => {
+                // drop the connection references before sending confirmation
+                // and ending the command loop
+                drop(conn);
+                drop(shared);
+                let _ = tx.send(());
+                return;
+            }
+        }
+    }
+}
+
 fn prepare(conn: &mut ConnectionState, query: &str) -> Result<SqliteStatement, Error> {
     // prepare statement object (or checkout from cache)
     let statement = conn.statements.get(query, true)?;
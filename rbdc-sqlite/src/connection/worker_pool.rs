@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use futures_executor::LocalPool;
+use futures_util::future::LocalBoxFuture;
+use futures_util::task::LocalSpawnExt;
+use rbdc::error::Error;
+
+// makes the command loop of a connection on the thread that runs it
+pub(crate) type Task = Box<dyn FnOnce() -> LocalBoxFuture<'static, ()> + Send>;
+
+// the open connections of each database file, by thread
+type Databases = Arc<Mutex<Vec<HashMap<String, usize>>>>;
+
+/// A bounded set of threads shared by SQLite connections, instead of a thread per connection.
+///
+/// Each connection keeps its own command queue and is served by one thread of the pool,
+/// the thread with the fewest connections when it is opened. The commands of the connections of
+/// a thread are run one at a time, so a long query or backup delays the others of the thread.
+///
+/// A connection waiting on a lock (`busy_timeout`) blocks its thread. If the connection holding
+/// the lock was served by the same thread, it could not run its `COMMIT` until the wait times out,
+/// so by default two connections to the same database file never share a thread: when every thread
+/// already serves the database, the connection gets a thread of its own.
+/// See [`share_database()`](Self::share_database).
+///
+/// Set it with [`SqliteConnectOptions::worker_pool()`](crate::SqliteConnectOptions::worker_pool);
+/// clones share the threads, so one pool can serve the connections of many databases.
+/// The threads stop when the pool and all its connections are dropped.
+#[derive(Clone)]
+pub struct SqliteWorkerPool {
+    threads: Arc<Vec<WorkerThread>>,
+    databases: Databases,
+    // connections on a thread of their own
+    dedicated: Arc<AtomicUsize>,
+    share_database: bool,
+}
+
+struct WorkerThread {
+    tx: flume::Sender<Task>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl SqliteWorkerPool {
+    /// start `threads` worker threads, at least one
+    pub fn new(threads: usize) -> Result<Self, Error> {
+        let threads = threads.max(1);
+        let mut workers = Vec::with_capacity(threads);
+        for id in 0..threads {
+            let (tx, rx) = flume::unbounded::<Task>();
+            thread::Builder::new()
+                .name(format!("rbdc-sqlite-pool-worker-{}", id))
+                .spawn(move || {
+                    let mut pool = LocalPool::new();
+                    let spawner = pool.spawner();
+                    pool.run_until(async {
+                        while let Ok(task) = rx.recv_async().await {
+                            if let Err(e) = spawner.spawn_local(task()) {
+                                log::warn!("error spawning sqlite connection: {}", e);
+                            }
+                        }
+                    });
+                    // the pool is dropped, serve the open connections until they close
+                    pool.run();
+                })?;
+            workers.push(WorkerThread {
+                tx,
+                connections: Arc::new(AtomicUsize::new(0)),
+            });
+        }
+        Ok(Self {
+            threads: Arc::new(workers),
+            databases: Arc::new(Mutex::new(vec![HashMap::new(); threads])),
+            dedicated: Arc::new(AtomicUsize::new(0)),
+            share_database: false,
+        })
+    }
+
+    /// Let connections to the same database file share a thread, so the pool never starts more threads.
+    /// default false.
+    ///
+    /// Only safe when the connections do not wait on each other's locks: a connection blocked by
+    /// `busy_timeout` stalls the thread, and the connection holding the lock can not release it
+    /// before the wait ends.
+    pub fn share_database(mut self, share: bool) -> Self {
+        self.share_database = share;
+        self
+    }
+
+    /// the number of threads
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// the number of open connections served by the pool
+    pub fn connections(&self) -> usize {
+        self.threads
+            .iter()
+            .map(|x| x.connections.load(Ordering::Relaxed))
+            .sum::<usize>()
+            + self.dedicated.load(Ordering::Relaxed)
+    }
+
+    /// run the connection `task` of `database` (None for a private in-memory database)
+    pub(crate) fn spawn(&self, database: Option<String>, task: Task) -> Result<(), Error> {
+        let database = database.filter(|_| !self.share_database);
+        let mut databases = self
+            .databases
+            .lock()
+            .map_err(|_| Error::from("WorkerCrashed"))?;
+        let index = (0..self.threads.len())
+            .filter(|i| match &database {
+                Some(database) => !databases[*i].contains_key(database),
+                None => true,
+            })
+            .min_by_key(|i| self.threads[*i].connections.load(Ordering::Relaxed));
+        let index = match index {
+            Some(v) => v,
+            None => {
+                // every thread serves a connection of the database
+                drop(databases);
+                self.dedicated.fetch_add(1, Ordering::Relaxed);
+                let guard = ConnectionCount {
+                    connections: self.dedicated.clone(),
+                    database: None,
+                };
+                thread::Builder::new()
+                    .name("rbdc-sqlite-pool-dedicated".to_string())
+                    .spawn(move || {
+                        let _guard = guard;
+                        futures_executor::block_on(task())
+                    })?;
+                return Ok(());
+            }
+        };
+        if let Some(database) = &database {
+            *databases[index].entry(database.clone()).or_default() += 1;
+        }
+        drop(databases);
+        let worker = &self.threads[index];
+        let connections = worker.connections.clone();
+        connections.fetch_add(1, Ordering::Relaxed);
+        let guard = ConnectionCount {
+            connections,
+            database: database.map(|v| (self.databases.clone(), index, v)),
+        };
+        worker
+            .tx
+            .send(Box::new(move || {
+                Box::pin(async move {
+                    // counted until the command loop ends
+                    let _guard = guard;
+                    task().await
+                })
+            }))
+            .map_err(|_| Error::from("WorkerCrashed"))
+    }
+}
+
+struct ConnectionCount {
+    connections: Arc<AtomicUsize>,
+    database: Option<(Databases, usize, String)>,
+}
+
+impl Drop for ConnectionCount {
+    fn drop(&mut self) {
+        self.connections.fetch_sub(1, Ordering::Relaxed);
+        if let Some((databases, index, database)) = self.database.take() {
+            if let Ok(mut databases) = databases.lock() {
+                let databases = &mut databases[index];
+                if let Some(count) = databases.get_mut(&database) {
+                    *count -= 1;
+                    if *count == 0 {
+                        databases.remove(&database);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Debug for SqliteWorkerPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteWorkerPool")
+            .field("threads", &self.threads())
+            .field("connections", &self.connections())
+            .field("share_database", &self.share_database)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::connection::worker_pool::SqliteWorkerPool;
+    use crate::SqliteConnectOptions;
+    use rbdc::db::Connection;
+    use rbs::Value;
+
+    #[tokio::test]
+    async fn test_worker_pool() {
+        let pool = SqliteWorkerPool::new(2).unwrap();
+        let mut conns = vec![];
+        for i in 0..8 {
+            let mut conn = SqliteConnectOptions::new()
+                .worker_pool(pool.clone())
+                .connect()
+                .await
+                .unwrap();
+            conn.exec("create table t (id integer)", vec![])
+                .await
+                .unwrap();
+            conn.exec("insert into t values (?)", vec![Value::I64(i)])
+                .await
+                .unwrap();
+            conns.push(conn);
+        }
+        assert_eq!(pool.connections(), 8);
+
+        let mut tasks = vec![];
+        for (i, mut conn) in conns.into_iter().enumerate() {
+            tasks.push(tokio::spawn(async move {
+                let v = conn.get_values("select id from t", vec![]).await.unwrap();
+                assert_eq!(v[0]["id"], Value::I64(i as i64));
+                // the command loop gives the handle away and waits for it on the shared thread
+                let handle = conn.lock_handle().await.unwrap();
+                drop(handle);
+                conn.exec("select 1", vec![]).await.unwrap();
+                conn.close().await.unwrap();
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // the count drops when the command loop ends, just after `close()` returns
+        for _ in 0..100 {
+            if pool.connections() == 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(pool.connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_same_database() {
+        let path =
+            std::env::temp_dir().join(format!("rbdc-sqlite-worker-pool-{}.db", std::process::id()));
+        let pool = SqliteWorkerPool::new(1).unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .busy_timeout(std::time::Duration::from_secs(10))
+            .worker_pool(pool.clone());
+        // the thread serves the database, the other connections get a thread of their own
+        let mut a = options.connect().await.unwrap();
+        let mut b = options.connect().await.unwrap();
+        let mut c = options.connect().await.unwrap();
+        assert_eq!(pool.connections(), 3);
+        a.exec("create table if not exists t (id integer)", vec![])
+            .await
+            .unwrap();
+
+        // b waits on the lock of a, a must still be able to commit
+        a.exec("begin immediate", vec![]).await.unwrap();
+        a.exec("insert into t values (1)", vec![]).await.unwrap();
+        let wait = tokio::spawn(async move {
+            b.exec("insert into t values (2)", vec![]).await.unwrap();
+            b
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let start = std::time::Instant::now();
+        a.exec("commit", vec![]).await.unwrap();
+        let mut b = wait.await.unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        let v = c
+            .get_values("select count(1) as c from t", vec![])
+            .await
+            .unwrap();
+        assert_eq!(v[0]["c"], Value::I64(2));
+
+        for conn in [&mut a, &mut b, &mut c] {
+            conn.close().await.unwrap();
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -34,6 +34,11 @@ impl Driver for SqliteDriver {
     fn default_option(&self) -> Box<dyn ConnectOptions> {
         Box::new(SqliteConnectOptions::default())
     }
+
+    /// `SQLITE_MAX_VARIABLE_NUMBER` of the bundled sqlite (3.32.0+), older builds allow 999
+    fn max_params(&self) -> usize {
+        32766
+    }
 }
 
 impl Placeholder for SqliteDriver {
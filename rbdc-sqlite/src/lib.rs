@@ -8,11 +8,18 @@
 
 pub use arguments::{SqliteArgumentValue, SqliteArguments};
 pub use column::SqliteColumn;
+pub use connection::backup::{BackupProgress, BackupTarget};
+pub use connection::function::Function;
+pub use connection::worker_pool::SqliteWorkerPool;
+pub use connection::hook::{
+    change_channel, SqliteChange, SqliteChangeOp, SqliteChangeReceiver, SqliteChangeSender,
+};
 pub use connection::{LockedSqliteHandle, SqliteConnection};
 pub use database::Sqlite;
 pub use error::SqliteError;
 pub use options::{
-    SqliteAutoVacuum, SqliteConnectOptions, SqliteJournalMode, SqliteLockingMode, SqliteSynchronous,
+    SqliteAutoVacuum, SqliteConnectOptions, SqliteDateTimeFormat, SqliteJournalMode,
+    SqliteLockingMode, SqliteSynchronous,
 };
 pub use query_result::SqliteQueryResult;
 pub use row::SqliteRow;
@@ -1,12 +1,13 @@
 use crate::query::SqliteQuery;
 use crate::type_info::Type;
+use crate::connection::SqliteCancelHandle;
 use crate::{SqliteConnectOptions, SqliteConnection, SqliteQueryResult, SqliteRow};
 use either::Either;
 use futures_core::future::BoxFuture;
 use futures_core::stream::BoxStream;
 use futures_util::FutureExt;
 use futures_util::{StreamExt, TryStreamExt};
-use rbdc::db::{Connection, ExecResult, Row};
+use rbdc::db::{CancelHandle, Connection, ExecResult, Row};
 use rbdc::error::Error;
 use rbs::Value;
 use std::fmt::Write;
@@ -44,6 +45,14 @@ impl SqliteConnectOptions {
                 }
             }
 
+            for function in &self.functions {
+                conn.create_function(function.clone()).await?;
+            }
+
+            for sql in &self.after_connect {
+                conn.exec(sql, vec![]).await?;
+            }
+
             Ok(conn)
         })
     }
@@ -134,6 +143,13 @@ impl Connection for SqliteConnection {
         Box::pin(async { self.do_close().await })
     }
 
+    fn cancel_handle(&self) -> Option<Box<dyn CancelHandle>> {
+        Some(Box::new(SqliteCancelHandle::new(
+            self.worker.handle_raw.clone(),
+            self.worker.shared.clone(),
+        )))
+    }
+
     fn ping(&mut self) -> BoxFuture<Result<(), Error>> {
         Box::pin(async move {
             self.worker
@@ -143,3 +159,35 @@ impl Connection for SqliteConnection {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::SqliteConnectOptions;
+    use rbdc::db::Connection;
+    use rbs::Value;
+
+    #[tokio::test]
+    async fn test_after_connect() {
+        let mut conn = SqliteConnectOptions::new()
+            .after_connect("create temp table t (id integer)")
+            .after_connect("insert into t values (1)")
+            .connect()
+            .await
+            .unwrap();
+        let v = conn
+            .get_values("select count(1) as c from t", vec![])
+            .await
+            .unwrap();
+        assert_eq!(v[0]["c"], Value::I64(1));
+    }
+
+    #[tokio::test]
+    async fn test_extension_not_found() {
+        let e = SqliteConnectOptions::new()
+            .extension("./not-an-extension", None)
+            .connect()
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("not-an-extension"));
+    }
+}
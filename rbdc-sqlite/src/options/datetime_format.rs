@@ -0,0 +1,60 @@
+use rbdc::error::Error;
+use std::str::FromStr;
+
+/// How `Date`, `DateTime` and `Timestamp` values are stored in SQLite, which has no date types.
+///
+/// Columns declared as `DATE`, `TIME`, `DATETIME` or `TIMESTAMP` are decoded from any of
+/// the storage formats; the format decides how parameters are encoded and how numbers are read.
+/// `Time` values are always stored as text.
+///
+/// See [SQLite documentation](https://www.sqlite.org/lang_datefunc.html) for the formats understood
+/// by the date and time functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqliteDateTimeFormat {
+    /// ISO 8601 text such as `2024-01-02T03:04:05.000000000+08:00`, a `Timestamp` stays integer milliseconds.
+    /// integers are read as milliseconds in a `TIMESTAMP` column, as seconds (or milliseconds past the year 5000) in the others
+    Text,
+    /// integer seconds since 1970-01-01 00:00:00 UTC
+    UnixSeconds,
+    /// integer milliseconds since 1970-01-01 00:00:00 UTC
+    UnixMillis,
+    /// real number of days since noon in Greenwich on November 24, 4714 B.C. (an integer at noon UTC)
+    JulianDay,
+}
+
+impl SqliteDateTimeFormat {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            SqliteDateTimeFormat::Text => "text",
+            SqliteDateTimeFormat::UnixSeconds => "unix",
+            SqliteDateTimeFormat::UnixMillis => "unix_ms",
+            SqliteDateTimeFormat::JulianDay => "julian",
+        }
+    }
+}
+
+impl Default for SqliteDateTimeFormat {
+    fn default() -> Self {
+        SqliteDateTimeFormat::Text
+    }
+}
+
+impl FromStr for SqliteDateTimeFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(match &*s.to_ascii_lowercase() {
+            "text" => SqliteDateTimeFormat::Text,
+            "unix" => SqliteDateTimeFormat::UnixSeconds,
+            "unix_ms" => SqliteDateTimeFormat::UnixMillis,
+            "julian" => SqliteDateTimeFormat::JulianDay,
+
+            _ => {
+                return Err(Error::from(format!(
+                    "Configuration:unknown value {:?} for `datetime_format`",
+                    s
+                )));
+            }
+        })
+    }
+}
@@ -2,12 +2,14 @@ use std::path::Path;
 
 mod auto_vacuum;
 mod connect;
+mod datetime_format;
 mod journal_mode;
 mod locking_mode;
 mod parse;
 mod synchronous;
 
 pub use auto_vacuum::SqliteAutoVacuum;
+pub use datetime_format::SqliteDateTimeFormat;
 use futures_core::future::BoxFuture;
 pub use journal_mode::SqliteJournalMode;
 pub use locking_mode::SqliteLockingMode;
@@ -18,10 +20,14 @@ use std::{borrow::Cow, time::Duration};
 pub use synchronous::SqliteSynchronous;
 
 use crate::connection::collation::Collation;
+use crate::connection::function::Function;
+use crate::connection::hook::SqliteChangeSender;
+use crate::connection::worker_pool::SqliteWorkerPool;
 use indexmap::IndexMap;
 use rbdc::common::DebugFn;
 use rbdc::db::{ConnectOptions, Connection};
 use rbdc::Error;
+use rbs::Value;
 use serde::{Deserialize, Deserializer};
 
 /// Options and flags which can be used to configure a SQLite connection.
@@ -36,6 +42,8 @@ use serde::{Deserialize, Deserializer};
 /// `sqlite://data.db` | Open the file `data.db` in the current directory. |
 /// `sqlite:///data.db` | Open the file `data.db` from the root (`/`) directory. |
 /// `sqlite://data.db?mode=ro` | Open the file `data.db` for read-only access. |
+/// `sqlite://data.db?datetime_format=unix_ms` | Store dates as unix milliseconds, see [`SqliteDateTimeFormat`]. |
+/// `sqlite://data.db?after_connect=PRAGMA%20cache_size%3D-20000` | Run the statement on every new connection, may be repeated. |
 ///
 #[derive(Clone, Debug)]
 pub struct SqliteConnectOptions {
@@ -53,6 +61,12 @@ pub struct SqliteConnectOptions {
     pub(crate) row_channel_size: usize,
 
     pub(crate) collations: Vec<Collation>,
+    pub(crate) functions: Vec<Function>,
+    pub(crate) change_sender: Option<SqliteChangeSender>,
+    pub(crate) extensions: Vec<(String, Option<String>)>,
+    pub(crate) after_connect: Vec<String>,
+    pub(crate) worker_pool: Option<SqliteWorkerPool>,
+    pub(crate) datetime_format: SqliteDateTimeFormat,
 
     pub(crate) serialized: bool,
     pub(crate) thread_name: Arc<DebugFn<dyn Fn(u64) -> String + Send + Sync + 'static>>,
@@ -84,6 +98,8 @@ impl<'de> Deserialize<'de> for SqliteConnectOptions {
 
             // pub(crate) collations: Vec<Collation>,
             pub(crate) serialized: bool,
+            #[serde(default)]
+            pub(crate) after_connect: Vec<String>,
             // pub(crate) thread_name: Arc<DebugFn<dyn Fn(u64) -> String + Send + Sync + 'static>>,
         }
         let op = SqliteConnectOptions::deserialize(deserializer)?;
@@ -99,6 +115,7 @@ impl<'de> Deserialize<'de> for SqliteConnectOptions {
         s.command_channel_size = op.command_channel_size;
         s.row_channel_size = op.row_channel_size;
         s.serialized = op.serialized;
+        s.after_connect = op.after_connect;
         Ok(s)
     }
 }
@@ -146,6 +163,12 @@ impl SqliteConnectOptions {
             immutable: false,
             pragmas,
             collations: Default::default(),
+            functions: Default::default(),
+            change_sender: None,
+            extensions: Default::default(),
+            after_connect: Default::default(),
+            worker_pool: None,
+            datetime_format: Default::default(),
             serialized: false,
             thread_name: Arc::new(DebugFn(|id| format!("rbdc-sqlite-worker-{}", id))),
             command_channel_size: 50,
@@ -271,6 +294,61 @@ impl SqliteConnectOptions {
         self
     }
 
+    /// Sets how `Date`, `DateTime` and `Timestamp` values are stored, see [`SqliteDateTimeFormat`].
+    ///
+    /// The default is ISO 8601 text.
+    pub fn datetime_format(mut self, format: SqliteDateTimeFormat) -> Self {
+        self.datetime_format = format;
+        self
+    }
+
+    /// Run the connections on the threads of a [`SqliteWorkerPool`] instead of a thread per connection.
+    ///
+    /// The options of a `Pool` are shared by all its connections, so they share the threads.
+    /// Pass clones of the same worker pool to the options of many databases
+    /// (one database per tenant) to bound the threads of the whole process.
+    /// Connections to the same database file are kept on different threads, as a connection
+    /// waiting on a lock blocks its thread, see [`SqliteWorkerPool::share_database()`].
+    ///
+    /// ```rust
+    /// use rbdc_sqlite::{SqliteConnectOptions, SqliteWorkerPool};
+    ///
+    /// let workers = SqliteWorkerPool::new(4).unwrap();
+    /// let tenant_a = SqliteConnectOptions::new()
+    ///     .filename("a.db")
+    ///     .worker_pool(workers.clone());
+    /// let tenant_b = SqliteConnectOptions::new()
+    ///     .filename("b.db")
+    ///     .worker_pool(workers);
+    /// ```
+    pub fn worker_pool(mut self, pool: SqliteWorkerPool) -> Self {
+        self.worker_pool = Some(pool);
+        self
+    }
+
+    /// Load an [extension](https://www.sqlite.org/loadext.html) such as spatialite or sqlite-vec
+    /// on every new connection, from the path of the shared library.
+    ///
+    /// `entry_point` is the name of the init function, `None` lets SQLite derive it from the file name.
+    /// Loading is only enabled while the connection is established,
+    /// the SQL function `load_extension()` stays disabled.
+    ///
+    /// An extension runs arbitrary native code in the process, only load trusted libraries.
+    pub fn extension(mut self, path: impl Into<String>, entry_point: Option<&str>) -> Self {
+        self.extensions
+            .push((path.into(), entry_point.map(|x| x.to_string())));
+        self
+    }
+
+    /// Run a SQL statement on every new connection, after the pragmas, collations and functions
+    /// are set up. The statements run in the order they are added.
+    ///
+    /// Can also be set with the `after_connect` parameter of the URI.
+    pub fn after_connect(mut self, sql: impl Into<String>) -> Self {
+        self.after_connect.push(sql.into());
+        self
+    }
+
     /// Add a custom collation for comparing strings in SQL.
     ///
     /// If a collation with the same name already exists, it will be replaced.
@@ -296,6 +374,87 @@ impl SqliteConnectOptions {
         self
     }
 
+    /// Add a user defined scalar function, registered on every connection.
+    ///
+    /// `n_args` is the number of arguments, or -1 for any number of arguments.
+    /// A `deterministic` function always returns the same result for the same arguments,
+    /// so SQLite can use it in indexes and optimize repeated calls.
+    /// The result `Value::Map` and `Value::Array` are returned as json text.
+    ///
+    /// `X REGEXP Y` calls the function `regexp(Y, X)`, which SQLite does not provide itself.
+    ///
+    /// See [`sqlite3_create_function()`](https://www.sqlite.org/c3ref/create_function.html) for details.
+    ///
+    /// ```rust
+    /// use rbdc_sqlite::SqliteConnectOptions;
+    /// use rbs::Value;
+    ///
+    /// let options = SqliteConnectOptions::new().function("regexp", 2, true, |args| {
+    ///     let pattern = args[0].as_str().unwrap_or_default();
+    ///     let text = args[1].as_str().unwrap_or_default();
+    ///     Ok(Value::Bool(text.contains(pattern)))
+    /// });
+    /// ```
+    pub fn function<N, F>(mut self, name: N, n_args: i32, deterministic: bool, f: F) -> Self
+    where
+        N: Into<Arc<str>>,
+        F: Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.functions
+            .push(Function::scalar(name, n_args, deterministic, f));
+        self
+    }
+
+    /// Add a user defined aggregate function, registered on every connection.
+    ///
+    /// Each group starts with the state made by `init`, `step` is called with the arguments
+    /// of every row of the group and `finalize` turns the state into the result.
+    /// A group without rows (`SELECT f(x) FROM t` on an empty table) calls `init` and `finalize` only.
+    ///
+    /// ```rust
+    /// use rbdc_sqlite::SqliteConnectOptions;
+    /// use rbs::Value;
+    ///
+    /// let options = SqliteConnectOptions::new().aggregate(
+    ///     "product",
+    ///     1,
+    ///     || 1.0,
+    ///     |state: &mut f64, args| {
+    ///         *state *= args[0].as_f64().unwrap_or(1.0);
+    ///         Ok(())
+    ///     },
+    ///     |state| Ok(Value::F64(state)),
+    /// );
+    /// ```
+    pub fn aggregate<N, S, I, T, F>(
+        mut self,
+        name: N,
+        n_args: i32,
+        init: I,
+        step: T,
+        finalize: F,
+    ) -> Self
+    where
+        N: Into<Arc<str>>,
+        S: Send + 'static,
+        I: Fn() -> S + Send + Sync + 'static,
+        T: Fn(&mut S, &[Value]) -> Result<(), Error> + Send + Sync + 'static,
+        F: Fn(S) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.functions
+            .push(Function::aggregate(name, n_args, init, step, finalize));
+        self
+    }
+
+    /// Send the inserts, updates and deletes of every connection, and the commits and rollbacks,
+    /// to the [`SqliteChangeReceiver`](crate::SqliteChangeReceiver) made by [`change_channel()`](crate::change_channel).
+    ///
+    /// See [`sqlite3_update_hook()`](https://www.sqlite.org/c3ref/update_hook.html) for details.
+    pub fn change_hook(mut self, sender: SqliteChangeSender) -> Self {
+        self.change_sender = Some(sender);
+        self
+    }
+
     /// Set to `true` to signal to SQLite that the database file is on read-only media.
     ///
     /// If enabled, SQLite assumes the database file _cannot_ be modified, even by higher
@@ -111,6 +111,14 @@ impl FromStr for SqliteConnectOptions {
                         }
                     },
 
+                    "datetime_format" => {
+                        options.datetime_format = value.parse()?;
+                    }
+
+                    "after_connect" => {
+                        options.after_connect.push(value.to_string());
+                    }
+
                     _ => {
                         return Err(Error::from(
                             format!(
@@ -165,3 +173,30 @@ fn test_parse_shared_in_memory() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_parse_after_connect() -> Result<(), Error> {
+    let options: SqliteConnectOptions =
+        "sqlite://a.db?after_connect=PRAGMA%20cache_size%3D-20000&after_connect=SELECT%201"
+            .parse()?;
+    assert_eq!(
+        options.after_connect,
+        vec!["PRAGMA cache_size=-20000".to_string(), "SELECT 1".to_string()]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_datetime_format() -> Result<(), Error> {
+    let options: SqliteConnectOptions = "sqlite://a.db?datetime_format=unix_ms".parse()?;
+    assert_eq!(
+        options.datetime_format,
+        crate::SqliteDateTimeFormat::UnixMillis
+    );
+    assert!("sqlite://a.db?datetime_format=x"
+        .parse::<SqliteConnectOptions>()
+        .is_err());
+
+    Ok(())
+}
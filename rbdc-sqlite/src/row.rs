@@ -1,8 +1,8 @@
 #![allow(clippy::rc_buffer)]
 
-use crate::decode::Decode;
 use crate::statement::StatementHandle;
-use crate::{SqliteColumn, SqliteValue, SqliteValueRef};
+use crate::types::value::decode_value;
+use crate::{SqliteColumn, SqliteDateTimeFormat, SqliteValue, SqliteValueRef};
 use rbdc::db::{MetaData, Row};
 use rbdc::error::Error;
 use rbdc::ext::ustr::UStr;
@@ -17,6 +17,7 @@ pub struct SqliteRow {
     pub(crate) values: Vec<SqliteValue>,
     pub(crate) columns: Arc<Vec<SqliteColumn>>,
     pub(crate) column_names: Arc<HashMap<UStr, usize>>,
+    pub(crate) datetime_format: SqliteDateTimeFormat,
 }
 
 // Accessing values from the statement object is
@@ -50,6 +51,7 @@ impl SqliteRow {
             values: values,
             columns: Arc::clone(columns),
             column_names: Arc::clone(column_names),
+            datetime_format: SqliteDateTimeFormat::default(),
         }
     }
 }
@@ -83,7 +85,7 @@ impl Row for SqliteRow {
     fn get(&mut self, i: usize) -> Result<Value, Error> {
         match self.try_take(i) {
             Err(e) => Err(Error::from(format!("get error index:{},error:{}", i, e))),
-            Ok(v) => Value::decode(v),
+            Ok(v) => decode_value(v, self.datetime_format),
         }
     }
 }
@@ -38,6 +38,7 @@ pub(crate) enum DataType {
     Date,
     Time,
     Datetime,
+    Timestamp,
 }
 
 /// Type information for a SQLite type.
@@ -75,6 +76,7 @@ impl SqliteTypeInfo {
             DataType::Date => "DATE",
             DataType::Time => "TIME",
             DataType::Datetime => "DATETIME",
+            DataType::Timestamp => "TIMESTAMP",
         }
     }
 }
@@ -109,7 +111,8 @@ impl FromStr for DataType {
 
             "date" => DataType::Date,
             "time" => DataType::Time,
-            "datetime" | "timestamp" => DataType::Datetime,
+            "datetime" => DataType::Datetime,
+            "timestamp" => DataType::Timestamp,
 
             _ if s.contains("int") => DataType::Int64,
 
@@ -185,6 +188,7 @@ fn test_data_type_from_str() -> Result<(), Error> {
     assert_eq!(DataType::Datetime, "DATETIME".parse()?);
     assert_eq!(DataType::Time, "TIME".parse()?);
     assert_eq!(DataType::Date, "DATE".parse()?);
+    assert_eq!(DataType::Timestamp, "TIMESTAMP".parse()?);
 
     Ok(())
 }
@@ -0,0 +1,262 @@
+use crate::decode::Decode;
+use crate::type_info::DataType;
+use crate::{SqliteDateTimeFormat, SqliteValue};
+use rbdc::Error;
+use rbs::Value;
+use std::str::FromStr;
+
+// julian day of 1970-01-01 00:00:00 UTC
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+const MILLIS_PER_DAY: f64 = 86_400_000.0;
+// larger integers of a `DATE`/`DATETIME` column are millis in `Text` format: seconds would be after the year 5000
+const MAX_UNIX_SECONDS: i64 = 100_000_000_000;
+
+impl SqliteDateTimeFormat {
+    /// convert a `Date`, `DateTime` or `Timestamp` parameter to the storage format
+    pub(crate) fn encode(&self, v: Value) -> Value {
+        match v {
+            // a `Timestamp` is stored as integer millis in `Text` format, like it always was
+            Value::Ext("Timestamp", inner) if *self == SqliteDateTimeFormat::Text => {
+                Value::Ext("Timestamp", inner)
+            }
+            Value::Ext("Timestamp", inner) => match inner.as_i64() {
+                Some(ms) => self.millis_to_value(ms),
+                None => Value::Ext("Timestamp", inner),
+            },
+            Value::Ext(t @ ("Date" | "DateTime"), inner) => {
+                if *self == SqliteDateTimeFormat::Text {
+                    return Value::Ext(t, inner);
+                }
+                let s = inner.as_str().unwrap_or_default();
+                let parsed = if t == "Date" {
+                    fastdate::Date::from_str(s).map(fastdate::DateTime::from)
+                } else {
+                    fastdate::DateTime::from_str(s)
+                };
+                match parsed {
+                    Ok(dt) => self.millis_to_value(dt.unix_timestamp_millis()),
+                    Err(_) => Value::Ext(t, inner),
+                }
+            }
+            v => v,
+        }
+    }
+
+    fn millis_to_value(&self, ms: i64) -> Value {
+        match self {
+            SqliteDateTimeFormat::Text => {
+                Value::String(fastdate::DateTime::from_timestamp_millis(ms).to_string())
+            }
+            SqliteDateTimeFormat::UnixSeconds => Value::I64(ms.div_euclid(1000)),
+            SqliteDateTimeFormat::UnixMillis => Value::I64(ms),
+            SqliteDateTimeFormat::JulianDay => {
+                Value::F64(ms as f64 / MILLIS_PER_DAY + UNIX_EPOCH_JULIAN_DAY)
+            }
+        }
+    }
+
+    fn int_to_millis(&self, declared: DataType, v: i64) -> i64 {
+        match self {
+            SqliteDateTimeFormat::UnixMillis => v,
+            // a julian day at 12:00:00 UTC is integral, stored as an integer by the NUMERIC affinity
+            SqliteDateTimeFormat::JulianDay => self.real_to_millis(v as f64),
+            // `Timestamp` params are stored as integer millis in `Text` format
+            SqliteDateTimeFormat::Text if declared == DataType::Timestamp => v,
+            SqliteDateTimeFormat::Text if v.abs() >= MAX_UNIX_SECONDS => v,
+            _ => v.saturating_mul(1000),
+        }
+    }
+
+    fn real_to_millis(&self, v: f64) -> i64 {
+        match self {
+            SqliteDateTimeFormat::UnixSeconds => (v * 1000.0).round() as i64,
+            SqliteDateTimeFormat::UnixMillis => v.round() as i64,
+            // a real is a julian day, integral reals are stored as integers by the NUMERIC affinity for the date functions of SQLite
+            _ => ((v - UNIX_EPOCH_JULIAN_DAY) * MILLIS_PER_DAY).round() as i64,
+        }
+    }
+}
+
+/// decode a value of a column declared as `DATE`, `TIME`, `DATETIME` or `TIMESTAMP`
+pub(crate) fn decode_datetime(
+    declared: DataType,
+    value: SqliteValue,
+    format: SqliteDateTimeFormat,
+) -> Result<Value, Error> {
+    let ms = match value.type_info().0 {
+        DataType::Int | DataType::Int64 | DataType::Bool => format.int_to_millis(declared, value.int64()),
+        DataType::Float => format.real_to_millis(value.double()),
+        DataType::Text => {
+            let s = value.text()?.to_string();
+            return Ok(match declared {
+                DataType::Date => Value::Ext("Date", Box::new(Value::String(s))),
+                DataType::Time => Value::Ext("Time", Box::new(Value::String(s))),
+                DataType::Timestamp => match fastdate::DateTime::from_str(&s) {
+                    Ok(dt) => Value::Ext(
+                        "Timestamp",
+                        Box::new(Value::I64(dt.unix_timestamp_millis())),
+                    ),
+                    Err(_) => Value::String(s),
+                },
+                _ => Value::Ext("DateTime", Box::new(Value::String(s))),
+            });
+        }
+        _ => return Ok(Value::Binary(Vec::<u8>::decode(value)?)),
+    };
+    let dt = fastdate::DateTime::from_timestamp_millis(ms);
+    Ok(match declared {
+        DataType::Date => Value::Ext(
+            "Date",
+            Box::new(Value::String(fastdate::Date::from(dt).to_string())),
+        ),
+        DataType::Time => Value::Ext(
+            "Time",
+            Box::new(Value::String(fastdate::Time::from(dt).to_string())),
+        ),
+        DataType::Timestamp => Value::Ext("Timestamp", Box::new(Value::I64(ms))),
+        _ => Value::Ext("DateTime", Box::new(Value::String(dt.to_string()))),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{SqliteConnectOptions, SqliteDateTimeFormat};
+    use rbdc::db::Connection;
+    use rbdc::types::{Date, DateTime, Timestamp};
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_datetime_round_trip() {
+        for format in [
+            SqliteDateTimeFormat::Text,
+            SqliteDateTimeFormat::UnixSeconds,
+            SqliteDateTimeFormat::UnixMillis,
+            SqliteDateTimeFormat::JulianDay,
+        ] {
+            let mut conn = SqliteConnectOptions::new()
+                .datetime_format(format)
+                .connect()
+                .await
+                .unwrap();
+            conn.exec("create table t (d DATE, dt DATETIME, ts TIMESTAMP)", vec![])
+                .await
+                .unwrap();
+            let date = Date::from_str("2023-04-05").unwrap();
+            let datetime = DateTime::from_str("2023-04-05T06:07:08Z").unwrap();
+            let timestamp = Timestamp(1680674828000);
+            conn.exec(
+                "insert into t (d, dt, ts) values (?, ?, ?)",
+                vec![
+                    rbs::to_value!(date.clone()),
+                    rbs::to_value!(datetime.clone()),
+                    rbs::to_value!(timestamp.clone()),
+                ],
+            )
+            .await
+            .unwrap();
+            let rows = conn.get_values("select * from t", vec![]).await.unwrap();
+            let row = &rows[0];
+            assert_eq!(rbs::from_value::<Date>(row["d"].clone()).unwrap(), date);
+            assert_eq!(
+                rbs::from_value::<DateTime>(row["dt"].clone()).unwrap(),
+                datetime,
+                "{:?}",
+                format
+            );
+            assert_eq!(
+                rbs::from_value::<Timestamp>(row["ts"].clone()).unwrap(),
+                timestamp
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_text_round_trip() {
+        let mut conn = SqliteConnectOptions::new().connect().await.unwrap();
+        conn.exec("create table t (ts TIMESTAMP)", vec![])
+            .await
+            .unwrap();
+        for timestamp in [Timestamp(86_400_000), Timestamp(-86_400_000)] {
+            conn.exec("delete from t", vec![]).await.unwrap();
+            conn.exec(
+                "insert into t (ts) values (?)",
+                vec![rbs::to_value!(timestamp.clone())],
+            )
+            .await
+            .unwrap();
+            let rows = conn.get_values("select ts from t", vec![]).await.unwrap();
+            assert_eq!(
+                rbs::from_value::<Timestamp>(rows[0]["ts"].clone()).unwrap(),
+                timestamp
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_unix_seconds() {
+        let mut conn = SqliteConnectOptions::new().connect().await.unwrap();
+        conn.exec("create table t (dt DATETIME)", vec![])
+            .await
+            .unwrap();
+        conn.exec("insert into t values (0), (2440588.5)", vec![])
+            .await
+            .unwrap();
+        let rows = conn.get_values("select dt from t", vec![]).await.unwrap();
+        let dt = rbs::from_value::<DateTime>(rows[0]["dt"].clone()).unwrap();
+        assert_eq!(dt.unix_timestamp_millis(), 0);
+        // a real is a julian day, integral reals are stored as integers by the NUMERIC affinity
+        let dt = rbs::from_value::<DateTime>(rows[1]["dt"].clone()).unwrap();
+        assert_eq!(dt.unix_timestamp_millis(), 86_400_000);
+    }
+
+    #[tokio::test]
+    async fn test_julian_day_noon() {
+        let mut conn = SqliteConnectOptions::new()
+            .datetime_format(SqliteDateTimeFormat::JulianDay)
+            .connect()
+            .await
+            .unwrap();
+        conn.exec("create table t (dt DATETIME)", vec![])
+            .await
+            .unwrap();
+        let datetime = DateTime::from_str("2023-04-05T12:00:00Z").unwrap();
+        conn.exec(
+            "insert into t (dt) values (?)",
+            vec![rbs::to_value!(datetime.clone())],
+        )
+        .await
+        .unwrap();
+        let rows = conn
+            .get_values("select dt, typeof(dt) as t from t", vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["t"], rbs::Value::String("integer".to_string()));
+        assert_eq!(
+            rbs::from_value::<DateTime>(rows[0]["dt"].clone()).unwrap(),
+            datetime
+        );
+    }
+
+    #[tokio::test]
+    async fn test_text_timestamp_is_millis() {
+        let mut conn = SqliteConnectOptions::new().connect().await.unwrap();
+        conn.exec("create table t (ts TIMESTAMP)", vec![])
+            .await
+            .unwrap();
+        conn.exec(
+            "insert into t (ts) values (?)",
+            vec![rbs::to_value!(Timestamp(1680674828000))],
+        )
+        .await
+        .unwrap();
+        let rows = conn
+            .get_values("select ts, typeof(ts) as t from t", vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["t"], rbs::Value::String("integer".to_string()));
+        assert_eq!(
+            rbs::from_value::<Timestamp>(rows[0]["ts"].clone()).unwrap(),
+            Timestamp(1680674828000)
+        );
+    }
+}
@@ -17,6 +17,10 @@
 //! | `f64`                                 | REAL                                                 |
 //! | `&str`, [`String`]                    | TEXT                                                 |
 //! | `&[u8]`, `Vec<u8>`                    | BLOB                                                 |
+//! | `rbdc::Date`                          | DATE, see [`SqliteDateTimeFormat`](crate::SqliteDateTimeFormat) |
+//! | `rbdc::Time`                          | TIME                                                 |
+//! | `rbdc::DateTime`                      | DATETIME                                             |
+//! | `rbdc::Timestamp`                     | TIMESTAMP                                            |
 //! # Nullable
 //!
 //! In addition, `Option<T>` is supported where `T` implements `Type`. An `Option<T>` represents
@@ -25,12 +29,13 @@
 
 mod bool;
 mod bytes;
+pub(crate) mod datetime;
 mod float;
 mod int;
 mod str;
 mod uint;
 use crate::type_info::Type;
-mod value;
+pub(crate) mod value;
 
 
 #[cfg(test)]
@@ -1,7 +1,8 @@
 use crate::decode::Decode;
 use crate::encode::{Encode, IsNull};
 use crate::type_info::DataType;
-use crate::{SqliteArgumentValue, SqliteValue};
+use crate::types::datetime::decode_datetime;
+use crate::{SqliteArgumentValue, SqliteDateTimeFormat, SqliteValue};
 use rbdc::Error;
 use rbs::Value;
 
@@ -10,31 +11,47 @@ impl Decode for Value {
     where
         Self: Sized,
     {
-        if value.type_info_opt().is_none() {
-            return Ok(Value::Null);
-        }
-        match value.type_info().0 {
-            DataType::Null => Ok(Value::Null),
-            DataType::Int => Ok(Value::I64(i64::decode(value)?)),
-            DataType::Float => Ok(Value::F64(f64::decode(value)?)),
-            DataType::Text => Ok(Value::String(String::decode(value)?)),
-            DataType::Blob => Ok(Value::Binary(Vec::<u8>::decode(value)?)),
-            DataType::Numeric => Ok(Value::String(String::decode(value)?)),
-            DataType::Bool => Ok(Value::Bool(bool::decode(value)?)),
-            DataType::Int64 => Ok(Value::I64(i64::decode(value)?)),
-            DataType::Date => Ok(Value::Ext(
-                "Date",
-                Box::new(Value::String(String::decode(value)?)),
-            )),
-            DataType::Time => Ok(Value::Ext(
-                "Time",
-                Box::new(Value::String(String::decode(value)?)),
-            )),
-            DataType::Datetime => Ok(Value::Ext(
-                "Datetime",
-                Box::new(Value::String(String::decode(value)?)),
-            )),
-        }
+        decode_value(value, SqliteDateTimeFormat::default())
+    }
+}
+
+/// decode by the declared type of the column for dates, otherwise by the stored type
+pub(crate) fn decode_value(
+    value: SqliteValue,
+    format: SqliteDateTimeFormat,
+) -> Result<Value, Error> {
+    if value.type_info_opt().is_none() {
+        return Ok(Value::Null);
+    }
+    let declared = value.type_info.0;
+    if let DataType::Date | DataType::Time | DataType::Datetime | DataType::Timestamp = declared {
+        return decode_datetime(declared, value, format);
+    }
+    match value.type_info().0 {
+        DataType::Null => Ok(Value::Null),
+        DataType::Int => Ok(Value::I64(i64::decode(value)?)),
+        DataType::Float => Ok(Value::F64(f64::decode(value)?)),
+        DataType::Text => Ok(Value::String(String::decode(value)?)),
+        DataType::Blob => Ok(Value::Binary(Vec::<u8>::decode(value)?)),
+        DataType::Numeric => Ok(Value::String(String::decode(value)?)),
+        DataType::Bool => Ok(Value::Bool(bool::decode(value)?)),
+        DataType::Int64 => Ok(Value::I64(i64::decode(value)?)),
+        DataType::Date => Ok(Value::Ext(
+            "Date",
+            Box::new(Value::String(String::decode(value)?)),
+        )),
+        DataType::Time => Ok(Value::Ext(
+            "Time",
+            Box::new(Value::String(String::decode(value)?)),
+        )),
+        DataType::Datetime => Ok(Value::Ext(
+            "DateTime",
+            Box::new(Value::String(String::decode(value)?)),
+        )),
+        DataType::Timestamp => Ok(Value::Ext(
+            "Timestamp",
+            Box::new(Value::I64(i64::decode(value)?)),
+        )),
     }
 }
 
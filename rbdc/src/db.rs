@@ -21,8 +21,17 @@ pub trait Driver: Debug + Sync + Send {
 
     /// make an default option
     fn default_option(&self) -> Box<dyn ConnectOptions>;
+
+    /// the max number of bind parameters one statement can take,
+    /// batch statements are split into chunks that stay under it
+    fn max_params(&self) -> usize {
+        DEFAULT_MAX_PARAMS
+    }
 }
 
+/// the bind parameter limit of pg and mysql prepared statements
+pub const DEFAULT_MAX_PARAMS: usize = 65535;
+
 impl Driver for Box<dyn Driver>{
     fn name(&self) -> &str {
         self.deref().name()
@@ -39,6 +48,10 @@ impl Driver for Box<dyn Driver>{
     fn default_option(&self) -> Box<dyn ConnectOptions> {
         self.deref().default_option()
     }
+
+    fn max_params(&self) -> usize {
+        self.deref().max_params()
+    }
 }
 
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
@@ -92,12 +105,98 @@ pub trait Connection: Send {
         })
     }
 
+    /// Execute a statement or a script that may return many result sets,
+    /// such as a stored procedure `CALL` or `SET ...; SELECT ...;`.
+    /// return every result set (rows of a statement with columns),
+    /// and one ExecResult for each statement of the script.
+    /// the default impl returns the rows of `get_values` as one result set
+    fn get_result_sets(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+    ) -> BoxFuture<Result<(Vec<Vec<Value>>, Vec<ExecResult>), Error>> {
+        let v = self.get_values(sql, params);
+        Box::pin(async move {
+            let rows = v.await?;
+            let result = ExecResult {
+                rows_affected: rows.len() as u64,
+                last_insert_id: Value::Null,
+            };
+            Ok((vec![rows], vec![result]))
+        })
+    }
+
     /// Execute a query that is expected to update some rows.
     fn exec(&mut self, sql: &str, params: Vec<Value>) -> BoxFuture<Result<ExecResult, Error>>;
 
+    /// Execute many statements that are expected to update some rows,
+    /// return one result for each statement.
+    /// the statements run in order until one fails, the statements that did not run
+    /// (or were rolled back with it, such as a postgres pipeline) get `Error::batch_aborted()`.
+    /// the outer error is for a batch that failed as a whole, such as a lost connection.
+    /// drivers that support pipelining (for example postgres) send the whole batch in one round trip,
+    /// the default impl executes the statements one by one
+    fn exec_batch(
+        &mut self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<Result<Vec<Result<ExecResult, Error>>, Error>> {
+        Box::pin(async move {
+            let mut results = Vec::with_capacity(batch.len());
+            let mut failed = false;
+            for (sql, params) in batch {
+                if failed {
+                    results.push(Err(Error::batch_aborted()));
+                    continue;
+                }
+                let result = self.exec(&sql, params).await;
+                failed = result.is_err();
+                results.push(result);
+            }
+            Ok(results)
+        })
+    }
+
+    /// Load many rows into `table` with the bulk load protocol of the database,
+    /// which is not limited by the number of parameters of a statement.
+    /// each row is a `Value::Map` of the column names, such as `rbs::to_value!(table)`.
+    /// the default impl returns an error, only mssql supports it
+    fn bulk_insert(
+        &mut self,
+        table: &str,
+        rows: Vec<Value>,
+    ) -> BoxFuture<Result<ExecResult, Error>> {
+        let _ = rows;
+        let table = table.to_string();
+        Box::pin(async move {
+            Err(Error::from(format!(
+                "bulk_insert into {} is not supported by the driver",
+                table
+            )))
+        })
+    }
+
+    /// whether the connection is inside a transaction, as reported by the server.
+    /// None if the driver doesn't know, then the pool tracks `begin`/`commit`/`rollback`
+    fn in_transaction(&self) -> Option<bool> {
+        None
+    }
+
+    /// a handle to cancel the statement running on this connection from another task.
+    /// None if the driver can't cancel, then a timed out connection is dropped
+    fn cancel_handle(&self) -> Option<Box<dyn CancelHandle>> {
+        None
+    }
+
     /// ping
     fn ping(&mut self) -> BoxFuture<Result<(), Error>>;
 
+    /// forget the statements the driver prepared and cached on this connection.
+    /// the pool calls it after the `after_release` hooks,
+    /// which may have dropped them on the server, e.g. pg `DISCARD ALL`
+    fn reset(&mut self) -> BoxFuture<Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+
     /// close connection
     /// Normally conn is dropped when the link is dropped,
     /// but it is recommended to actively close this function so that the database does not report errors.
@@ -107,6 +206,12 @@ pub trait Connection: Send {
     fn close(&mut self) -> BoxFuture<Result<(), Error>>;
 }
 
+/// cancels the statement running on a connection, e.g. pg `CancelRequest` or mysql `KILL QUERY`.
+/// the running statement fails and the connection stays usable
+pub trait CancelHandle: Send + Sync {
+    fn cancel(&self) -> BoxFuture<'static, Result<(), Error>>;
+}
+
 /// Result set from executing a query against a statement
 pub trait Row: 'static + Send + Debug {
     /// get meta data about this result set
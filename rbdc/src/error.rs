@@ -3,6 +3,8 @@ use std::fmt::{Display, Formatter};
 use std::num::{ParseFloatError, ParseIntError, TryFromIntError};
 use std::str::Utf8Error;
 
+const BATCH_ABORTED: &str = "statement aborted: another statement of the batch failed";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Error {
     E(String),
@@ -14,6 +16,17 @@ impl Error {
     pub fn protocol(err: impl Display) -> Self {
         Error::E(err.to_string())
     }
+
+    /// the error of a statement of a batch that did not run because another statement of the batch failed
+    pub fn batch_aborted() -> Self {
+        Error::E(BATCH_ABORTED.to_string())
+    }
+
+    pub fn is_batch_aborted(&self) -> bool {
+        match self {
+            Error::E(e) => e == BATCH_ABORTED,
+        }
+    }
 }
 
 impl Display for Error {
@@ -1,7 +1,9 @@
+use crate::net::tls::client_identity;
 use crate::net::CertificateInput;
 use rustls::{
-    client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
-    CertificateError, ClientConfig, Error as TlsError, OwnedTrustAnchor, RootCertStore, ServerName,
+    client::{ServerCertVerified, ServerCertVerifier, WantsClientCert, WebPkiVerifier},
+    CertificateError, ClientConfig, ConfigBuilder, Error as TlsError, OwnedTrustAnchor,
+    PrivateKey, RootCertStore, ServerName,
 };
 use std::io::Cursor;
 use std::sync::Arc;
@@ -13,13 +15,13 @@ pub async fn configure_tls_connector(
     accept_invalid_certs: bool,
     accept_invalid_hostnames: bool,
     root_cert_path: Option<&CertificateInput>,
+    client_cert_path: Option<&CertificateInput>,
+    client_key_path: Option<&CertificateInput>,
 ) -> Result<crate::rt::TlsConnector, Error> {
     let config = ClientConfig::builder().with_safe_defaults();
 
     let config = if accept_invalid_certs {
-        config
-            .with_custom_certificate_verifier(Arc::new(DummyTlsVerifier))
-            .with_no_client_auth()
+        config.with_custom_certificate_verifier(Arc::new(DummyTlsVerifier))
     } else {
         let mut cert_store = RootCertStore::empty();
         cert_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
@@ -43,22 +45,57 @@ pub async fn configure_tls_connector(
             }
         }
 
+        // the same verifier as `with_root_certificates`, which leads to a different builder state
+        let verifier = WebPkiVerifier::new(cert_store, None);
         if accept_invalid_hostnames {
-            let verifier = WebPkiVerifier::new(cert_store, None);
-
-            config
-                .with_custom_certificate_verifier(Arc::new(NoHostnameTlsVerifier { verifier }))
-                .with_no_client_auth()
+            config.with_custom_certificate_verifier(Arc::new(NoHostnameTlsVerifier { verifier }))
         } else {
-            config
-                .with_root_certificates(cert_store)
-                .with_no_client_auth()
+            config.with_custom_certificate_verifier(Arc::new(verifier))
         }
     };
 
+    let config = with_client_auth(config, client_cert_path, client_key_path).await?;
+
     Ok(Arc::new(config).into())
 }
 
+async fn with_client_auth(
+    config: ConfigBuilder<ClientConfig, WantsClientCert>,
+    client_cert_path: Option<&CertificateInput>,
+    client_key_path: Option<&CertificateInput>,
+) -> Result<ClientConfig, Error> {
+    let (cert_path, key_path) = match client_identity(client_cert_path, client_key_path)? {
+        Some(v) => v,
+        None => return Ok(config.with_no_client_auth()),
+    };
+    let data = cert_path.data().await?;
+    let cert_chain = rustls_pemfile::certs(&mut Cursor::new(data))
+        .map_err(|_| Error::E(format!("Invalid certificate {}", cert_path)))?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect();
+    let data = key_path.data().await?;
+    let key = load_private_key(&data)
+        .ok_or_else(|| Error::E(format!("Invalid private key {}", key_path)))?;
+    config
+        .with_client_auth_cert(cert_chain, key)
+        .map_err(|err| Error::E(err.to_string()))
+}
+
+/// the first PKCS#8, PKCS#1 (RSA) or SEC1 (EC) key of the PEM data
+fn load_private_key(data: &[u8]) -> Option<PrivateKey> {
+    let mut cursor = Cursor::new(data);
+    while let Ok(Some(item)) = rustls_pemfile::read_one(&mut cursor) {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Some(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    None
+}
+
 struct DummyTlsVerifier;
 
 impl ServerCertVerifier for DummyTlsVerifier {
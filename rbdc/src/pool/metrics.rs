@@ -0,0 +1,265 @@
+use deadpool::Status;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// upper bounds in seconds of the histogram buckets
+pub const BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// a fixed bucket histogram of durations
+#[derive(Debug)]
+pub struct Histogram {
+    name: &'static str,
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            buckets: Default::default(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn record(&self, arg: Duration) {
+        let secs = arg.as_secs_f64();
+        if let Some(idx) = BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(arg.as_micros() as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(self.name).record(secs);
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let mut buckets = Vec::with_capacity(BUCKETS.len());
+        for (idx, bound) in BUCKETS.iter().enumerate() {
+            cumulative += self.buckets[idx].load(Ordering::Relaxed);
+            buckets.push((*bound, cumulative));
+        }
+        HistogramSnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistogramSnapshot {
+    /// (upper bound in seconds, count of values <= bound)
+    pub buckets: Vec<(f64, u64)>,
+    pub count: u64,
+    /// sum of values in seconds
+    pub sum: f64,
+}
+
+/// a counter, also sent to the `metrics` crate with the `metrics` feature
+#[derive(Debug)]
+pub struct Counter {
+    name: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn incr(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!(self.name).increment(1);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// cumulative metrics of a [`Pool`](crate::pool::Pool)
+#[derive(Debug)]
+pub struct PoolMetrics {
+    /// time waited for a connection by `get`/`try_get`
+    pub acquire_wait: Histogram,
+    pub acquire_timeouts: Counter,
+    /// `get`/`try_get` failed for other reasons than a timeout
+    pub acquire_errors: Counter,
+    pub connections_created: Counter,
+    pub connections_closed: Counter,
+    pub connect_errors: Counter,
+    pub ping_failed: Counter,
+    /// time from checkout to the return of the connection, recorded when it is returned
+    pub in_use: Histogram,
+    /// time of each statement executed by a pooled connection
+    pub statement: Histogram,
+    pub statement_errors: Counter,
+}
+
+impl Default for PoolMetrics {
+    fn default() -> Self {
+        Self {
+            acquire_wait: Histogram::new("rbdc_pool_acquire_wait_seconds"),
+            acquire_timeouts: Counter::new("rbdc_pool_acquire_timeouts_total"),
+            acquire_errors: Counter::new("rbdc_pool_acquire_errors_total"),
+            connections_created: Counter::new("rbdc_pool_connections_created_total"),
+            connections_closed: Counter::new("rbdc_pool_connections_closed_total"),
+            connect_errors: Counter::new("rbdc_pool_connect_errors_total"),
+            ping_failed: Counter::new("rbdc_pool_ping_failed_total"),
+            in_use: Histogram::new("rbdc_pool_in_use_seconds"),
+            statement: Histogram::new("rbdc_statement_seconds"),
+            statement_errors: Counter::new("rbdc_statement_errors_total"),
+        }
+    }
+}
+
+impl PoolMetrics {
+    pub fn snapshot(&self, status: Status) -> PoolMetricsSnapshot {
+        let snapshot = PoolMetricsSnapshot {
+            max_size: status.max_size as u64,
+            size: status.size as u64,
+            available: status.available as u64,
+            waiting: status.waiting as u64,
+            acquire_wait: self.acquire_wait.snapshot(),
+            acquire_timeouts: self.acquire_timeouts.get(),
+            acquire_errors: self.acquire_errors.get(),
+            connections_created: self.connections_created.get(),
+            connections_closed: self.connections_closed.get(),
+            connect_errors: self.connect_errors.get(),
+            ping_failed: self.ping_failed.get(),
+            in_use: self.in_use.snapshot(),
+            statement: self.statement.snapshot(),
+            statement_errors: self.statement_errors.get(),
+        };
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!("rbdc_pool_max_size").set(snapshot.max_size as f64);
+            metrics::gauge!("rbdc_pool_size").set(snapshot.size as f64);
+            metrics::gauge!("rbdc_pool_available").set(snapshot.available as f64);
+            metrics::gauge!("rbdc_pool_waiting").set(snapshot.waiting as f64);
+        }
+        snapshot
+    }
+}
+
+/// a serializable snapshot of [`PoolMetrics`] and the pool status, e.g. for a health endpoint
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoolMetricsSnapshot {
+    pub max_size: u64,
+    pub size: u64,
+    pub available: u64,
+    pub waiting: u64,
+    pub acquire_wait: HistogramSnapshot,
+    pub acquire_timeouts: u64,
+    pub acquire_errors: u64,
+    pub connections_created: u64,
+    pub connections_closed: u64,
+    pub connect_errors: u64,
+    pub ping_failed: u64,
+    pub in_use: HistogramSnapshot,
+    pub statement: HistogramSnapshot,
+    pub statement_errors: u64,
+}
+
+impl PoolMetricsSnapshot {
+    /// render the Prometheus text exposition format, `prefix` is prepended to every name
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let mut s = String::new();
+        for (name, v) in [
+            ("pool_max_size", self.max_size),
+            ("pool_size", self.size),
+            ("pool_available", self.available),
+            ("pool_waiting", self.waiting),
+        ] {
+            let _ = writeln!(s, "# TYPE {}{} gauge", prefix, name);
+            let _ = writeln!(s, "{}{} {}", prefix, name, v);
+        }
+        for (name, v) in [
+            ("pool_acquire_timeouts_total", self.acquire_timeouts),
+            ("pool_acquire_errors_total", self.acquire_errors),
+            ("pool_connections_created_total", self.connections_created),
+            ("pool_connections_closed_total", self.connections_closed),
+            ("pool_connect_errors_total", self.connect_errors),
+            ("pool_ping_failed_total", self.ping_failed),
+            ("statement_errors_total", self.statement_errors),
+        ] {
+            let _ = writeln!(s, "# TYPE {}{} counter", prefix, name);
+            let _ = writeln!(s, "{}{} {}", prefix, name, v);
+        }
+        for (name, v) in [
+            ("pool_acquire_wait_seconds", &self.acquire_wait),
+            ("pool_in_use_seconds", &self.in_use),
+            ("statement_seconds", &self.statement),
+        ] {
+            let _ = writeln!(s, "# TYPE {}{} histogram", prefix, name);
+            for (bound, count) in &v.buckets {
+                let _ = writeln!(s, "{}{}_bucket{{le=\"{}\"}} {}", prefix, name, bound, count);
+            }
+            let _ = writeln!(s, "{}{}_bucket{{le=\"+Inf\"}} {}", prefix, name, v.count);
+            let _ = writeln!(s, "{}{}_sum {}", prefix, name, v.sum);
+            let _ = writeln!(s, "{}{}_count {}", prefix, name, v.count);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pool::PoolMetrics;
+    use deadpool::Status;
+    use std::time::Duration;
+
+    #[test]
+    fn test_histogram() {
+        let m = PoolMetrics::default();
+        m.statement.record(Duration::from_micros(500));
+        m.statement.record(Duration::from_millis(20));
+        m.statement.record(Duration::from_secs(10));
+        let v = m.statement.snapshot();
+        assert_eq!(v.count, 3);
+        assert_eq!(v.buckets[0], (0.001, 1));
+        assert_eq!(v.buckets[3], (0.025, 2));
+        assert_eq!(v.buckets[9], (5.0, 2));
+        assert!((v.sum - 10.0205).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_prometheus() {
+        let m = PoolMetrics::default();
+        m.connections_created.incr();
+        m.acquire_wait.record(Duration::from_millis(2));
+        let text = m
+            .snapshot(Status {
+                max_size: 10,
+                size: 1,
+                available: 1,
+                waiting: 0,
+            })
+            .to_prometheus("rbdc_");
+        assert!(text.contains("rbdc_pool_max_size 10\n"));
+        assert!(text.contains("# TYPE rbdc_pool_connections_created_total counter\nrbdc_pool_connections_created_total 1\n"));
+        assert!(text.contains("rbdc_pool_acquire_wait_seconds_bucket{le=\"0.001\"} 0\n"));
+        assert!(text.contains("rbdc_pool_acquire_wait_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(text.contains("rbdc_pool_acquire_wait_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("rbdc_pool_acquire_wait_seconds_count 1\n"));
+    }
+}
@@ -1,2 +1,8 @@
+pub mod metrics;
+pub mod options;
 pub mod pool;
+pub mod timeout;
+pub use metrics::*;
+pub use options::*;
 pub use pool::*;
+pub use timeout::{statement_timeout, with_statement_timeout, CANCEL_GRACE};
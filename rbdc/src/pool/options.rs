@@ -0,0 +1,213 @@
+use crate::db::Connection;
+use crate::Error;
+use deadpool::managed::Metrics;
+use futures_core::future::BoxFuture;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// an async closure run on a pooled connection, an error closes the connection
+pub type ConnectionHook =
+    Arc<dyn for<'a> Fn(&'a mut dyn Connection) -> BoxFuture<'a, Result<(), Error>> + Send + Sync>;
+
+/// connection lifecycle policies of the [`Pool`](crate::pool::Pool).
+///
+/// ```rust
+/// use std::time::Duration;
+/// use rbdc::pool::PoolOptions;
+/// let opts = PoolOptions::new()
+///     .max_size(20)
+///     .max_lifetime(Some(Duration::from_secs(25 * 60)))
+///     .idle_timeout(Some(Duration::from_secs(10 * 60)))
+///     .min_idle(2)
+///     .after_connect(|conn| {
+///         Box::pin(async move {
+///             conn.exec("SET application_name = 'app'", vec![]).await?;
+///             Ok(())
+///         })
+///     });
+/// ```
+#[derive(Clone)]
+pub struct PoolOptions {
+    /// max connections of the pool, default is the deadpool default (cpu_count * 4)
+    pub max_size: Option<usize>,
+    /// close connections older than this, default None
+    pub max_lifetime: Option<Duration>,
+    /// close connections not handed out for this long, default None
+    pub idle_timeout: Option<Duration>,
+    /// connections the reaper keeps open and idle, default 0
+    pub min_idle: usize,
+    /// how often the reaper runs, default 30s
+    pub reaper_interval: Duration,
+    /// run after the driver opened a connection, e.g. `SET search_path`
+    pub after_connect: Vec<ConnectionHook>,
+    /// run before an idle connection is handed out, after the ping
+    pub before_acquire: Vec<ConnectionHook>,
+    /// run on a connection given back to the pool before it is idle again, e.g. `DISCARD ALL`.
+    /// they run on a task spawned when the [`PoolConnection`](crate::pool::PoolConnection) is dropped,
+    /// then the statements the driver cached are forgotten, see [`Connection::reset`].
+    /// a connection dropped outside a tokio runtime runs them at its next checkout
+    pub after_release: Vec<ConnectionHook>,
+    /// cancel statements of pooled connections running longer than this, default None.
+    /// override it for a scope with [`with_statement_timeout`](crate::pool::with_statement_timeout)
+    pub statement_timeout: Option<Duration>,
+}
+
+impl Debug for PoolOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolOptions")
+            .field("max_size", &self.max_size)
+            .field("max_lifetime", &self.max_lifetime)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("min_idle", &self.min_idle)
+            .field("reaper_interval", &self.reaper_interval)
+            .field("after_connect", &self.after_connect.len())
+            .field("before_acquire", &self.before_acquire.len())
+            .field("after_release", &self.after_release.len())
+            .field("statement_timeout", &self.statement_timeout)
+            .finish()
+    }
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_lifetime: None,
+            idle_timeout: None,
+            min_idle: 0,
+            reaper_interval: Duration::from_secs(30),
+            after_connect: vec![],
+            before_acquire: vec![],
+            after_release: vec![],
+            statement_timeout: None,
+        }
+    }
+}
+
+impl PoolOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_size(mut self, arg: usize) -> Self {
+        self.max_size = Some(arg);
+        self
+    }
+
+    pub fn max_lifetime(mut self, arg: Option<Duration>) -> Self {
+        self.max_lifetime = arg;
+        self
+    }
+
+    pub fn idle_timeout(mut self, arg: Option<Duration>) -> Self {
+        self.idle_timeout = arg;
+        self
+    }
+
+    pub fn min_idle(mut self, arg: usize) -> Self {
+        self.min_idle = arg;
+        self
+    }
+
+    pub fn reaper_interval(mut self, arg: Duration) -> Self {
+        self.reaper_interval = arg;
+        self
+    }
+
+    pub fn statement_timeout(mut self, arg: Option<Duration>) -> Self {
+        self.statement_timeout = arg;
+        self
+    }
+
+    pub fn after_connect<F>(mut self, hook: F) -> Self
+    where
+        F: for<'a> Fn(&'a mut dyn Connection) -> BoxFuture<'a, Result<(), Error>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.after_connect.push(Arc::new(hook));
+        self
+    }
+
+    pub fn before_acquire<F>(mut self, hook: F) -> Self
+    where
+        F: for<'a> Fn(&'a mut dyn Connection) -> BoxFuture<'a, Result<(), Error>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.before_acquire.push(Arc::new(hook));
+        self
+    }
+
+    pub fn after_release<F>(mut self, hook: F) -> Self
+    where
+        F: for<'a> Fn(&'a mut dyn Connection) -> BoxFuture<'a, Result<(), Error>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.after_release.push(Arc::new(hook));
+        self
+    }
+
+    /// the connection is past `max_lifetime` or `idle_timeout`.
+    /// idle time counts from the last time the pool handed the connection out
+    pub fn is_expired(&self, metrics: &Metrics) -> bool {
+        if let Some(max_lifetime) = self.max_lifetime {
+            if metrics.age() >= max_lifetime {
+                return true;
+            }
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            if metrics.last_used() >= idle_timeout {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// whether the pool needs a background reaper
+    pub fn need_reaper(&self) -> bool {
+        self.max_lifetime.is_some() || self.idle_timeout.is_some() || self.min_idle > 0
+    }
+}
+
+/// run hooks in order, stop at the first error
+pub(crate) async fn run_hooks(
+    hooks: &[ConnectionHook],
+    conn: &mut dyn Connection,
+) -> Result<(), Error> {
+    for hook in hooks {
+        hook(conn).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pool::PoolOptions;
+    use deadpool::managed::Metrics;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_is_expired() {
+        let now = Instant::now();
+        let metrics = Metrics {
+            created: now - Duration::from_secs(100),
+            recycled: Some(now - Duration::from_secs(10)),
+            recycle_count: 1,
+        };
+        assert!(!PoolOptions::new().is_expired(&metrics));
+        assert!(!PoolOptions::new().need_reaper());
+        let opts = PoolOptions::new().max_lifetime(Some(Duration::from_secs(60)));
+        assert!(opts.is_expired(&metrics));
+        let opts = PoolOptions::new().idle_timeout(Some(Duration::from_secs(5)));
+        assert!(opts.is_expired(&metrics));
+        let opts = PoolOptions::new().idle_timeout(Some(Duration::from_secs(60)));
+        assert!(!opts.is_expired(&metrics));
+        assert!(opts.need_reaper());
+    }
+}
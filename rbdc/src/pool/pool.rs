@@ -1,4 +1,7 @@
-use crate::db::{ConnectOptions, Connection, Driver, ExecResult, Row};
+use crate::db::{CancelHandle, ConnectOptions, Connection, Driver, ExecResult, Row};
+use crate::pool::options::run_hooks;
+use crate::pool::timeout::{run_timeout, statement_timeout};
+use crate::pool::{PoolMetrics, PoolMetricsSnapshot, PoolOptions};
 use crate::Error;
 use async_trait::async_trait;
 use deadpool::managed::{Manager, Metrics, Object, PoolBuilder, PoolError, RecycleError, RecycleResult, Timeouts};
@@ -9,15 +12,17 @@ use std::fmt::{Debug, Formatter};
 use std::future::Future;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// RBDC pool.
 /// you can use just like any deadpool methods  pool.deref().close() and more...
-/// Max & idle connection lifetime see https://github.com/bikeshedder/deadpool/issues/178
+/// Max & idle connection lifetime and min idle connections see [`PoolOptions`]
 #[derive(Clone)]
 pub struct Pool {
     pub manager: ManagerPorxy,
     pub inner: deadpool::managed::Pool<ManagerPorxy>,
+    /// counts the clones of this pool, the reaper stops when it holds the last one
+    handles: Arc<()>,
 }
 
 impl Pool {
@@ -26,6 +31,11 @@ impl Pool {
         self.manager.driver_type()
     }
 
+    /// return the max number of bind parameters of the driver
+    pub fn max_params(&self) -> usize {
+        self.manager.driver.max_params()
+    }
+
     /// spawn task on runtime
     pub fn spawn_task<T>(&self, task: T)
     where
@@ -68,16 +78,113 @@ impl Pool {
         self.deref().timeouts()
     }
 
-    /// get connection
+    /// Retrieves a snapshot of the pool metrics and status
+    pub fn metrics(&self) -> PoolMetricsSnapshot {
+        self.manager.metrics.snapshot(self.status())
+    }
+
+    /// get connection.
+    /// the connection is released (rolled back, `after_release` hooks) at its next checkout,
+    /// use [`acquire`](Self::acquire) to release it when it is returned
     pub async fn get(&self) -> Result<Object<ManagerPorxy>, PoolError<Error>> {
-        self.deref().get().await
+        self.acquire().await.map(PoolConnection::into_inner)
     }
 
-    /// try get connection
+    /// try get connection, see [`get`](Self::get)
     pub async fn try_get(&self) -> Result<Object<ManagerPorxy>, PoolError<Error>> {
+        self.try_acquire().await.map(PoolConnection::into_inner)
+    }
+
+    /// get a connection released when it is returned to the pool
+    pub async fn acquire(&self) -> Result<PoolConnection, PoolError<Error>> {
+        let start = Instant::now();
+        let result = self.deref().get().await;
+        self.record_acquire(start, result)
+    }
+
+    /// try get a connection released when it is returned to the pool
+    pub async fn try_acquire(&self) -> Result<PoolConnection, PoolError<Error>> {
+        let start = Instant::now();
         let mut t = self.deref().timeouts();
         t.wait = Some(Duration::ZERO);
-        self.deref().timeout_get(&t).await
+        let result = self.deref().timeout_get(&t).await;
+        self.record_acquire(start, result)
+    }
+
+    fn record_acquire(
+        &self,
+        start: Instant,
+        result: Result<Object<ManagerPorxy>, PoolError<Error>>,
+    ) -> Result<PoolConnection, PoolError<Error>> {
+        let metrics = &self.manager.metrics;
+        match result {
+            Ok(mut conn) => {
+                metrics.acquire_wait.record(start.elapsed());
+                conn.checkout_at = Some(Instant::now());
+                conn.last_used_at = None;
+                conn.pending_release = true;
+                Ok(PoolConnection { inner: Some(conn) })
+            }
+            Err(e) => {
+                match e {
+                    PoolError::Timeout(_) => metrics.acquire_timeouts.incr(),
+                    _ => metrics.acquire_errors.incr(),
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// close idle connections past `max_lifetime`/`idle_timeout`,
+    /// then open connections until `min_idle` connections are idle.
+    /// the reaper started by [`Pool::new_options`] calls this every `reaper_interval`
+    pub async fn reap(&self) {
+        let options = &self.manager.pool_options;
+        self.deref().retain(|_, metrics| !options.is_expired(&metrics));
+        let status = self.status();
+        if status.available >= options.min_idle {
+            return;
+        }
+        let want = options.min_idle.min(status.max_size);
+        let mut conns = Vec::with_capacity(want);
+        let mut t = self.deref().timeouts();
+        t.wait = Some(Duration::ZERO);
+        for _ in 0..want {
+            //not a checkout, it is left out of the acquire metrics
+            match self.deref().timeout_get(&t).await {
+                Ok(conn) => conns.push(conn),
+                Err(_) => break,
+            }
+        }
+        //dropping the connections puts them back to the pool as idle ones
+        drop(conns);
+    }
+
+    fn start_reaper(&self) {
+        if !self.manager.pool_options.need_reaper() {
+            return;
+        }
+        //without a runtime the expired connections are still closed when they are recycled
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(v) => v,
+            Err(_) => {
+                log::warn!("[rbdc] pool reaper not started, no tokio runtime");
+                return;
+            }
+        };
+        let pool = self.clone();
+        handle.spawn(async move {
+            let interval = pool.manager.pool_options.reaper_interval;
+            loop {
+                //every other handle is dropped, the pool is unreachable and must not be kept alive
+                if pool.is_closed() || Arc::strong_count(&pool.handles) == 1 {
+                    break;
+                }
+                pool.reap().await;
+                tokio::time::sleep(interval).await;
+            }
+            pool.close();
+        });
     }
 }
 
@@ -123,11 +230,113 @@ impl Deref for ManagerPorxy {
 pub struct RBDCManager {
     pub driver: Box<dyn Driver>,
     pub option: Box<dyn ConnectOptions>,
+    pub pool_options: PoolOptions,
+    pub metrics: PoolMetrics,
 }
 
 pub struct DropBox {
     pub manager_proxy: ManagerPorxy,
     pub conn: Option<Box<dyn Connection>>,
+    /// a `begin` was executed without `commit`/`rollback`
+    pub in_tx: bool,
+    /// the pool handed the connection out
+    pub checkout_at: Option<Instant>,
+    /// the last statement finished
+    pub last_used_at: Option<Instant>,
+    /// a timed out statement may still be running or its cancel may still arrive,
+    /// the connection is closed at recycle
+    pub broken: bool,
+    /// the connection was handed out and the `after_release` hooks didn't run yet
+    pub pending_release: bool,
+}
+
+impl DropBox {
+    /// record the in-use time of the last checkout, from the checkout to `released_at`.
+    /// None for an object returned without its [`PoolConnection`],
+    /// then the last statement is the latest known use
+    fn record_in_use(&mut self, released_at: Option<Instant>) {
+        let last_used_at = self.last_used_at.take();
+        if let Some(checkout_at) = self.checkout_at.take() {
+            let released_at = released_at.or(last_used_at).unwrap_or(checkout_at);
+            self.manager_proxy
+                .metrics
+                .in_use
+                .record(released_at.saturating_duration_since(checkout_at));
+        }
+    }
+
+    /// split into the connection and the bookkeeping of a statement run on it
+    fn statement(&mut self) -> (&mut Box<dyn Connection>, Statement<'_>) {
+        let DropBox {
+            manager_proxy,
+            conn,
+            in_tx,
+            last_used_at,
+            broken,
+            ..
+        } = self;
+        let conn = conn.as_mut().unwrap();
+        let timeout = statement_timeout(manager_proxy.pool_options.statement_timeout);
+        let cancel = match timeout {
+            Some(_) => conn.cancel_handle(),
+            None => None,
+        };
+        (
+            conn,
+            Statement {
+                manager: manager_proxy,
+                in_tx,
+                last_used_at,
+                broken,
+                timeout,
+                cancel,
+            },
+        )
+    }
+
+    /// a `begin` was executed without `commit`/`rollback`, as reported by the driver if it knows
+    fn tx_open(&self) -> bool {
+        match &self.conn {
+            Some(conn) => conn.in_transaction().unwrap_or(self.in_tx),
+            None => false,
+        }
+    }
+
+    /// whether [`DropBox::release`] has work to do
+    fn need_release(&self) -> bool {
+        self.tx_open()
+            || (self.pending_release && !self.manager_proxy.pool_options.after_release.is_empty())
+    }
+
+    /// rollback the transaction abandoned on a connection given back to the pool,
+    /// run the `after_release` hooks, then forget the statements they may have dropped
+    async fn release(&mut self) -> Result<(), Error> {
+        if !self.need_release() {
+            return Ok(());
+        }
+        if self.tx_open() {
+            log::warn!("[rbdc] connection returned to the pool inside a transaction, rollback it");
+            let result = self.conn.as_mut().unwrap().exec("rollback", vec![]).await;
+            self.in_tx = false;
+            result?;
+        }
+        let hooks = &self.manager_proxy.pool_options.after_release;
+        if self.pending_release && !hooks.is_empty() {
+            let conn = self.conn.as_mut().unwrap();
+            run_hooks(hooks, conn.as_mut()).await?;
+            conn.reset().await?;
+        }
+        self.pending_release = false;
+        Ok(())
+    }
+
+    /// take and close the connection
+    async fn close_conn(&mut self) {
+        if let Some(mut conn) = self.conn.take() {
+            let _ = conn.close().await;
+            self.manager_proxy.metrics.connections_closed.incr();
+        }
+    }
 }
 
 impl Deref for DropBox {
@@ -146,7 +355,9 @@ impl DerefMut for DropBox {
 
 impl Drop for DropBox {
     fn drop(&mut self) {
+        self.record_in_use(None);
         if let Some(mut conn) = self.conn.take() {
+            self.manager_proxy.metrics.connections_closed.incr();
             self.manager_proxy.spawn_task(async move {
                 let _ = conn.close().await;
             });
@@ -160,20 +371,56 @@ impl Manager for ManagerPorxy {
     type Error = Error;
 
     async fn create(&self) -> Result<Self::Type, Self::Error> {
+        let mut conn = match self.driver.connect_opt(self.option.as_ref()).await {
+            Ok(v) => v,
+            Err(e) => {
+                self.metrics.connect_errors.incr();
+                return Err(e);
+            }
+        };
+        if let Err(e) = run_hooks(&self.pool_options.after_connect, conn.as_mut()).await {
+            self.metrics.connect_errors.incr();
+            let _ = conn.close().await;
+            return Err(e);
+        }
+        self.metrics.connections_created.incr();
         Ok(DropBox {
             manager_proxy: self.clone(),
-            conn: Some(self.driver.connect_opt(self.option.as_ref()).await?),
+            conn: Some(conn),
+            in_tx: false,
+            checkout_at: None,
+            last_used_at: None,
+            broken: false,
+            pending_release: false,
         })
     }
 
-    async fn recycle(&self, conn: &mut Self::Type, _metrics: &Metrics) -> RecycleResult<Self::Error> {
-        match conn.ping().await {
+    async fn recycle(&self, conn: &mut Self::Type, metrics: &Metrics) -> RecycleResult<Self::Error> {
+        conn.record_in_use(None);
+        if conn.broken {
+            conn.close_conn().await;
+            return Err(RecycleError::StaticMessage("Connection is broken"));
+        }
+        if self.pool_options.is_expired(metrics) {
+            conn.close_conn().await;
+            return Err(RecycleError::StaticMessage("Connection is expired"));
+        }
+        //without a runtime the connection wasn't released when it was returned
+        let mut result = conn.release().await;
+        if result.is_ok() {
+            result = conn.ping().await;
+            if result.is_err() {
+                self.metrics.ping_failed.incr();
+            }
+        }
+        if result.is_ok() {
+            result = run_hooks(&self.pool_options.before_acquire, conn.as_mut()).await;
+        }
+        match result {
             Ok(_) => Ok(()),
             Err(e) => {
                 //shutdown connection
-                if let Some(mut conn) = conn.conn.take() {
-                    let _ = conn.close().await;
-                }
+                conn.close_conn().await;
                 return Err(RecycleError::Message(format!(
                     "Connection is ping fail={}",
                     e
@@ -190,12 +437,16 @@ impl RBDCManager {
         Ok(Self {
             driver: Box::new(driver),
             option: option,
+            pool_options: PoolOptions::default(),
+            metrics: PoolMetrics::default(),
         })
     }
     pub fn new_opt<D: Driver + 'static, Option: ConnectOptions>(driver: D, option: Option) -> Self {
         Self {
             driver: Box::new(driver),
             option: Box::new(option),
+            pool_options: PoolOptions::default(),
+            metrics: PoolMetrics::default(),
         }
     }
 
@@ -203,6 +454,8 @@ impl RBDCManager {
         Self {
             driver: driver,
             option: option,
+            pool_options: PoolOptions::default(),
+            metrics: PoolMetrics::default(),
         }
     }
 
@@ -237,6 +490,7 @@ impl Pool {
         let pool = Pool {
             manager: manager,
             inner: p,
+            handles: Arc::new(()),
         };
         Ok(pool)
     }
@@ -251,6 +505,7 @@ impl Pool {
         Ok(Pool {
             manager: manager,
             inner: inner,
+            handles: Arc::new(()),
         })
     }
 
@@ -262,9 +517,37 @@ impl Pool {
         Ok(Pool {
             manager: manager,
             inner: inner,
+            handles: Arc::new(()),
         })
     }
 
+    /// create a pool with [`PoolOptions`], the reaper is started when
+    /// `max_lifetime`, `idle_timeout` or `min_idle` is set and a tokio runtime is running.
+    /// the reaper stops after [`Pool::close`] or once every other handle of the pool is dropped
+    pub fn new_options(
+        d: Box<dyn Driver>,
+        o: Box<dyn ConnectOptions>,
+        options: PoolOptions,
+    ) -> Result<Self, Error> {
+        let mut builder = Pool::builder(ManagerPorxy::from(Arc::new(RBDCManager {
+            driver: d,
+            option: o,
+            pool_options: options.clone(),
+            metrics: PoolMetrics::default(),
+        })));
+        if let Some(max_size) = options.max_size {
+            builder = builder.max_size(max_size);
+        }
+        let inner = builder.build().map_err(|e| Error::from(e.to_string()))?;
+        let pool = Pool {
+            manager: inner.manager().clone(),
+            inner,
+            handles: Arc::new(()),
+        };
+        pool.start_reaper();
+        Ok(pool)
+    }
+
     pub fn new_builder(
         builder: PoolBuilder<ManagerPorxy, Object<ManagerPorxy>>,
         d: Box<dyn Driver>,
@@ -274,6 +557,7 @@ impl Pool {
         Ok(Pool {
             manager: manager,
             inner: builder.build().map_err(|e| Error::from(e.to_string()))?,
+            handles: Arc::new(()),
         })
     }
 
@@ -283,6 +567,131 @@ impl Pool {
 }
 
 impl Connection for Object<ManagerPorxy> {
+    fn get_rows(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+    ) -> BoxFuture<Result<Vec<Box<dyn Row>>, Error>> {
+        let (conn, statement) = self.deref_mut().statement();
+        let f = conn.get_rows(sql, params);
+        Box::pin(statement.run(None, f))
+    }
+
+    fn get_result_sets(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+    ) -> BoxFuture<Result<(Vec<Vec<Value>>, Vec<ExecResult>), Error>> {
+        let (conn, statement) = self.deref_mut().statement();
+        let f = conn.get_result_sets(sql, params);
+        Box::pin(statement.run(None, f))
+    }
+
+    fn exec(&mut self, sql: &str, params: Vec<Value>) -> BoxFuture<Result<ExecResult, Error>> {
+        let tx = tx_statement(sql);
+        let (conn, statement) = self.deref_mut().statement();
+        let f = conn.exec(sql, params);
+        Box::pin(statement.run(tx, f))
+    }
+
+    fn exec_batch(
+        &mut self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<Result<Vec<Result<ExecResult, Error>>, Error>> {
+        let (conn, statement) = self.deref_mut().statement();
+        let f = conn.exec_batch(batch);
+        Box::pin(statement.run(None, f))
+    }
+
+    fn bulk_insert(
+        &mut self,
+        table: &str,
+        rows: Vec<Value>,
+    ) -> BoxFuture<Result<ExecResult, Error>> {
+        let (conn, statement) = self.deref_mut().statement();
+        let f = conn.bulk_insert(table, rows);
+        Box::pin(statement.run(None, f))
+    }
+
+    fn close(&mut self) -> BoxFuture<Result<(), Error>> {
+        self.deref_mut().close()
+    }
+
+    fn in_transaction(&self) -> Option<bool> {
+        Some(self.deref().in_transaction().unwrap_or(self.in_tx))
+    }
+
+    fn cancel_handle(&self) -> Option<Box<dyn CancelHandle>> {
+        self.deref().cancel_handle()
+    }
+
+    fn ping(&mut self) -> BoxFuture<Result<(), Error>> {
+        self.deref_mut().ping()
+    }
+
+    fn reset(&mut self) -> BoxFuture<Result<(), Error>> {
+        self.deref_mut().reset()
+    }
+}
+
+/// a connection handed out by the [`Pool`], given back to it when dropped.
+/// an abandoned transaction is rolled back and the `after_release` hooks run
+/// on a task spawned by the drop before the connection is idle again,
+/// without a tokio runtime they run when the connection is recycled at its next checkout
+pub struct PoolConnection {
+    inner: Option<Object<ManagerPorxy>>,
+}
+
+impl PoolConnection {
+    /// take the deadpool object, it is then released at its next checkout
+    pub fn into_inner(mut self) -> Object<ManagerPorxy> {
+        self.inner.take().unwrap()
+    }
+}
+
+impl Debug for PoolConnection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolConnection").finish()
+    }
+}
+
+impl Deref for PoolConnection {
+    type Target = Object<ManagerPorxy>;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PoolConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner.as_mut().unwrap()
+    }
+}
+
+impl Drop for PoolConnection {
+    fn drop(&mut self) {
+        let mut conn = match self.inner.take() {
+            Some(v) => v,
+            None => return,
+        };
+        conn.record_in_use(Some(Instant::now()));
+        if !conn.need_release() {
+            return;
+        }
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            //dropping the object at the end of the task puts the connection back to the pool
+            handle.spawn(async move {
+                if let Err(e) = conn.release().await {
+                    log::warn!("[rbdc] release connection fail={}", e);
+                    conn.broken = true;
+                }
+            });
+        }
+    }
+}
+
+impl Connection for PoolConnection {
     fn get_rows(
         &mut self,
         sql: &str,
@@ -291,18 +700,292 @@ impl Connection for Object<ManagerPorxy> {
         self.deref_mut().get_rows(sql, params)
     }
 
+    fn get_result_sets(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+    ) -> BoxFuture<Result<(Vec<Vec<Value>>, Vec<ExecResult>), Error>> {
+        self.deref_mut().get_result_sets(sql, params)
+    }
+
     fn exec(&mut self, sql: &str, params: Vec<Value>) -> BoxFuture<Result<ExecResult, Error>> {
         self.deref_mut().exec(sql, params)
     }
 
+    fn exec_batch(
+        &mut self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<Result<Vec<Result<ExecResult, Error>>, Error>> {
+        self.deref_mut().exec_batch(batch)
+    }
+
+    fn bulk_insert(
+        &mut self,
+        table: &str,
+        rows: Vec<Value>,
+    ) -> BoxFuture<Result<ExecResult, Error>> {
+        self.deref_mut().bulk_insert(table, rows)
+    }
+
     fn close(&mut self) -> BoxFuture<Result<(), Error>> {
         self.deref_mut().close()
     }
 
+    fn in_transaction(&self) -> Option<bool> {
+        self.deref().in_transaction()
+    }
+
+    fn cancel_handle(&self) -> Option<Box<dyn CancelHandle>> {
+        self.deref().cancel_handle()
+    }
+
     fn ping(&mut self) -> BoxFuture<Result<(), Error>> {
         self.deref_mut().ping()
     }
+
+    fn reset(&mut self) -> BoxFuture<Result<(), Error>> {
+        self.deref_mut().reset()
+    }
+}
+
+/// the bookkeeping of a statement run on a pooled connection
+struct Statement<'a> {
+    manager: &'a ManagerPorxy,
+    in_tx: &'a mut bool,
+    last_used_at: &'a mut Option<Instant>,
+    broken: &'a mut bool,
+    timeout: Option<Duration>,
+    cancel: Option<Box<dyn CancelHandle>>,
+}
+
+impl Statement<'_> {
+    /// run `f` within the statement timeout and record its time into the pool metrics.
+    /// `tx` is the transaction state after `f` succeeded, see [`tx_statement`]
+    async fn run<T>(
+        self,
+        tx: Option<bool>,
+        f: BoxFuture<'_, Result<T, Error>>,
+    ) -> Result<T, Error> {
+        if *self.broken {
+            return Err(Error::from("connection is broken by a timed out statement"));
+        }
+        let start = Instant::now();
+        let result = match self.timeout {
+            Some(timeout) => run_timeout(f, timeout, self.cancel, self.broken).await,
+            None => f.await,
+        };
+        self.manager.metrics.statement.record(start.elapsed());
+        match &result {
+            Ok(_) => {
+                if let Some(tx) = tx {
+                    *self.in_tx = tx;
+                }
+            }
+            Err(_) => self.manager.metrics.statement_errors.incr(),
+        }
+        *self.last_used_at = Some(Instant::now());
+        result
+    }
+}
+
+/// Some(true) for a sql starting a transaction, Some(false) for a sql ending it.
+/// T-SQL `begin tran` / `commit tran` / `rollback tran` may name the transaction
+fn tx_statement(sql: &str) -> Option<bool> {
+    let sql = sql.trim().trim_end_matches(';').trim_end().to_ascii_lowercase();
+    let words: Vec<&str> = sql.split_whitespace().collect();
+    match words.as_slice() {
+        ["begin"] | ["begin", "work"] | ["start", "transaction"] => Some(true),
+        ["begin", "tran" | "transaction"] | ["begin", "tran" | "transaction", _] => Some(true),
+        ["commit" | "rollback" | "end"] | ["commit" | "rollback", "work"] => Some(false),
+        ["commit" | "rollback", "tran" | "transaction"]
+        | ["commit" | "rollback", "tran" | "transaction", _] => Some(false),
+        _ => None,
+    }
 }
 
 #[test]
 fn test_pool() {}
+
+#[test]
+fn test_tx_statement() {
+    assert_eq!(tx_statement("begin"), Some(true));
+    assert_eq!(tx_statement(" START TRANSACTION; "), Some(true));
+    assert_eq!(tx_statement("commit"), Some(false));
+    assert_eq!(tx_statement("Rollback;"), Some(false));
+    assert_eq!(tx_statement("select 1"), None);
+    assert_eq!(tx_statement("begin;select 1"), None);
+    assert_eq!(tx_statement("BEGIN TRAN"), Some(true));
+    assert_eq!(tx_statement("begin transaction t1;"), Some(true));
+    assert_eq!(tx_statement("COMMIT TRAN"), Some(false));
+    assert_eq!(tx_statement("rollback  tran t1"), Some(false));
+    assert_eq!(tx_statement("commit transaction"), Some(false));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::{ConnectOptions, Connection, Driver, ExecResult, Row};
+    use crate::pool::{Pool, PoolOptions};
+    use crate::rt::block_on;
+    use crate::Error;
+    use futures_core::future::BoxFuture;
+    use rbs::Value;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// the sql executed on every connection of the driver
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    #[derive(Debug, Default)]
+    struct MockDriver {
+        log: Log,
+    }
+
+    impl Driver for MockDriver {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn connect(&self, _url: &str) -> BoxFuture<Result<Box<dyn Connection>, Error>> {
+            let log = self.log.clone();
+            Box::pin(async move { Ok(Box::new(MockConnection { log }) as Box<dyn Connection>) })
+        }
+
+        fn connect_opt<'a>(
+            &'a self,
+            _opt: &'a dyn ConnectOptions,
+        ) -> BoxFuture<Result<Box<dyn Connection>, Error>> {
+            self.connect("")
+        }
+
+        fn default_option(&self) -> Box<dyn ConnectOptions> {
+            Box::new(MockOptions)
+        }
+    }
+
+    #[derive(Debug)]
+    struct MockOptions;
+
+    impl ConnectOptions for MockOptions {
+        fn connect(&self) -> BoxFuture<Result<Box<dyn Connection>, Error>> {
+            Box::pin(async { Err(Error::from("connect by the driver")) })
+        }
+
+        fn set_uri(&mut self, _uri: &str) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    struct MockConnection {
+        log: Log,
+    }
+
+    impl Connection for MockConnection {
+        fn get_rows(
+            &mut self,
+            sql: &str,
+            _params: Vec<Value>,
+        ) -> BoxFuture<Result<Vec<Box<dyn Row>>, Error>> {
+            self.log.lock().unwrap().push(sql.to_string());
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn exec(&mut self, sql: &str, _params: Vec<Value>) -> BoxFuture<Result<ExecResult, Error>> {
+            self.log.lock().unwrap().push(sql.to_string());
+            Box::pin(async { Ok(ExecResult::default()) })
+        }
+
+        fn ping(&mut self) -> BoxFuture<Result<(), Error>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn reset(&mut self) -> BoxFuture<Result<(), Error>> {
+            self.log.lock().unwrap().push("reset".to_string());
+            Box::pin(async { Ok(()) })
+        }
+
+        fn close(&mut self) -> BoxFuture<Result<(), Error>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn mock_pool(options: PoolOptions) -> (Pool, Log) {
+        let driver = MockDriver::default();
+        let log = driver.log.clone();
+        let pool = Pool::new_options(Box::new(driver), Box::new(MockOptions), options).unwrap();
+        (pool, log)
+    }
+
+    #[test]
+    fn test_reaper_stops_when_pool_dropped() {
+        block_on(async move {
+            let options = PoolOptions::new()
+                .idle_timeout(Some(Duration::from_secs(60)))
+                .reaper_interval(Duration::from_millis(5));
+            let (pool, _) = mock_pool(options);
+            let handles = Arc::downgrade(&pool.handles);
+            let conn = pool.acquire().await.unwrap();
+            drop(conn);
+            drop(pool);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(handles.upgrade().is_none());
+        });
+    }
+
+    #[test]
+    fn test_after_release_on_return() {
+        block_on(async move {
+            let options = PoolOptions::new().after_release(|conn| {
+                Box::pin(async move {
+                    conn.exec("DISCARD ALL", vec![]).await?;
+                    Ok(())
+                })
+            });
+            let (pool, log) = mock_pool(options);
+            let mut conn = pool.acquire().await.unwrap();
+            conn.exec("select 1", vec![]).await.unwrap();
+            drop(conn);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert_eq!(*log.lock().unwrap(), ["select 1", "DISCARD ALL", "reset"]);
+            assert_eq!(pool.status().available, 1);
+            //the hooks ran already, the next checkout doesn't run them again
+            drop(pool.get().await.unwrap());
+            assert_eq!(log.lock().unwrap().len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_rollback_on_return() {
+        block_on(async move {
+            let (pool, log) = mock_pool(PoolOptions::new().max_size(1));
+            let mut conn = pool.acquire().await.unwrap();
+            conn.exec("begin tran", vec![]).await.unwrap();
+            assert_eq!(conn.in_transaction(), Some(true));
+            drop(conn);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert_eq!(*log.lock().unwrap(), ["begin tran", "rollback"]);
+            assert_eq!(pool.status().available, 1);
+            let conn = pool.acquire().await.unwrap();
+            assert_eq!(conn.in_transaction(), Some(false));
+        });
+    }
+
+    #[test]
+    fn test_in_use_until_returned() {
+        block_on(async move {
+            let options = PoolOptions::new().min_idle(1);
+            let (pool, _) = mock_pool(options);
+            pool.reap().await;
+            assert_eq!(pool.status().available, 1);
+            let metrics = pool.metrics();
+            assert_eq!(metrics.acquire_wait.count, 0);
+            let mut conn = pool.acquire().await.unwrap();
+            conn.exec("select 1", vec![]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(conn);
+            let metrics = pool.metrics();
+            assert_eq!(metrics.acquire_wait.count, 1);
+            assert_eq!(metrics.in_use.count, 1);
+            assert!(metrics.in_use.sum >= 0.02);
+        });
+    }
+}
@@ -0,0 +1,167 @@
+use crate::db::CancelHandle;
+use crate::Error;
+use futures_core::future::BoxFuture;
+use std::future::Future;
+use std::time::Duration;
+
+/// how long a cancelled statement has to finish before its connection is dropped
+pub const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+tokio::task_local! {
+    static STATEMENT_TIMEOUT: Option<Duration>;
+}
+
+/// run `f` with the statements of pooled connections limited to `timeout`,
+/// overriding `PoolOptions::statement_timeout`. None disables the timeout
+/// ```rust
+/// use std::time::Duration;
+/// use rbdc::pool::with_statement_timeout;
+/// # async fn f() {
+/// let v = with_statement_timeout(Some(Duration::from_secs(3)), async {
+///     //rb.query("select pg_sleep(10)", vec![]).await
+/// })
+/// .await;
+/// # }
+/// ```
+pub async fn with_statement_timeout<F: Future>(timeout: Option<Duration>, f: F) -> F::Output {
+    STATEMENT_TIMEOUT.scope(timeout, f).await
+}
+
+/// the timeout of the current `with_statement_timeout` scope, or `default`
+pub fn statement_timeout(default: Option<Duration>) -> Option<Duration> {
+    STATEMENT_TIMEOUT.try_with(|v| *v).unwrap_or(default)
+}
+
+/// await `f` for `timeout`, then cancel it on the server and wait [`CANCEL_GRACE`] for it.
+/// `broken` is set when the statement may still be running,
+/// or when it finished before the cancel and the cancel may hit the next statement
+pub(crate) async fn run_timeout<T>(
+    mut f: BoxFuture<'_, Result<T, Error>>,
+    timeout: Duration,
+    cancel: Option<Box<dyn CancelHandle>>,
+    broken: &mut bool,
+) -> Result<T, Error> {
+    if let Ok(v) = tokio::time::timeout(timeout, &mut f).await {
+        return v;
+    }
+    let err = Error::from(format!("statement timeout after {:?}", timeout));
+    if let Some(cancel) = cancel {
+        match cancel.cancel().await {
+            Ok(_) => {
+                if let Ok(v) = tokio::time::timeout(CANCEL_GRACE, &mut f).await {
+                    if v.is_ok() {
+                        //the statement finished before the cancel arrived,
+                        //the server may still cancel whatever runs next on the connection
+                        *broken = true;
+                        return v;
+                    }
+                    return v.map_err(|_| err);
+                }
+            }
+            Err(e) => {
+                log::warn!("[rbdc] cancel statement fail: {}", e);
+            }
+        }
+    }
+    *broken = true;
+    Err(err)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::CancelHandle;
+    use crate::pool::timeout::run_timeout;
+    use crate::pool::{statement_timeout, with_statement_timeout};
+    use crate::rt::block_on;
+    use crate::Error;
+    use futures_core::future::BoxFuture;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    struct MockCancel(Arc<AtomicBool>);
+
+    impl CancelHandle for MockCancel {
+        fn cancel(&self) -> BoxFuture<'static, Result<(), Error>> {
+            let v = self.0.clone();
+            Box::pin(async move {
+                v.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn test_statement_timeout_scope() {
+        block_on(async move {
+            let default = Some(Duration::from_secs(1));
+            assert_eq!(statement_timeout(default), default);
+            let v = with_statement_timeout(None, async { statement_timeout(default) }).await;
+            assert_eq!(v, None);
+        });
+    }
+
+    #[test]
+    fn test_run_timeout_cancel() {
+        block_on(test_run_timeout_cancel_task());
+    }
+
+    async fn test_run_timeout_cancel_task() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        let f: BoxFuture<Result<(), Error>> = Box::pin(async move {
+            while !flag.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            Err(Error::from("canceling statement due to user request"))
+        });
+        let mut broken = false;
+        let r = run_timeout(
+            f,
+            Duration::from_millis(10),
+            Some(Box::new(MockCancel(cancelled))),
+            &mut broken,
+        )
+        .await;
+        assert!(r.unwrap_err().to_string().contains("statement timeout"));
+        assert!(!broken);
+    }
+
+    #[test]
+    fn test_run_timeout_finished_before_cancel() {
+        block_on(test_run_timeout_finished_before_cancel_task());
+    }
+
+    async fn test_run_timeout_finished_before_cancel_task() {
+        let f: BoxFuture<Result<i32, Error>> = Box::pin(async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(1)
+        });
+        let mut broken = false;
+        let r = run_timeout(
+            f,
+            Duration::from_millis(10),
+            Some(Box::new(MockCancel(Arc::new(AtomicBool::new(false))))),
+            &mut broken,
+        )
+        .await;
+        assert_eq!(r.unwrap(), 1);
+        assert!(broken);
+    }
+
+    #[test]
+    fn test_run_timeout_without_cancel() {
+        block_on(test_run_timeout_without_cancel_task());
+    }
+
+    async fn test_run_timeout_without_cancel_task() {
+        let f: BoxFuture<Result<(), Error>> = Box::pin(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        });
+        let mut broken = false;
+        let r = run_timeout(f, Duration::from_millis(10), None, &mut broken).await;
+        assert!(r.is_err());
+        assert!(broken);
+    }
+}
@@ -34,6 +34,8 @@ macro_rules! impl_insert {
     };
     ($table:ty{},$table_name:expr) => {
         impl $table {
+            /// the statements are split by the `DEFAULT_MAX_PARAMS` of any database,
+            /// use `insert_batch_exec` (or `insert_batch_limit`) for the smaller limit of the driver, like SQL Server
             pub fn insert_batch(
                 // executor: &dyn $crate::executor::Executor,
                 tables: &[$table],
@@ -41,6 +43,19 @@ macro_rules! impl_insert {
             ) -> std::result::Result<
                 Vec<(String, Vec<rbs::Value>, bool, Option<u64>, Option<u64>)>,
                 $crate::rbdc::Error,
+            > {
+                <$table>::insert_batch_limit(tables, batch_size, $crate::rbdc::db::DEFAULT_MAX_PARAMS)
+            }
+
+            /// insert_batch with statements binding no more than `max_params`,
+            /// pass `rb.max_params()?` to respect the limit of the driver
+            pub fn insert_batch_limit(
+                tables: &[$table],
+                batch_size: u64,
+                max_params: usize,
+            ) -> std::result::Result<
+                Vec<(String, Vec<rbs::Value>, bool, Option<u64>, Option<u64>)>,
+                $crate::rbdc::Error,
             > {
                 #[$crate::py_sql(
                     "`insert into ${table_name} `
@@ -82,7 +97,17 @@ macro_rules! impl_insert {
                 // };
                 let mut res =
                     Vec::<(String, Vec<rbs::Value>, bool, Option<u64>, Option<u64>)>::new();
-                let ranges = $crate::sql::Page::<()>::make_ranges(tables.len() as u64, batch_size);
+                // every row binds one parameter per column
+                let columns = match rbs::to_value!(&tables[0]) {
+                    rbs::Value::Map(m) => m.len() as u64,
+                    _ => 1,
+                };
+                let ranges = $crate::sql::Page::<()>::make_param_ranges(
+                    tables.len() as u64,
+                    batch_size,
+                    columns,
+                    max_params as u64,
+                );
                 for (offset, limit) in ranges {
                     let result = insert_batch(
                         // executor,
@@ -97,6 +122,17 @@ macro_rules! impl_insert {
                 Ok(res)
             }
 
+            /// insert_batch in chunks that fit the parameter limit of the executor's driver,
+            /// all chunks are sent with one `exec_batch`
+            pub async fn insert_batch_exec(
+                executor: &dyn $crate::executor::Executor,
+                tables: &[$table],
+                batch_size: u64,
+            ) -> std::result::Result<$crate::rbdc::db::ExecResult, $crate::rbdc::Error> {
+                let plan = <$table>::insert_batch_limit(tables, batch_size, executor.max_params()?)?;
+                $crate::executor::exec_plan(executor, plan).await
+            }
+
             pub fn insert(
                 table: &$table,
             ) -> std::result::Result<
@@ -136,6 +172,23 @@ macro_rules! impl_select {
           trim ',': for _,item in column_values:
              #{item},
           `)`"},$table_name);
+        impl $table {
+            /// select_in_column split into statements binding no more than `batch_size` values
+            /// and `max_params`, pass `rb.max_params()?` to respect the limit of the driver
+            pub fn select_in_column_batch<V:serde::Serialize>(
+                column: &str,
+                column_values: &[V],
+                batch_size: u64,
+                max_params: usize,
+            ) -> std::result::Result<Vec<(String, Vec<rbs::Value>, bool, Option<u64>, Option<u64>)>, $crate::rbdc::Error> {
+                let mut res = Vec::<(String, Vec<rbs::Value>, bool, Option<u64>, Option<u64>)>::new();
+                let ranges = $crate::sql::Page::<()>::make_param_ranges(column_values.len() as u64, batch_size, 1, max_params as u64);
+                for (offset, limit) in ranges {
+                    res.push(<$table>::select_in_column(column,&column_values[offset as usize..limit as usize])?);
+                }
+                Ok(res)
+            }
+        }
     };
     ($table:ty{$fn_name:ident $(< $($gkey:ident:$gtype:path $(,)?)* >)? ($($param_key:ident:$param_type:ty $(,)?)*) => $sql:expr}$(,$table_name:expr)?) => {
         $crate::impl_select!($table{$fn_name$(<$($gkey:$gtype,)*>)?($($param_key:$param_type,)*) ->Vec => $sql}$(,$table_name)?);
@@ -205,6 +258,17 @@ macro_rules! impl_update {
                 }
                 Ok(res)
             }
+
+            /// update_by_column_batch with all statements sent in one `exec_batch`
+            pub async fn update_by_column_batch_exec(
+                executor: &dyn $crate::executor::Executor,
+                tables: &[$table],
+                column: &str,
+                batch_size: u64,
+            ) -> std::result::Result<$crate::rbdc::db::ExecResult, $crate::rbdc::Error> {
+                let plan = <$table>::update_by_column_batch(tables, column, batch_size)?;
+                $crate::executor::exec_plan(executor, plan).await
+            }
         }
     };
     ($table:ty{$fn_name:ident($($param_key:ident:$param_type:ty$(,)?)*) => $sql_where:expr}$(,$table_name:expr)?) => {
@@ -267,19 +331,44 @@ macro_rules! impl_delete {
           `)`"},$table_name);
 
         impl $table {
+            /// the statements are split by the `DEFAULT_MAX_PARAMS` of any database,
+            /// use `delete_by_column_batch_exec` (or `delete_by_column_batch_limit`) for the smaller limit of the driver
             pub fn delete_by_column_batch<V:serde::Serialize>(
                 column: &str,
                 values: &[V],
                 batch_size: u64,
+            ) -> std::result::Result<Vec<(String, Vec<rbs::Value> ,bool, Option<u64>, Option<u64>)>, $crate::rbdc::Error> {
+                <$table>::delete_by_column_batch_limit(column, values, batch_size, $crate::rbdc::db::DEFAULT_MAX_PARAMS)
+            }
+
+            /// delete_by_column_batch with statements binding no more than `max_params`,
+            /// pass `rb.max_params()?` to respect the limit of the driver
+            pub fn delete_by_column_batch_limit<V:serde::Serialize>(
+                column: &str,
+                values: &[V],
+                batch_size: u64,
+                max_params: usize,
             ) -> std::result::Result<Vec<(String, Vec<rbs::Value> ,bool, Option<u64>, Option<u64>)>, $crate::rbdc::Error> {
                 let mut res = Vec::<(String, Vec<rbs::Value>, bool, Option<u64>, Option<u64>)>::new();
-                let ranges = $crate::sql::Page::<()>::make_ranges(values.len() as u64, batch_size);
+                let ranges = $crate::sql::Page::<()>::make_param_ranges(values.len() as u64, batch_size, 1, max_params as u64);
                 for (offset, limit) in ranges {
                     let result = <$table>::delete_in_column(column,&values[offset as usize..limit as usize])?;
                     res.push((result.0, result.1, false, None, None));
                 }
                 Ok(res)
             }
+
+            /// delete_by_column_batch in chunks that fit the parameter limit of the executor's driver,
+            /// all chunks are sent with one `exec_batch`
+            pub async fn delete_by_column_batch_exec<V:serde::Serialize>(
+                executor: &dyn $crate::executor::Executor,
+                column: &str,
+                values: &[V],
+                batch_size: u64,
+            ) -> std::result::Result<$crate::rbdc::db::ExecResult, $crate::rbdc::Error> {
+                let plan = <$table>::delete_by_column_batch_limit(column, values, batch_size, executor.max_params()?)?;
+                $crate::executor::exec_plan(executor, plan).await
+            }
         }
     };
     ($table:ty{$fn_name:ident $(< $($gkey:ident:$gtype:path $(,)?)* >)? ($($param_key:ident:$param_type:ty$(,)?)*) => $sql_where:expr}$(,$table_name:expr)?) => {
@@ -1,8 +1,10 @@
 use crate::decode::decode;
-use crate::intercept::ResultType;
+use crate::intercept::{with_context, InterceptContext, ResultType};
 use crate::rbatis::RBatis;
+use crate::retry::is_idempotent;
 use crate::snowflake::new_snowflake_id;
 use crate::sql::Tx;
+use crate::trace::{operation, StatementSpan};
 use crate::{Error, utils};
 use futures::Future;
 use futures_core::future::BoxFuture;
@@ -11,7 +13,10 @@ use rbs::Value;
 use serde::de::DeserializeOwned;
 use std::fmt::{Debug, Formatter};
 use dark_std::sync::SyncVec;
+use rbdc::pool::with_statement_timeout;
 use rbdc::rt::tokio::sync::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// the rbatis's Executor. this trait impl with structs = RBatis,RBatisConnExecutor,RBatisTxExecutor,RBatisTxExecutorGuard
 pub trait Executor: RBatisRef + Send + Sync {
@@ -21,6 +26,172 @@ pub trait Executor: RBatisRef + Send + Sync {
     }
     fn exec(&self, sql: &str, args: Vec<Value>) -> BoxFuture<'_, Result<ExecResult, Error>>;
     fn query(&self, sql: &str, args: Vec<Value>) -> BoxFuture<'_, Result<Value, Error>>;
+
+    /// exec with a statement timeout overriding `PoolOptions::statement_timeout`, None disables it.
+    /// a timed out statement is cancelled on the server, see `rbdc::pool::with_statement_timeout`
+    fn exec_timeout(
+        &self,
+        sql: &str,
+        args: Vec<Value>,
+        timeout: Option<Duration>,
+    ) -> BoxFuture<'_, Result<ExecResult, Error>> {
+        let sql = sql.to_string();
+        Box::pin(with_statement_timeout(timeout, async move {
+            self.exec(&sql, args).await
+        }))
+    }
+
+    /// query with a statement timeout overriding `PoolOptions::statement_timeout`, None disables it
+    fn query_timeout(
+        &self,
+        sql: &str,
+        args: Vec<Value>,
+        timeout: Option<Duration>,
+    ) -> BoxFuture<'_, Result<Value, Error>> {
+        let sql = sql.to_string();
+        Box::pin(with_statement_timeout(timeout, async move {
+            self.query(&sql, args).await
+        }))
+    }
+
+    /// exec many sql, return one ExecResult for each sql.
+    /// the default impl calls exec one by one, executors holding a connection
+    /// send the batch with `Connection::exec_batch` (pipelined on postgres)
+    fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<'_, Result<Vec<ExecResult>, Error>> {
+        Box::pin(async move {
+            let mut results = Vec::with_capacity(batch.len());
+            for (sql, args) in batch {
+                results.push(self.exec(&sql, args).await?);
+            }
+            Ok(results)
+        })
+    }
+}
+
+/// run the statements of a crud plan (`insert_batch_limit`, `update_by_column_batch`...) with one
+/// `exec_batch`, pipelined on postgres. return the sum of rows_affected and the last last_insert_id.
+///
+/// fails before running anything when a statement binds more params than the driver takes
+pub async fn exec_plan(
+    executor: &dyn Executor,
+    plan: Vec<(String, Vec<Value>, bool, Option<u64>, Option<u64>)>,
+) -> Result<ExecResult, Error> {
+    let max_params = executor.max_params()?;
+    if let Some((sql, args, _, _, _)) = plan.iter().find(|v| v.1.len() > max_params) {
+        return Err(Error::from(format!(
+            "sql binds {} params, more than the {} of the {} driver, split it with the max_params of the driver: {}",
+            args.len(),
+            max_params,
+            executor.driver_type()?,
+            sql
+        )));
+    }
+    let batch = plan
+        .into_iter()
+        .map(|(sql, args, _, _, _)| (sql, args))
+        .collect();
+    let mut result = ExecResult::default();
+    for v in executor.exec_batch(batch).await? {
+        result.rows_affected += v.rows_affected;
+        result.last_insert_id = v.last_insert_id;
+    }
+    Ok(result)
+}
+
+/// run intercepts around every sql of the batch, and send the sql not intercepted to the connection in one batch
+async fn exec_batch_intercepted(
+    executor: &dyn Executor,
+    task_id: i64,
+    tx_id: Option<i64>,
+    conn: &Mutex<Box<dyn Connection>>,
+    batch: Vec<(String, Vec<Value>)>,
+) -> Result<Vec<ExecResult>, Error> {
+    let mut results: Vec<Option<Result<ExecResult, Error>>> = Vec::with_capacity(batch.len());
+    let mut pending = Vec::with_capacity(batch.len());
+    let mut send = Vec::with_capacity(batch.len());
+    'batch: for (index, (mut sql, mut args)) in batch.into_iter().enumerate() {
+        let ctx = Arc::new(InterceptContext::new(task_id));
+        let mut before_result = Err(Error::from(""));
+        for item in executor.rb_ref().intercepts.iter() {
+            let next = with_context(
+                &ctx,
+                item.before(
+                    task_id,
+                    executor,
+                    &mut sql,
+                    &mut args,
+                    ResultType::Exec(&mut before_result),
+                ),
+            )
+            .await?;
+            if !next {
+                results.push(Some(before_result));
+                continue 'batch;
+            }
+        }
+        results.push(None);
+        pending.push((index, ctx, sql.clone(), args.clone()));
+        send.push((sql, args));
+    }
+    let statement = send
+        .iter()
+        .map(|(sql, _)| sql.as_str())
+        .collect::<Vec<_>>()
+        .join(";\n");
+    let mut span = StatementSpan::new(executor.rb_ref(), task_id, tx_id, "BATCH", &statement);
+    let mut conn = conn.lock().await;
+    let batch_result = span.instrument(conn.exec_batch(send)).await;
+    drop(conn);
+    let elapsed = span.elapsed();
+    span.record_batch(&batch_result);
+    let mut batch_result = match batch_result {
+        Ok(v) => v,
+        // the batch failed as a whole, the error goes to its first statement
+        Err(e) => std::iter::once(Err(e))
+            .chain(pending.iter().skip(1).map(|_| Err(Error::batch_aborted())))
+            .collect(),
+    }
+    .into_iter();
+    for (index, ctx, mut sql, mut args_after) in pending {
+        ctx.set_elapsed(elapsed);
+        let mut result = batch_result
+            .next()
+            .unwrap_or_else(|| Err(Error::from("[rbatis] exec_batch lost result")));
+        for item in executor.rb_ref().intercepts.iter() {
+            let next = with_context(
+                &ctx,
+                item.after(
+                    task_id,
+                    executor,
+                    &mut sql,
+                    &mut args_after,
+                    ResultType::Exec(&mut result),
+                ),
+            )
+            .await?;
+            if !next {
+                break;
+            }
+        }
+        results[index] = Some(result);
+    }
+    // return the error of the failed statement rather than of the ones aborted with it
+    let mut values = Vec::with_capacity(results.len());
+    let mut aborted = None;
+    for v in results.into_iter().flatten() {
+        match v {
+            Ok(v) => values.push(v),
+            Err(e) if e.is_batch_aborted() => aborted = aborted.or(Some(e)),
+            Err(e) => return Err(e),
+        }
+    }
+    match aborted {
+        Some(e) => Err(e),
+        None => Ok(values),
+    }
 }
 
 pub trait RBatisRef: Send + Sync {
@@ -33,6 +204,10 @@ pub trait RBatisRef: Send + Sync {
     fn driver_type(&self) -> crate::Result<&str> {
         self.rb_ref().driver_type()
     }
+
+    fn max_params(&self) -> crate::Result<usize> {
+        self.rb_ref().max_params()
+    }
 }
 
 impl RBatisRef for RBatis {
@@ -62,6 +237,13 @@ impl RBatisConnExecutor {
         Ok(v)
     }
 
+    pub async fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> Result<Vec<ExecResult>, Error> {
+        Executor::exec_batch(self, batch).await
+    }
+
     pub async fn query(&self, sql: &str, args: Vec<Value>) -> Result<Value, Error> {
         let v = Executor::query(self, sql, args).await?;
         Ok(v)
@@ -81,33 +263,51 @@ impl Executor for RBatisConnExecutor {
         let mut sql = sql.to_string();
         Box::pin(async move {
             let rb_task_id = self.id + utils::timestamp::create_timestamp();
+            let ctx = Arc::new(InterceptContext::new(rb_task_id));
             let mut before_result = Err(Error::from(""));
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .before(
+                let next = with_context(
+                    &ctx,
+                    item.before(
                         rb_task_id,
                         self as &dyn Executor,
                         &mut sql,
                         &mut args,
                         ResultType::Exec(&mut before_result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return before_result;
                 }
             }
             let mut args_after = args.clone();
-            let mut result = self.conn.lock().await.exec(&sql, args).await;
+            let mut conn = self.conn.lock().await;
+            let retry = is_idempotent();
+            let mut attempt = 1;
+            let mut span = StatementSpan::new(&self.rb, rb_task_id, None, &operation(&sql), &sql);
+            let mut result = span.instrument(conn.exec(&sql, args.clone())).await;
+            while let Err(e) = &result {
+                if !self.reconnect(&mut conn, retry, &mut attempt, e).await {
+                    break;
+                }
+                result = span.instrument(conn.exec(&sql, args.clone())).await;
+            }
+            drop(conn);
+            ctx.set_elapsed(span.elapsed());
+            span.record_exec(&result);
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .after(
+                let next = with_context(
+                    &ctx,
+                    item.after(
                         rb_task_id,
                         self as &dyn Executor,
                         &mut sql,
                         &mut args_after,
                         ResultType::Exec(&mut result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return result;
                 }
@@ -120,34 +320,52 @@ impl Executor for RBatisConnExecutor {
         let mut sql = sql.to_string();
         Box::pin(async move {
             let rb_task_id = self.id + utils::timestamp::create_timestamp();
+            let ctx = Arc::new(InterceptContext::new(rb_task_id));
             let mut before_result = Err(Error::from(""));
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .before(
+                let next = with_context(
+                    &ctx,
+                    item.before(
                         rb_task_id,
                         self,
                         &mut sql,
                         &mut args,
                         ResultType::Query(&mut before_result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return before_result.map(|v| Value::from(v));
                 }
             }
             let mut conn = self.conn.lock().await;
             let mut args_after = args.clone();
-            let mut result = conn.get_values(&sql, args).await;
+            let retry = is_idempotent()
+                || self.rb.retry_policy().map_or(false, |v| v.is_read(&sql));
+            let mut attempt = 1;
+            let mut span = StatementSpan::new(&self.rb, rb_task_id, None, &operation(&sql), &sql);
+            let mut result = span.instrument(conn.get_values(&sql, args.clone())).await;
+            while let Err(e) = &result {
+                if !self.reconnect(&mut conn, retry, &mut attempt, e).await {
+                    break;
+                }
+                result = span.instrument(conn.get_values(&sql, args.clone())).await;
+            }
+            drop(conn);
+            ctx.set_elapsed(span.elapsed());
+            span.record_query(&result);
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .after(
+                let next = with_context(
+                    &ctx,
+                    item.after(
                         rb_task_id,
                         self,
                         &mut sql,
                         &mut args_after,
                         ResultType::Query(&mut result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return result.map(|v| Value::from(v));
                 }
@@ -155,6 +373,16 @@ impl Executor for RBatisConnExecutor {
             Ok(Value::Array(result?))
         })
     }
+
+    fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<'_, Result<Vec<ExecResult>, Error>> {
+        Box::pin(async move {
+            let rb_task_id = self.id + utils::timestamp::create_timestamp();
+            exec_batch_intercepted(self, rb_task_id, None, &self.conn, batch).await
+        })
+    }
 }
 
 impl RBatisRef for RBatisConnExecutor {
@@ -164,6 +392,43 @@ impl RBatisRef for RBatisConnExecutor {
 }
 
 impl RBatisConnExecutor {
+    /// after `e`, wait the backoff of `RBatis::retry_policy` and replace `conn` with a fresh
+    /// connection from the pool. false if the statement must not be retried
+    async fn reconnect(
+        &self,
+        conn: &mut Box<dyn Connection>,
+        retry: bool,
+        attempt: &mut usize,
+        e: &Error,
+    ) -> bool {
+        let policy = match self.rb.retry_policy() {
+            Some(v) if retry => v,
+            _ => return false,
+        };
+        if !policy.is_retryable(e) || conn.in_transaction() == Some(true) {
+            return false;
+        }
+        let pool = match self.rb.get_pool() {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+        while *attempt < policy.max_attempts {
+            rbdc::rt::sleep(policy.delay(*attempt)).await;
+            *attempt += 1;
+            log::warn!("[rbatis] [{}] retry {} after: {}", self.id, attempt, e);
+            match pool.acquire().await {
+                Ok(v) => {
+                    *conn = Box::new(v);
+                    return true;
+                }
+                Err(e) => {
+                    log::warn!("[rbatis] [{}] reconnect fail: {}", self.id, e);
+                }
+            }
+        }
+        false
+    }
+
     pub async fn begin(self) -> crate::Result<RBatisTxExecutor> {
         let tx = self.conn.into_inner().begin().await?;
         return Ok(RBatisTxExecutor {
@@ -199,6 +464,13 @@ impl<'a> RBatisTxExecutor {
         let v = Executor::exec(self, sql, args).await?;
         Ok(v)
     }
+    /// exec many sql in one batch
+    pub async fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> Result<Vec<ExecResult>, Error> {
+        Executor::exec_batch(self, batch).await
+    }
     /// query value
     pub async fn query(&self, sql: &str, args: Vec<Value>) -> Result<Value, Error> {
         let v = Executor::query(self, sql, args).await?;
@@ -218,33 +490,44 @@ impl Executor for RBatisTxExecutor {
     fn exec(&self, sql: &str, mut args: Vec<Value>) -> BoxFuture<'_, Result<ExecResult, Error>> {
         let mut sql = sql.to_string();
         Box::pin(async move {
+            let ctx = Arc::new(InterceptContext::new(self.tx_id));
             let mut before_result = Err(Error::from(""));
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .before(
+                let next = with_context(
+                    &ctx,
+                    item.before(
                         self.tx_id,
                         self,
                         &mut sql,
                         &mut args,
                         ResultType::Exec(&mut before_result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return before_result;
                 }
             }
             let mut args_after = args.clone();
-            let mut result = self.conn.lock().await.exec(&sql, args).await;
+            let mut span =
+                StatementSpan::new(&self.rb, self.tx_id, Some(self.tx_id), &operation(&sql), &sql);
+            let mut conn = self.conn.lock().await;
+            let mut result = span.instrument(conn.exec(&sql, args)).await;
+            drop(conn);
+            ctx.set_elapsed(span.elapsed());
+            span.record_exec(&result);
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .after(
+                let next = with_context(
+                    &ctx,
+                    item.after(
                         self.tx_id,
                         self,
                         &mut sql,
                         &mut args_after,
                         ResultType::Exec(&mut result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return result;
                 }
@@ -256,35 +539,44 @@ impl Executor for RBatisTxExecutor {
     fn query(&self, sql: &str, mut args: Vec<Value>) -> BoxFuture<'_, Result<Value, Error>> {
         let mut sql = sql.to_string();
         Box::pin(async move {
+            let ctx = Arc::new(InterceptContext::new(self.tx_id));
             let mut before_result = Err(Error::from(""));
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .before(
+                let next = with_context(
+                    &ctx,
+                    item.before(
                         self.tx_id,
                         self,
                         &mut sql,
                         &mut args,
                         ResultType::Query(&mut before_result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return before_result.map(|v| Value::from(v));
                 }
             }
             let mut conn = self.conn.lock().await;
             let mut args_after = args.clone();
-            let conn = conn.get_values(&sql, args);
-            let mut result = conn.await;
+            let mut span =
+                StatementSpan::new(&self.rb, self.tx_id, Some(self.tx_id), &operation(&sql), &sql);
+            let mut result = span.instrument(conn.get_values(&sql, args)).await;
+            drop(conn);
+            ctx.set_elapsed(span.elapsed());
+            span.record_query(&result);
             for item in self.rb_ref().intercepts.iter() {
-                let next = item
-                    .after(
+                let next = with_context(
+                    &ctx,
+                    item.after(
                         self.tx_id,
                         self,
                         &mut sql,
                         &mut args_after,
                         ResultType::Query(&mut result),
-                    )
-                    .await?;
+                    ),
+                )
+                .await?;
                 if !next {
                     return result.map(|v| Value::from(v));
                 }
@@ -292,6 +584,15 @@ impl Executor for RBatisTxExecutor {
             Ok(Value::Array(result?))
         })
     }
+
+    fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<'_, Result<Vec<ExecResult>, Error>> {
+        Box::pin(async move {
+            exec_batch_intercepted(self, self.tx_id, Some(self.tx_id), &self.conn, batch).await
+        })
+    }
 }
 
 impl RBatisRef for RBatisTxExecutor {
@@ -443,6 +744,18 @@ impl Executor for RBatisTxExecutorGuard {
             }
         })
     }
+
+    fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<'_, Result<Vec<ExecResult>, Error>> {
+        Box::pin(async move {
+            match self.tx.as_ref() {
+                None => Err(Error::from("the tx is done!")),
+                Some(tx) => tx.exec_batch(batch).await,
+            }
+        })
+    }
 }
 
 impl RBatis {
@@ -452,6 +765,15 @@ impl RBatis {
         conn.exec(sql, args).await
     }
 
+    /// exec many sql on one connection, postgres sends the whole batch in one round trip
+    pub async fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> Result<Vec<ExecResult>, Error> {
+        let conn = self.acquire().await?;
+        conn.exec_batch(batch).await
+    }
+
     /// query raw Value
     pub async fn query(&self, sql: &str, args: Vec<Value>) -> Result<Value, Error> {
         let conn = self.acquire().await?;
@@ -486,6 +808,16 @@ impl Executor for RBatis {
             conn.query(&sql, args).await
         })
     }
+
+    fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<'_, Result<Vec<ExecResult>, Error>> {
+        Box::pin(async move {
+            let conn = self.acquire().await?;
+            conn.exec_batch(batch).await
+        })
+    }
 }
 
 impl RBatisRef for &RBatis {
@@ -510,6 +842,16 @@ impl Executor for &RBatis {
             conn.query(&sql, args).await
         })
     }
+
+    fn exec_batch(
+        &self,
+        batch: Vec<(String, Vec<Value>)>,
+    ) -> BoxFuture<'_, Result<Vec<ExecResult>, Error>> {
+        Box::pin(async move {
+            let conn = self.acquire().await?;
+            conn.exec_batch(batch).await
+        })
+    }
 }
 
 
@@ -3,7 +3,15 @@ use crate::Error;
 use async_trait::async_trait;
 use rbdc::db::ExecResult;
 use rbs::Value;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+rbdc::rt::tokio::task_local! {
+    static CONTEXT: Arc<InterceptContext>;
+}
 
 #[derive(Debug, Clone)]
 pub enum ResultType<A, B> {
@@ -22,6 +30,68 @@ impl<A, B> ResultType<A, B> {
     }
 }
 
+/// the context of one statement, shared by the `before` and `after` of every intercept.
+/// get it inside an intercept with [`context`]
+#[derive(Debug)]
+pub struct InterceptContext {
+    /// same as the task_id of `before`/`after`
+    pub task_id: i64,
+    /// created before the first `before`
+    pub start: Instant,
+    elapsed: Mutex<Option<Duration>>,
+    data: Mutex<HashMap<String, Value>>,
+}
+
+impl InterceptContext {
+    pub fn new(task_id: i64) -> Self {
+        Self {
+            task_id,
+            start: Instant::now(),
+            elapsed: Mutex::new(None),
+            data: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// the time the database took to run the statement once it ran, the sum of its attempts
+    /// without waiting for the connection or the retry backoff,
+    /// or the time since `start` (in `before`, or when a `before` returned the result)
+    pub fn elapsed(&self) -> Duration {
+        match *self.elapsed.lock().unwrap() {
+            Some(v) => v,
+            None => self.start.elapsed(),
+        }
+    }
+
+    pub(crate) fn set_elapsed(&self, elapsed: Duration) {
+        *self.elapsed.lock().unwrap() = Some(elapsed);
+    }
+
+    /// get user data stored by a previous `before`/`after`
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.data.lock().unwrap().get(key).cloned()
+    }
+
+    /// store user data for the next `before`/`after` of this statement
+    pub fn insert(&self, key: &str, value: Value) -> Option<Value> {
+        self.data.lock().unwrap().insert(key.to_string(), value)
+    }
+
+    pub fn remove(&self, key: &str) -> Option<Value> {
+        self.data.lock().unwrap().remove(key)
+    }
+}
+
+/// the [`InterceptContext`] of the statement, inside `Intercept::before`/`Intercept::after`.
+/// None when called outside an intercept
+pub fn context() -> Option<Arc<InterceptContext>> {
+    CONTEXT.try_with(|v| v.clone()).ok()
+}
+
+/// run the intercept future `f` with `ctx` as its [`context`]
+pub(crate) async fn with_context<F: Future>(ctx: &Arc<InterceptContext>, f: F) -> F::Output {
+    CONTEXT.scope(ctx.clone(), f).await
+}
+
 /// sql intercept
 /// example:
 ///
@@ -57,7 +127,8 @@ pub trait Intercept: Send + Sync + Debug {
 
     /// task_id maybe is conn_id or tx_id,
     /// is_prepared_sql = !args.is_empty(),
-    /// if return Ok(false) will be return data. return Ok(true) will run next
+    /// if return Ok(false) will be return data. return Ok(true) will run next.
+    /// [`context`] is shared with `after`
     async fn before(
         &self,
         _task_id: i64,
@@ -71,7 +142,8 @@ pub trait Intercept: Send + Sync + Debug {
 
     /// task_id maybe is conn_id or tx_id,
     /// is_prepared_sql = !args.is_empty(),
-    /// if return Ok(false) will be return data. return Ok(true) will run next
+    /// if return Ok(false) will be return data. return Ok(true) will run next.
+    /// `context().elapsed()` is the time the statement took
     async fn after(
         &self,
         _task_id: i64,
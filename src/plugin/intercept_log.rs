@@ -1,6 +1,8 @@
 use crate::decode::is_debug_mode;
 use crate::executor::Executor;
-use crate::intercept::{Intercept, ResultType};
+use crate::intercept::{context, Intercept, ResultType};
+use crate::redact::Redactor;
+use crate::trace::operation;
 use crate::{Error, RBatis};
 use async_trait::async_trait;
 use log::{log, Level, LevelFilter};
@@ -8,7 +10,9 @@ use rbdc::db::ExecResult;
 use rbs::Value;
 use std::fmt::{Display, Formatter};
 use std::ops::Deref;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
 use rbatis_codegen::ops::AsProxy;
 
 struct RbsValueDisplay<'a> {
@@ -54,11 +58,23 @@ pub struct LogInterceptor {
     /// replace_holder=true 'select column from table' []
     /// replace_holder=false 'select ? from table' ['column']
     pub replace_holder: AtomicBool,
+
+    /// statements slower than this many milliseconds are logged at Warn, 0=Off.
+    /// the slow query log ignores level_filter
+    pub slow_threshold: AtomicU64,
+
+    /// redact args and rows (debug_mode) before they are logged
+    pub redactor: RwLock<Redactor>,
 }
 
 impl Clone for LogInterceptor {
     fn clone(&self) -> Self {
-        LogInterceptor::new(self.get_level_filter())
+        Self {
+            level_filter: AtomicUsize::new(self.level_filter.load(Ordering::Relaxed)),
+            replace_holder: AtomicBool::new(self.get_replace_holder()),
+            slow_threshold: AtomicU64::new(self.slow_threshold.load(Ordering::Relaxed)),
+            redactor: RwLock::new(self.get_redactor()),
+        }
     }
 }
 
@@ -67,6 +83,8 @@ impl LogInterceptor {
         let s = Self {
             level_filter: AtomicUsize::new(0),
             replace_holder: AtomicBool::new(false),
+            slow_threshold: AtomicU64::new(0),
+            redactor: RwLock::new(Redactor::default()),
         };
         s.set_level_filter(level_filter);
         s
@@ -117,6 +135,49 @@ impl LogInterceptor {
     pub fn get_replace_holder(&self) -> bool {
         self.replace_holder.load(Ordering::Relaxed)
     }
+
+    /// log statements slower than `threshold` at Warn, None=Off
+    pub fn set_slow_threshold(&self, threshold: Option<Duration>) {
+        let millis = threshold.map(|v| v.as_millis().max(1) as u64).unwrap_or(0);
+        self.slow_threshold.store(millis, Ordering::SeqCst);
+    }
+
+    pub fn get_slow_threshold(&self) -> Option<Duration> {
+        match self.slow_threshold.load(Ordering::Relaxed) {
+            0 => None,
+            v => Some(Duration::from_millis(v)),
+        }
+    }
+
+    /// mask sensitive args and rows, see [`Redactor`]
+    pub fn set_redactor(&self, redactor: Redactor) {
+        *self.redactor.write().unwrap() = redactor;
+    }
+
+    pub fn get_redactor(&self) -> Redactor {
+        self.redactor.read().unwrap().clone()
+    }
+
+    fn redact_args(&self, sql: &str, args: &[Value]) -> Vec<Value> {
+        self.redactor.read().unwrap().redact_args(sql, args)
+    }
+
+    fn redact_error(&self, sql: &str, args: &[Value], e: &Error) -> String {
+        self.redactor
+            .read()
+            .unwrap()
+            .redact_error(sql, args, &e.to_string())
+    }
+
+    fn replace_holder(&self, sql: &str, args: &[Value]) -> String {
+        let mut sql = sql.to_string();
+        if self.get_replace_holder() {
+            for x in args {
+                sql = sql.replacen('?', &x.as_sql(), 1);
+            }
+        }
+        sql
+    }
 }
 
 /// from  rb
@@ -161,20 +222,14 @@ impl Intercept for LogInterceptor {
         let level = self.to_level().unwrap();
         //send sql/args
         let op;
-        if sql.trim_start().starts_with("select") {
+        if operation(sql) == "SELECT" {
             op = "query";
         } else {
             op = "exec ";
         }
-        if self.replace_holder.load(Ordering::Relaxed) {
-            let mut sql = sql.to_string();
-            for x in &*args {
-                sql = sql.replacen('?', &x.as_sql(), 1);
-            }
-            log!(level, "[rbatis] [{}] {} => `{}` {}",task_id,op,&sql,RbsValueDisplay::new(args));
-        } else {
-            log!(level, "[rbatis] [{}] {} => `{}` {}",task_id,op,&sql,RbsValueDisplay::new(args));
-        }
+        let args = self.redact_args(sql, args);
+        let sql = self.replace_holder(sql, &args);
+        log!(level, "[rbatis] [{}] {} => `{}` {}",task_id,op,&sql,RbsValueDisplay::new(&args));
         return Ok(true);
     }
 
@@ -182,54 +237,87 @@ impl Intercept for LogInterceptor {
         &self,
         task_id: i64,
         _rb: &dyn Executor,
-        _sql: &mut String,
-        _args: &mut Vec<Value>,
+        sql: &mut String,
+        args: &mut Vec<Value>,
         result: ResultType<&mut Result<ExecResult, Error>, &mut Result<Vec<Value>, Error>>,
     ) -> Result<bool, Error> {
+        let elapsed = context().map(|v| v.elapsed()).unwrap_or_default();
+        let type_name = result.type_name();
+        if let Some(threshold) = self.get_slow_threshold() {
+            if elapsed >= threshold {
+                let args = self.redact_args(sql, args);
+                log!(
+                    Level::Warn,
+                    "[rbatis] [{}] slow {:5} elapsed={:?} => `{}` {}",
+                    task_id,
+                    type_name,
+                    elapsed,
+                    self.replace_holder(sql, &args),
+                    RbsValueDisplay::new(&args)
+                );
+            }
+        }
         if self.get_level_filter() == LevelFilter::Off {
             return Ok(true);
         }
         let level = self.to_level().unwrap();
-        let type_name = result.type_name();
         //ResultType
         match result {
             ResultType::Exec(result) => match result {
                 Ok(result) => {
                     log!(
                         level,
-                        "[rbatis] [{}] {:5} <= rows_affected={}",
+                        "[rbatis] [{}] {:5} <= rows_affected={} elapsed={:?}",
                         task_id,
                         type_name,
-                        result
+                        result,
+                        elapsed
                     );
                 }
                 Err(e) => {
-                    log!(level, "[rbatis] [{}] {:5} <= {}", task_id, type_name, e);
+                    log!(
+                        level,
+                        "[rbatis] [{}] {:5} <= {} elapsed={:?}",
+                        task_id,
+                        type_name,
+                        self.redact_error(sql, args, e),
+                        elapsed
+                    );
                 }
             },
             ResultType::Query(result) => match result {
                 Ok(result) => {
                     if is_debug_mode() {
+                        let rows = self.redactor.read().unwrap().redact_rows(result);
                         log!(
                             level,
-                            "[rbatis] [{}] {:5} <= len={},rows={}",
+                            "[rbatis] [{}] {:5} <= len={},rows={} elapsed={:?}",
                             task_id,
                             type_name,
                             result.len(),
-                            RbsValueDisplay { inner: result }
+                            RbsValueDisplay::new(&rows),
+                            elapsed
                         );
                     } else {
                         log!(
                             level,
-                            "[rbatis] [{}] {:5} <= len={}",
+                            "[rbatis] [{}] {:5} <= len={} elapsed={:?}",
                             task_id,
                             type_name,
-                            result.len()
+                            result.len(),
+                            elapsed
                         );
                     }
                 }
                 Err(e) => {
-                    log!(level, "[rbatis] [{}] {:5} <= {}", task_id, type_name, e);
+                    log!(
+                        level,
+                        "[rbatis] [{}] {:5} <= {} elapsed={:?}",
+                        task_id,
+                        type_name,
+                        self.redact_error(sql, args, e),
+                        elapsed
+                    );
                 }
             },
         }
@@ -244,7 +332,10 @@ mod test {
     use log::LevelFilter;
     use crate::intercept::Intercept;
     use crate::intercept_log::LogInterceptor;
-    use crate::RBatis;
+    use crate::redact::Redactor;
+    use crate::{Error, RBatis};
+    use rbs::Value;
+    use std::time::Duration;
 
     #[test]
     fn test_get() {
@@ -254,4 +345,53 @@ mod test {
         assert_eq!(intercept.is_some(), true);
         println!("{}", intercept.unwrap().name());
     }
+
+    #[test]
+    fn test_slow_threshold() {
+        let intercept = LogInterceptor::new(LevelFilter::Off);
+        assert_eq!(intercept.get_slow_threshold(), None);
+        intercept.set_slow_threshold(Some(Duration::from_millis(200)));
+        assert_eq!(
+            intercept.clone().get_slow_threshold(),
+            Some(Duration::from_millis(200))
+        );
+        intercept.set_slow_threshold(None);
+        assert_eq!(intercept.get_slow_threshold(), None);
+    }
+
+    #[test]
+    fn test_clone() {
+        let intercept = LogInterceptor::new(LevelFilter::Debug);
+        intercept.set_replace_holder(true);
+        intercept.set_slow_threshold(Some(Duration::from_millis(200)));
+        intercept.set_redactor(Redactor::new().column("password"));
+        let cloned = intercept.clone();
+        assert_eq!(cloned.get_level_filter(), LevelFilter::Debug);
+        assert!(cloned.get_replace_holder());
+        assert_eq!(
+            cloned.get_slow_threshold(),
+            Some(Duration::from_millis(200))
+        );
+        assert!(cloned.get_redactor().columns.contains("password"));
+    }
+
+    #[test]
+    fn test_redactor() {
+        let intercept = LogInterceptor::new(LevelFilter::Off);
+        intercept.set_redactor(Redactor::new().column("password"));
+        let intercept = intercept.clone();
+        assert!(intercept.get_redactor().columns.contains("password"));
+        intercept.set_replace_holder(true);
+        let sql = "update user set password = ? where id = ?";
+        let args = intercept.redact_args(sql, &[Value::from("123456"), Value::from(1)]);
+        assert_eq!(
+            intercept.replace_holder(sql, &args),
+            "update user set password = *** where id = 1"
+        );
+        let e = Error::from("invalid input syntax for type json: \"123456\"");
+        assert_eq!(
+            intercept.redact_error(sql, &[Value::from("123456"), Value::from(1)], &e),
+            "invalid input syntax for type json: \"***\""
+        );
+    }
 }
\ No newline at end of file
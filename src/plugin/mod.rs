@@ -1,5 +1,8 @@
 pub mod intercept;
 pub mod intercept_log;
 pub mod object_id;
+pub mod redact;
+pub mod retry;
 pub mod snowflake;
 pub mod table_sync;
+pub mod trace;
@@ -0,0 +1,465 @@
+use rbs::value::map::ValueMap;
+use rbs::Value;
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// decides whether a value must be masked, whatever its column
+pub type RedactMatcher = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+
+/// redaction of sensitive args and rows before they are logged,
+/// used by [`LogInterceptor`](crate::intercept_log::LogInterceptor) and usable by any `Intercept`.
+///
+/// a value is masked when its column (matched from the sql, or the key of a returned row)
+/// is one of `columns`, or when a matcher accepts it.
+/// the column of an arg is guessed from the sql and may not be found,
+/// with `fail_closed` such an arg is masked too.
+/// strings and binary longer than `max_length` are shortened.
+/// ```rust
+/// use rbatis::redact::Redactor;
+/// use rbs::Value;
+/// let redactor = Redactor::new()
+///     .column("password")
+///     .matcher(|v| v.as_str().unwrap_or_default().starts_with("Bearer "))
+///     .max_length(64);
+/// let args = redactor.redact_args(
+///     "update user set password = ? where id = ?",
+///     &[Value::from("123456"), Value::from(1)],
+/// );
+/// assert_eq!(args, vec![Value::from("***"), Value::from(1)]);
+/// ```
+#[derive(Clone)]
+pub struct Redactor {
+    /// column or crud field names in lower case
+    pub columns: HashSet<String>,
+    pub matchers: Vec<RedactMatcher>,
+    /// max chars of a string, max bytes of a binary. None=unlimited
+    pub max_length: Option<usize>,
+    /// the replacement of a masked value, default `***`
+    pub mask: String,
+    /// mask the args whose column is not found in the sql when `columns` is set, default false
+    pub fail_closed: bool,
+}
+
+impl Debug for Redactor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Redactor")
+            .field("columns", &self.columns)
+            .field("matchers", &self.matchers.len())
+            .field("max_length", &self.max_length)
+            .field("mask", &self.mask)
+            .field("fail_closed", &self.fail_closed)
+            .finish()
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self {
+            columns: HashSet::new(),
+            matchers: vec![],
+            max_length: None,
+            mask: "***".to_string(),
+            fail_closed: false,
+        }
+    }
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// mask the values of column (or crud field) `name`, case insensitive
+    pub fn column(mut self, name: &str) -> Self {
+        self.columns.insert(name.to_ascii_lowercase());
+        self
+    }
+
+    pub fn columns<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        for name in names {
+            self = self.column(name);
+        }
+        self
+    }
+
+    /// mask every string/number value accepted by `f`
+    pub fn matcher<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Value) -> bool + Send + Sync + 'static,
+    {
+        self.matchers.push(Arc::new(f));
+        self
+    }
+
+    pub fn max_length(mut self, arg: usize) -> Self {
+        self.max_length = Some(arg);
+        self
+    }
+
+    pub fn mask(mut self, arg: &str) -> Self {
+        self.mask = arg.to_string();
+        self
+    }
+
+    pub fn fail_closed(mut self, arg: bool) -> Self {
+        self.fail_closed = arg;
+        self
+    }
+
+    /// nothing to redact
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty() && self.matchers.is_empty() && self.max_length.is_none()
+    }
+
+    /// redact the args of `sql`, every `?` is matched to its column
+    pub fn redact_args(&self, sql: &str, args: &[Value]) -> Vec<Value> {
+        if self.is_empty() {
+            return args.to_vec();
+        }
+        let columns = arg_columns(sql);
+        let fail_closed = self.fail_closed && !self.columns.is_empty();
+        args.iter()
+            .enumerate()
+            .map(|(i, v)| match columns.get(i).and_then(|v| v.as_deref()) {
+                None if fail_closed => Value::String(self.mask.clone()),
+                column => self.redact_value(column, v),
+            })
+            .collect()
+    }
+
+    /// mask the masked args of `sql` where the error message `msg` echoes them,
+    /// e.g. pg `Key (email)=(a@b.c) already exists`
+    pub fn redact_error(&self, sql: &str, args: &[Value], msg: &str) -> String {
+        if self.is_empty() {
+            return msg.to_string();
+        }
+        let mut msg = msg.to_string();
+        for (v, redacted) in args.iter().zip(self.redact_args(sql, args)) {
+            if redacted.as_str() != Some(self.mask.as_str()) || *v == redacted {
+                continue;
+            }
+            let text = match v {
+                Value::String(s) => s.clone(),
+                Value::Null | Value::Array(_) | Value::Map(_) | Value::Binary(_) => continue,
+                v => v.to_string(),
+            };
+            if !text.is_empty() {
+                msg = msg.replace(&text, &self.mask);
+            }
+        }
+        msg
+    }
+
+    /// redact returned rows, the keys of a row map are its columns
+    pub fn redact_rows(&self, rows: &[Value]) -> Vec<Value> {
+        if self.is_empty() {
+            return rows.to_vec();
+        }
+        rows.iter().map(|v| self.redact_value(None, v)).collect()
+    }
+
+    /// redact `v`, the value of `column`
+    pub fn redact_value(&self, column: Option<&str>, v: &Value) -> Value {
+        if let Some(column) = column {
+            if self.columns.contains(&column.to_ascii_lowercase()) {
+                return Value::String(self.mask.clone());
+            }
+        }
+        match v {
+            Value::Array(arr) => {
+                Value::Array(arr.iter().map(|v| self.redact_value(column, v)).collect())
+            }
+            Value::Map(m) => {
+                let mut map = ValueMap::with_capacity(m.len());
+                for (k, v) in m {
+                    map.insert(k.clone(), self.redact_value(k.as_str(), v));
+                }
+                Value::Map(map)
+            }
+            Value::Ext(name, v) => Value::Ext(name, Box::new(self.redact_value(column, v))),
+            v if self.matchers.iter().any(|f| f(v)) => Value::String(self.mask.clone()),
+            Value::String(s) => {
+                let len = s.chars().count();
+                match self.max_length {
+                    Some(max) if len > max => Value::String(format!(
+                        "{}...(len={})",
+                        s.chars().take(max).collect::<String>(),
+                        len
+                    )),
+                    _ => v.clone(),
+                }
+            }
+            Value::Binary(b) => match self.max_length {
+                Some(max) if b.len() > max => Value::String(format!("<binary len={}>", b.len())),
+                _ => v.clone(),
+            },
+            _ => v.clone(),
+        }
+    }
+}
+
+/// the column of every `?` in `sql`, None when it can not be matched.
+/// matches `column = ?` (any operator, `in`, `like`, `between`) and `insert into t (columns) values (?...)`
+pub fn arg_columns(sql: &str) -> Vec<Option<String>> {
+    const RESET: [&str; 22] = [
+        "select",
+        "from",
+        "where",
+        "set",
+        "values",
+        "limit",
+        "offset",
+        "group",
+        "order",
+        "by",
+        "having",
+        "on",
+        "join",
+        "returning",
+        "case",
+        "when",
+        "then",
+        "else",
+        "end",
+        "as",
+        "union",
+        "into",
+    ];
+    // operators between a column and its `?`
+    const KEEP: [&str; 9] = [
+        "and", "or", "not", "like", "ilike", "in", "between", "is", "escape",
+    ];
+    let tokens = tokenize(sql);
+    let mut columns = vec![];
+    let mut column: Option<String> = None;
+    let mut insert_columns: Vec<String> = vec![];
+    let mut depth = 0;
+    // depth of the tuples after `values`, and the index of the next value in the tuple
+    let mut values: Option<usize> = None;
+    let mut index = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match *token {
+            "(" => {
+                depth += 1;
+                if values == Some(depth) {
+                    index = 0;
+                }
+                // `into t (a, b)`
+                if i >= 2 && tokens[i - 2].eq_ignore_ascii_case("into") {
+                    insert_columns = tokens[i + 1..]
+                        .iter()
+                        .take_while(|v| **v != ")")
+                        .filter(|v| **v != ",")
+                        .map(|v| column_name(v))
+                        .collect();
+                }
+            }
+            ")" => depth = depth.saturating_sub(1),
+            "," if values == Some(depth) => index += 1,
+            "?" => {
+                if values == Some(depth) {
+                    columns.push(insert_columns.get(index).cloned());
+                } else {
+                    columns.push(column.clone());
+                }
+            }
+            v if v.eq_ignore_ascii_case("values") => {
+                values = Some(depth + 1);
+                column = None;
+            }
+            v if RESET.iter().any(|k| v.eq_ignore_ascii_case(k)) => {
+                if v.eq_ignore_ascii_case("select") {
+                    values = None;
+                }
+                column = None;
+            }
+            v if KEEP.iter().any(|k| v.eq_ignore_ascii_case(k)) => {}
+            v if is_identifier(v) && tokens.get(i + 1) != Some(&"(") => {
+                column = Some(column_name(v));
+            }
+            _ => {}
+        }
+    }
+    columns
+}
+
+/// split sql into words, quoted names, `?` and punctuation. string literals are dropped
+fn tokenize(sql: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let bytes = sql.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        match c {
+            b'\'' => {
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == b'\'' {
+                        if bytes.get(i + 1) == Some(&b'\'') {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            b'`' | b'"' | b'[' => {
+                let end = if c == b'[' { b']' } else { c };
+                i += 1;
+                while i < bytes.len() && bytes[i] != end {
+                    i += 1;
+                }
+                i += 1;
+            }
+            c if c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80 => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric()
+                        || bytes[i] == b'_'
+                        || bytes[i] == b'.'
+                        || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+            }
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            _ => i += 1,
+        }
+        tokens.push(&sql[start..i.min(sql.len())]);
+    }
+    tokens
+}
+
+fn is_identifier(token: &str) -> bool {
+    match token.as_bytes().first() {
+        Some(c) => {
+            c.is_ascii_alphabetic()
+                || *c == b'_'
+                || *c == b'`'
+                || *c == b'"'
+                || *c == b'['
+                || *c >= 0x80
+        }
+        None => false,
+    }
+}
+
+/// `t.user_password` => `user_password`
+fn column_name(token: &str) -> String {
+    let name = token.rsplit('.').next().unwrap_or(token);
+    name.trim_matches(|c| c == '`' || c == '"' || c == '[' || c == ']')
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::redact::{arg_columns, Redactor};
+    use rbs::Value;
+
+    #[test]
+    fn test_arg_columns() {
+        let columns = |sql: &str| -> Vec<Option<String>> { arg_columns(sql) };
+        assert_eq!(
+            columns("select * from user where name = ? and t.`password` <> ? limit ?"),
+            vec![Some("name".into()), Some("password".into()), None]
+        );
+        assert_eq!(
+            columns("select * from user where name like '?%' and token = ?"),
+            vec![Some("token".into())]
+        );
+        assert_eq!(
+            columns("insert into user (id,name,password) values (?,?,?),(?,?,?)"),
+            vec![
+                Some("id".into()),
+                Some("name".into()),
+                Some("password".into()),
+                Some("id".into()),
+                Some("name".into()),
+                Some("password".into())
+            ]
+        );
+        assert_eq!(
+            columns("update user set token = lower(?) where id in (?,?) and age between ? and ?"),
+            vec![
+                Some("token".into()),
+                Some("id".into()),
+                Some("id".into()),
+                Some("age".into()),
+                Some("age".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redactor() {
+        let redactor = Redactor::new()
+            .columns(["password", "Token"])
+            .matcher(|v| v.as_str().unwrap_or_default().contains('@'))
+            .max_length(4);
+        assert_eq!(
+            redactor.redact_args(
+                "insert into user (id,name,email,password) values (?,?,?,?)",
+                &[
+                    Value::from(1),
+                    Value::from("abcdef"),
+                    Value::from("a@b.c"),
+                    Value::from("123"),
+                ]
+            ),
+            vec![
+                Value::from(1),
+                Value::from("abcd...(len=6)"),
+                Value::from("***"),
+                Value::from("***"),
+            ]
+        );
+        let mut row = rbs::value::map::ValueMap::new();
+        row.insert("token".into(), Value::from("t"));
+        row.insert("data".into(), Value::Binary(vec![0; 8]));
+        let mut redacted = rbs::value::map::ValueMap::new();
+        redacted.insert("token".into(), Value::from("***"));
+        redacted.insert("data".into(), Value::from("<binary len=8>"));
+        assert_eq!(
+            redactor.redact_rows(&[Value::Map(row)]),
+            vec![Value::Map(redacted)]
+        );
+    }
+
+    #[test]
+    fn test_redactor_fail_closed() {
+        let sql = "insert into user (id,password) select ?, ? from dual";
+        let args = [Value::from(1), Value::from("123456")];
+        let redactor = Redactor::new().column("password");
+        assert_eq!(redactor.redact_args(sql, &args), args.to_vec());
+        let redactor = redactor.fail_closed(true);
+        assert_eq!(
+            redactor.redact_args(sql, &args),
+            vec![Value::from("***"), Value::from("***")]
+        );
+        assert_eq!(
+            redactor.redact_args("select * from user where id = ?", &[Value::from(1)]),
+            vec![Value::from(1)]
+        );
+    }
+
+    #[test]
+    fn test_redact_error() {
+        let redactor = Redactor::new().column("email");
+        let msg = redactor.redact_error(
+            "insert into user (id,email) values (?,?)",
+            &[Value::from(7), Value::from("a@b.c")],
+            "duplicate key value violates unique constraint, Key (email)=(a@b.c) already exists.",
+        );
+        assert_eq!(
+            msg,
+            "duplicate key value violates unique constraint, Key (email)=(***) already exists."
+        );
+    }
+}
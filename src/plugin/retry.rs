@@ -0,0 +1,225 @@
+use crate::Error;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// decides whether a failed statement may be retried on a fresh connection
+pub type RetryClassifier = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// decides whether a sql only reads and is retried without [`idempotent`]
+pub type ReadClassifier = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+rbdc::rt::tokio::task_local! {
+    static IDEMPOTENT: bool;
+}
+
+/// retry policy of [`RBatis`](crate::RBatis), set it with `RBatis::set_retry_policy`.
+///
+/// reads outside a transaction are retried on a fresh connection from the pool
+/// when the classifier accepts the error. writes are only retried inside [`idempotent`].
+///
+/// what is a read is guessed from the sql by [`is_read`], a `select` calling a function
+/// that writes is retried too. set [`RetryPolicy::read`] to decide yourself,
+/// `.read(|_| false)` retries only the statements inside [`idempotent`].
+/// ```rust
+/// use std::time::Duration;
+/// use rbatis::RBatis;
+/// use rbatis::retry::RetryPolicy;
+/// let rb = RBatis::new();
+/// rb.set_retry_policy(
+///     RetryPolicy::new()
+///         .max_attempts(3)
+///         .backoff(Duration::from_millis(100)),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// attempts including the first one, default 3
+    pub max_attempts: usize,
+    /// delay before the first retry, doubled for every next one, default 100ms
+    pub backoff: Duration,
+    /// max delay between two attempts, default 2s
+    pub max_backoff: Duration,
+    /// default [`is_connection_lost`]
+    pub classifier: RetryClassifier,
+    /// default [`is_read`]
+    pub read: ReadClassifier,
+}
+
+impl Debug for RetryPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("max_backoff", &self.max_backoff)
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            classifier: Arc::new(is_connection_lost),
+            read: Arc::new(is_read),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_attempts(mut self, arg: usize) -> Self {
+        self.max_attempts = arg;
+        self
+    }
+
+    pub fn backoff(mut self, arg: Duration) -> Self {
+        self.backoff = arg;
+        self
+    }
+
+    pub fn max_backoff(mut self, arg: Duration) -> Self {
+        self.max_backoff = arg;
+        self
+    }
+
+    pub fn classifier<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.classifier = Arc::new(f);
+        self
+    }
+
+    pub fn read<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.read = Arc::new(f);
+        self
+    }
+
+    pub fn is_retryable(&self, e: &Error) -> bool {
+        (self.classifier)(e)
+    }
+
+    /// whether `sql` may be retried outside [`idempotent`]
+    pub fn is_read(&self, sql: &str) -> bool {
+        (self.read)(sql)
+    }
+
+    /// the delay before retry number `retry` (starts at 1)
+    pub fn delay(&self, retry: usize) -> Duration {
+        let factor = 1u32 << (retry.saturating_sub(1)).min(16);
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// the default classifier, matches the io errors of a dropped or restarted server
+pub fn is_connection_lost(e: &Error) -> bool {
+    let msg = e.to_string().to_ascii_lowercase();
+    [
+        "connection reset",
+        "connection refused",
+        "connection aborted",
+        "connection closed",
+        "broken pipe",
+        "unexpected eof",
+        "early eof",
+        "not connected",
+        "server closed the connection",
+        "server has gone away",
+        "lost connection",
+        "connection is ping fail",
+    ]
+    .iter()
+    .any(|v| msg.contains(v))
+}
+
+/// sql that only reads and may be retried.
+/// `with` is not a read, a postgres CTE may contain `insert`/`update`/`delete`.
+/// neither are the locking reads, `select ... into` and the selects calling
+/// the sequence and lock functions of pg and mysql.
+/// a `select` calling a user function that writes can't be told apart
+pub fn is_read(sql: &str) -> bool {
+    let sql = sql.trim_start().to_ascii_lowercase();
+    let read = [
+        "select", "show", "explain", "describe", "desc ", "pragma", "values",
+    ]
+    .iter()
+    .any(|v| sql.starts_with(v));
+    read && ![
+        "nextval",
+        "setval",
+        "_advisory_",
+        "get_lock",
+        "release_lock",
+        "for update",
+        "for share",
+        "for no key update",
+        "for key share",
+        "lock in share mode",
+        " into ",
+    ]
+    .iter()
+    .any(|v| sql.contains(v))
+}
+
+/// run `f` with its writes marked idempotent, so the [`RetryPolicy`] may retry them
+/// ```rust
+/// use rbatis::retry::idempotent;
+/// # async fn f() {
+/// idempotent(async {
+///     //rb.exec("update user set name = 'a' where id = 1", vec![]).await
+/// })
+/// .await;
+/// # }
+/// ```
+pub async fn idempotent<F: Future>(f: F) -> F::Output {
+    IDEMPOTENT.scope(true, f).await
+}
+
+/// whether the current task runs inside [`idempotent`]
+pub fn is_idempotent() -> bool {
+    IDEMPOTENT.try_with(|v| *v).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::retry::{is_connection_lost, is_read, RetryPolicy};
+    use crate::Error;
+    use std::time::Duration;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new();
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(10), Duration::from_secs(2));
+        assert!(is_connection_lost(&Error::from(
+            "Connection reset by peer (os error 104)"
+        )));
+        assert!(!is_connection_lost(&Error::from(
+            "syntax error at or near \"selec\""
+        )));
+        assert!(is_read(" SELECT 1"));
+        assert!(!is_read(
+            "with t as (delete from a returning *) select * from t"
+        ));
+        assert!(!is_read("update t set a = 1"));
+        assert!(!is_read("select nextval('user_id_seq')"));
+        assert!(!is_read("SELECT pg_try_advisory_lock(1)"));
+        assert!(!is_read("select get_lock('a', 10)"));
+        assert!(!is_read("select * from t where id = 1 for update"));
+        assert!(!is_read("select * into t2 from t"));
+        let policy = RetryPolicy::new().read(|_| false);
+        assert!(!policy.is_read("select 1"));
+        assert!(RetryPolicy::new().is_read("select 1"));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{Error, RBatis};
+use rbdc::db::ExecResult;
+use rbs::Value;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// the operation name of `sql`, the first keyword in upper case. for example `SELECT`.
+/// the operation of `WITH ... SELECT` is the statement after the common table expressions
+pub fn operation(sql: &str) -> String {
+    let mut words = sql.split_whitespace().map(|v| {
+        let keyword = v
+            .trim_matches(|c: char| !c.is_ascii_alphabetic())
+            .to_ascii_uppercase();
+        (v, keyword)
+    });
+    let first = match words.next() {
+        Some((_, keyword)) => keyword,
+        None => return String::new(),
+    };
+    if first != "WITH" {
+        return first;
+    }
+    let mut depth = 0;
+    for (word, keyword) in words {
+        let opened = word.chars().take_while(|c| *c == '(').count();
+        let statement = ["SELECT", "INSERT", "UPDATE", "DELETE", "MERGE"];
+        if depth + opened == 0 && statement.contains(&keyword.as_str()) {
+            return keyword;
+        }
+        depth += word.matches('(').count();
+        depth = depth.saturating_sub(word.matches(')').count());
+    }
+    first
+}
+
+/// the first table of `sql`, the name after `from`/`into`/`update`/`table`/`join`
+/// with quotes removed. None when the sql names no table (`select 1`)
+pub fn table(sql: &str) -> Option<String> {
+    let mut words = sql.split_whitespace();
+    while let Some(word) = words.next() {
+        let keyword = ["from", "into", "update", "table", "join"]
+            .iter()
+            .any(|v| word.eq_ignore_ascii_case(v));
+        if !keyword {
+            continue;
+        }
+        let name = words.next()?;
+        if name.starts_with('(') {
+            continue;
+        }
+        let name = name
+            .split(['(', ',', ';'])
+            .next()
+            .unwrap_or_default()
+            .replace(['`', '"', '[', ']'], "");
+        if !name.is_empty() {
+            return Some(name);
+        }
+    }
+    None
+}
+
+/// the OpenTelemetry `db.system` of a driver name
+pub fn db_system(driver_type: &str) -> &str {
+    match driver_type {
+        "postgres" => "postgresql",
+        v => v,
+    }
+}
+
+/// the `rbatis.statement` span of one statement, a no-op without the `tracing` feature.
+///
+/// fields follow the OpenTelemetry database conventions: `db.system`, `db.statement`,
+/// `db.operation`, `db.sql.table`, `db.rows_affected`/`db.rows_returned`, plus
+/// `rbatis.task_id`, `rbatis.tx_id`, `elapsed_ms` and `error`.
+/// `elapsed_ms` is the time of the statement attempts only,
+/// waiting for the connection and the retry backoff are left out
+pub(crate) struct StatementSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    elapsed: Duration,
+}
+
+impl StatementSpan {
+    #[cfg(feature = "tracing")]
+    pub fn new(rb: &RBatis, task_id: i64, tx_id: Option<i64>, operation: &str, sql: &str) -> Self {
+        let system = db_system(rb.driver_type().unwrap_or_default());
+        let table = table(sql);
+        let span = tracing::info_span!(
+            "rbatis.statement",
+            otel.name = %format!("{} {}", operation, table.as_deref().unwrap_or(system)),
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            db.system = system,
+            db.statement = sql,
+            db.operation = operation,
+            db.sql.table = table.as_deref(),
+            db.rows_affected = tracing::field::Empty,
+            db.rows_returned = tracing::field::Empty,
+            rbatis.task_id = task_id,
+            rbatis.tx_id = tx_id,
+            elapsed_ms = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        Self {
+            span,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub fn new(
+        _rb: &RBatis,
+        _task_id: i64,
+        _tx_id: Option<i64>,
+        _operation: &str,
+        _sql: &str,
+    ) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// run one attempt `f` of the statement inside the span, adding its time to [`Self::elapsed`]
+    pub async fn instrument<F: Future>(&mut self, f: F) -> F::Output {
+        let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let v = tracing::Instrument::instrument(f, self.span.clone()).await;
+        #[cfg(not(feature = "tracing"))]
+        let v = f.await;
+        self.elapsed += start.elapsed();
+        v
+    }
+
+    /// the time of the attempts run by [`Self::instrument`]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn record_exec(&self, result: &Result<ExecResult, Error>) {
+        self.record(
+            result
+                .as_ref()
+                .map(|v| ("db.rows_affected", v.rows_affected)),
+        );
+    }
+
+    pub fn record_batch(&self, result: &Result<Vec<Result<ExecResult, Error>>, Error>) {
+        let rows_affected = result.as_ref().and_then(|v| {
+            v.iter()
+                .filter(|v| !matches!(v, Err(e) if e.is_batch_aborted()))
+                .try_fold(0, |rows, v| v.as_ref().map(|v| rows + v.rows_affected))
+        });
+        self.record(rows_affected.map(|rows| ("db.rows_affected", rows)));
+    }
+
+    pub fn record_query(&self, result: &Result<Vec<Value>, Error>) {
+        self.record(
+            result
+                .as_ref()
+                .map(|v| ("db.rows_returned", v.len() as u64)),
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record(&self, rows: Result<(&str, u64), &Error>) {
+        self.span
+            .record("elapsed_ms", self.elapsed.as_millis() as u64);
+        match rows {
+            Ok((field, rows)) => {
+                self.span.record(field, rows);
+            }
+            Err(e) => {
+                self.span.record("otel.status_code", "ERROR");
+                self.span.record("error", tracing::field::display(e));
+            }
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn record(&self, _rows: Result<(&str, u64), &Error>) {}
+}
+
+#[cfg(test)]
+mod test {
+    use crate::trace::{db_system, operation, table};
+
+    #[test]
+    fn test_operation() {
+        assert_eq!(operation("  select * from activity"), "SELECT");
+        assert_eq!(operation("(select 1)"), "SELECT");
+        assert_eq!(operation("SELECT 1"), "SELECT");
+        assert_eq!(
+            operation("with t as (select id from a), u as ( select 1 ) select * from t"),
+            "SELECT"
+        );
+        assert_eq!(
+            operation("WITH t(id) AS (SELECT 1) DELETE FROM a WHERE id IN (SELECT id FROM t)"),
+            "DELETE"
+        );
+        assert_eq!(operation(""), "");
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            table("select * from `activity` where id = ?").as_deref(),
+            Some("activity")
+        );
+        assert_eq!(
+            table("insert into \"user\"(id,name) values (?,?)").as_deref(),
+            Some("user")
+        );
+        assert_eq!(
+            table("UPDATE activity SET name = ?").as_deref(),
+            Some("activity")
+        );
+        assert_eq!(
+            table("select * from (select 1) a join b on a.id = b.id").as_deref(),
+            Some("b")
+        );
+        assert_eq!(table("select 1"), None);
+        assert_eq!(db_system("postgres"), "postgresql");
+        assert_eq!(db_system("mysql"), "mysql");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_statement_span() {
+        use crate::trace::StatementSpan;
+        use crate::RBatis;
+        use rbdc::db::ExecResult;
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        #[derive(Default, Clone)]
+        struct Capture(Arc<Mutex<Vec<String>>>);
+        impl Visit for Capture {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}={:?}", field.name(), value));
+            }
+        }
+        impl tracing::Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                span.record(&mut self.clone());
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, values: &Record<'_>) {
+                values.record(&mut self.clone());
+            }
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let rb = RBatis::new();
+            let sql = "update activity set name = ?";
+            let span = StatementSpan::new(&rb, 1, Some(2), &operation(sql), sql);
+            span.record_exec(&Ok(ExecResult {
+                rows_affected: 3,
+                last_insert_id: Default::default(),
+            }));
+        });
+        let fields = capture.0.lock().unwrap().clone();
+        assert!(fields.contains(&"db.operation=\"UPDATE\"".to_string()));
+        assert!(fields.contains(&"db.sql.table=\"activity\"".to_string()));
+        assert!(fields.contains(&"rbatis.task_id=1".to_string()));
+        assert!(fields.contains(&"rbatis.tx_id=2".to_string()));
+        assert!(fields.contains(&"db.rows_affected=3".to_string()));
+    }
+}
@@ -1,15 +1,16 @@
 use crate::executor::{RBatisConnExecutor, RBatisTxExecutor};
 use crate::intercept_log::LogInterceptor;
 use crate::plugin::intercept::Intercept;
+use crate::retry::RetryPolicy;
 use crate::snowflake::new_snowflake_id;
 use crate::Error;
 use dark_std::sync::SyncVec;
 use rbdc::rt::tokio::sync::Mutex;
 use log::LevelFilter;
-use rbdc::pool::{ManagerPorxy, Pool};
+use rbdc::db::Connection;
+use rbdc::pool::{ManagerPorxy, Pool, PoolOptions};
 use std::fmt::Debug;
-use std::sync::{Arc, OnceLock};
-use std::time::Duration;
+use std::sync::{Arc, OnceLock, RwLock};
 
 /// RBatis engine
 #[derive(Clone, Debug)]
@@ -18,6 +19,8 @@ pub struct RBatis {
     pub pool: Arc<OnceLock<Pool>>,
     // intercept vec(default the intercepts[0] is a log interceptor)
     pub intercepts: Arc<SyncVec<Arc<dyn Intercept>>>,
+    // retry reads after a lost connection, default None
+    pub retry_policy: Arc<RwLock<Option<RetryPolicy>>>,
 }
 impl Default for RBatis {
     fn default() -> RBatis {
@@ -61,6 +64,7 @@ impl RBatis {
                 }
                 result
             }),
+            retry_policy: Arc::new(RwLock::new(None)),
         };
     }
 
@@ -80,13 +84,35 @@ impl RBatis {
         &self,
         driver: Driver,
         url: &str,
+    ) -> Result<(), Error> {
+        self.init_pool(driver, url, PoolOptions::default())
+    }
+
+    /// init pool with connection lifecycle policies.
+    /// for example:
+    /// ```rust
+    /// use std::time::Duration;
+    /// use rbatis::RBatis;
+    /// use rbdc::pool::PoolOptions;
+    /// let rb = RBatis::new();
+    /// let opts = PoolOptions::new()
+    ///     .max_lifetime(Some(Duration::from_secs(25 * 60)))
+    ///     .idle_timeout(Some(Duration::from_secs(10 * 60)))
+    ///     .min_idle(2);
+    /// //rb.init_pool(rbdc_sqlite::driver::SqliteDriver {}, "sqlite://target/sqlite.db", opts).unwrap();
+    /// ```
+    pub fn init_pool<Driver: rbdc::db::Driver + 'static>(
+        &self,
+        driver: Driver,
+        url: &str,
+        options: PoolOptions,
     ) -> Result<(), Error> {
         if url.is_empty() {
             return Err(Error::from("[rbatis] link url is empty!"));
         }
         let mut option = driver.default_option();
         option.set_uri(url)?;
-        let pool = Pool::new_box(Box::new(driver), option)?;
+        let pool = Pool::new_options(Box::new(driver), option, options)?;
         self.pool
             .set(pool)
             .map_err(|_e| Error::from("pool set fail!"))?;
@@ -138,6 +164,17 @@ impl RBatis {
         self.intercepts = Arc::new(SyncVec::from(arg));
     }
 
+    /// set the policy retrying reads on a fresh connection after the connection was lost,
+    /// shared with the clones of this RBatis
+    pub fn set_retry_policy(&self, arg: RetryPolicy) {
+        *self.retry_policy.write().unwrap() = Some(arg);
+    }
+
+    /// get the retry policy
+    pub fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry_policy.read().unwrap().clone()
+    }
+
     /// get conn pool
     ///
     /// can set option for example:
@@ -161,10 +198,17 @@ impl RBatis {
         Ok(pool.driver_type())
     }
 
+    /// get the max number of bind parameters of the driver,
+    /// pass it to `insert_batch_limit` and the other `*_limit` crud methods
+    pub fn max_params(&self) -> Result<usize, Error> {
+        let pool = self.get_pool()?;
+        Ok(pool.max_params())
+    }
+
     /// get an DataBase Connection used for the next step
     pub async fn acquire(&self) -> Result<RBatisConnExecutor, Error> {
         let pool = self.get_pool()?;
-        let conn = pool.get().await?;
+        let conn = pool.acquire().await?;
         return Ok(RBatisConnExecutor {
             id: new_snowflake_id(),
             conn: Mutex::new(Box::new(conn)),
@@ -175,9 +219,7 @@ impl RBatis {
     /// try get an DataBase Connection used for the next step
     pub async fn try_acquire(&self) -> Result<RBatisConnExecutor, Error> {
         let pool = self.get_pool()?;
-        let mut default = pool.inner.timeouts().clone();
-        default.wait = Some(Duration::ZERO);
-        let conn = pool.timeout_get(&default).await?;
+        let conn = pool.try_acquire().await?;
         return Ok(RBatisConnExecutor {
             id: new_snowflake_id(),
             conn: Mutex::new(Box::new(conn)),
@@ -188,7 +230,7 @@ impl RBatis {
     /// get an DataBase Connection,and call begin method,used for the next step
     pub async fn acquire_begin(&self) -> Result<RBatisTxExecutor, Error> {
         let pool = self.get_pool()?;
-        let mut conn = pool.get().await?;
+        let mut conn = pool.acquire().await?;
         conn.exec("begin", vec![]).await?;
         return Ok(RBatisTxExecutor {
             tx_id: new_snowflake_id(),
@@ -253,6 +253,21 @@ impl<T: Send+Sync> Page<T> {
         result
     }
 
+    /// create (Vec<offset,limit>) like `make_ranges`, shrinking page_size so that a range
+    /// binds no more than `max_params` when every item binds `params_per_item`
+    pub fn make_param_ranges(
+        total: u64,
+        page_size: u64,
+        params_per_item: u64,
+        max_params: u64,
+    ) -> Vec<(u64, u64)> {
+        let mut page_size = page_size;
+        if params_per_item != 0 {
+            page_size = page_size.min((max_params / params_per_item).max(1));
+        }
+        Self::make_ranges(total, page_size)
+    }
+
 
     pub fn set_total(mut self, total: u64) -> Self {
         self.total = total;
@@ -412,6 +427,16 @@ mod test {
         assert_eq!(v, new_v);
     }
 
+    #[test]
+    fn test_page_into_param_range() {
+        let ranges = Page::<i32>::make_param_ranges(9, 1000, 13, 40);
+        assert_eq!(ranges, vec![(0, 3), (3, 6), (6, 9)]);
+        let ranges = Page::<i32>::make_param_ranges(3, 2, 13, 65535);
+        assert_eq!(ranges, vec![(0, 2), (2, 3)]);
+        let ranges = Page::<i32>::make_param_ranges(2, 10, 100, 10);
+        assert_eq!(ranges, vec![(0, 1), (1, 2)]);
+    }
+
     #[test]
     fn test_page_into_pages() {
         let v = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
@@ -139,6 +139,16 @@ mod test {
     #[derive(Clone, Debug)]
     struct MockConnection {}
 
+    /// statements on `lost_connection` fail this many times with a connection reset
+    static LOST_CONNECTION: AtomicI32 = AtomicI32::new(0);
+
+    fn lost_connection(sql: &str) -> Result<(), Error> {
+        if sql.contains("lost_connection") && LOST_CONNECTION.fetch_sub(1, Ordering::SeqCst) > 0 {
+            return Err(Error::from("Connection reset by peer (os error 104)"));
+        }
+        Ok(())
+    }
+
     impl Connection for MockConnection {
         fn get_rows(
             &mut self,
@@ -147,13 +157,20 @@ mod test {
         ) -> BoxFuture<Result<Vec<Box<dyn Row>>, Error>> {
             let sql = sql.to_string();
             Box::pin(async move {
+                lost_connection(&sql)?;
                 let data = Box::new(MockRow { sql: sql, count: 1 }) as Box<dyn Row>;
                 Ok(vec![data])
             })
         }
 
         fn exec(&mut self, sql: &str, params: Vec<Value>) -> BoxFuture<Result<ExecResult, Error>> {
+            let sleep = sql.starts_with("select sleep");
+            let lost = lost_connection(sql);
             Box::pin(async move {
+                lost?;
+                if sleep {
+                    rbdc::rt::tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                }
                 Ok(ExecResult {
                     rows_affected: 0,
                     last_insert_id: Value::Null,
@@ -247,6 +264,204 @@ mod test {
         block_on(f);
     }
 
+    #[test]
+    fn test_pool_min_idle() {
+        let f = async move {
+            let rb = RBatis::new();
+            rb.init_pool(
+                MockDriver {},
+                "test",
+                rbdc::pool::PoolOptions::new()
+                    .max_size(4)
+                    .min_idle(2)
+                    .reaper_interval(std::time::Duration::from_millis(10)),
+            )
+            .unwrap();
+            rbdc::rt::sleep(std::time::Duration::from_millis(50)).await;
+            let status = rb.get_pool().unwrap().status();
+            assert_eq!(status.size, 2);
+            assert_eq!(status.available, 2);
+            rb.get_pool().unwrap().close();
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_pool_max_lifetime() {
+        let f = async move {
+            let rb = RBatis::new();
+            rb.init_pool(
+                MockDriver {},
+                "test",
+                rbdc::pool::PoolOptions::new()
+                    .max_lifetime(Some(std::time::Duration::from_millis(10)))
+                    .reaper_interval(std::time::Duration::from_secs(60)),
+            )
+            .unwrap();
+            let pool = rb.get_pool().unwrap();
+            drop(pool.get().await.unwrap());
+            assert_eq!(pool.status().size, 1);
+            rbdc::rt::sleep(std::time::Duration::from_millis(20)).await;
+            pool.reap().await;
+            assert_eq!(pool.status().size, 0);
+            pool.close();
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_pool_hooks() {
+        static CONNECT: AtomicI32 = AtomicI32::new(0);
+        static ACQUIRE: AtomicI32 = AtomicI32::new(0);
+        static RELEASE: AtomicI32 = AtomicI32::new(0);
+        let f = async move {
+            let rb = RBatis::new();
+            rb.init_pool(
+                MockDriver {},
+                "test",
+                rbdc::pool::PoolOptions::new()
+                    .after_connect(|conn| {
+                        Box::pin(async move {
+                            conn.exec("SET search_path = app", vec![]).await?;
+                            CONNECT.fetch_add(1, Ordering::SeqCst);
+                            Ok(())
+                        })
+                    })
+                    .before_acquire(|_conn| {
+                        Box::pin(async move {
+                            ACQUIRE.fetch_add(1, Ordering::SeqCst);
+                            Ok(())
+                        })
+                    })
+                    .after_release(|_conn| {
+                        Box::pin(async move {
+                            RELEASE.fetch_add(1, Ordering::SeqCst);
+                            Err(Error::from("reset fail"))
+                        })
+                    }),
+            )
+            .unwrap();
+            let pool = rb.get_pool().unwrap();
+            drop(pool.acquire().await.unwrap());
+            // after_release runs on a task spawned by the drop
+            rbdc::rt::sleep(std::time::Duration::from_millis(10)).await;
+            assert_eq!(RELEASE.load(Ordering::SeqCst), 1);
+            assert_eq!(CONNECT.load(Ordering::SeqCst), 1);
+            assert_eq!(ACQUIRE.load(Ordering::SeqCst), 0);
+            // the failed after_release hook discards the connection and a new one is created
+            drop(pool.get().await.unwrap());
+            assert_eq!(RELEASE.load(Ordering::SeqCst), 1);
+            assert_eq!(ACQUIRE.load(Ordering::SeqCst), 0);
+            assert_eq!(CONNECT.load(Ordering::SeqCst), 2);
+            assert_eq!(pool.status().size, 1);
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_pool_rollback_abandoned_tx() {
+        let f = async move {
+            let rb = RBatis::new();
+            rb.init_pool(MockDriver {}, "test", rbdc::pool::PoolOptions::new().max_size(1))
+                .unwrap();
+            let tx = rb.acquire_begin().await.unwrap();
+            assert_eq!(tx.conn.lock().await.in_transaction(), Some(true));
+            drop(tx);
+            let conn = rb.get_pool().unwrap().get().await.unwrap();
+            assert_eq!(conn.in_transaction(), Some(false));
+            drop(conn);
+            let mut tx = rb.acquire_begin().await.unwrap();
+            tx.commit().await.unwrap();
+            assert_eq!(tx.conn.lock().await.in_transaction(), Some(false));
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_pool_metrics() {
+        let f = async move {
+            let rb = RBatis::new();
+            rb.init(MockDriver {}, "test").unwrap();
+            rb.exec("update mock_table set name = 1", vec![]).await.unwrap();
+            let _: Vec<MockTable> = rb
+                .query_decode("select * from mock_table", vec![])
+                .await
+                .unwrap();
+            let metrics = rb.get_pool().unwrap().metrics();
+            assert_eq!(metrics.connections_created, 1);
+            assert_eq!(metrics.acquire_wait.count, 2);
+            assert_eq!(metrics.statement.count, 2);
+            // both connections were returned, their in-use time is recorded
+            assert_eq!(metrics.in_use.count, 2);
+            assert_eq!(metrics.size, 1);
+            let json = serde_json::to_string(&metrics).unwrap();
+            assert!(json.contains("\"connections_created\":1"));
+            let text = metrics.to_prometheus("rbdc_");
+            assert!(text.contains("rbdc_statement_seconds_count 2\n"));
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_statement_timeout() {
+        let f = async move {
+            let rb = RBatis::new();
+            let opts = rbdc::pool::PoolOptions::new()
+                .statement_timeout(Some(std::time::Duration::from_millis(50)));
+            rb.init_pool(MockDriver {}, "test", opts).unwrap();
+            let r = rb.exec("select sleep(60)", vec![]).await;
+            assert!(r.unwrap_err().to_string().contains("statement timeout"));
+            //without a cancel handle the connection is closed instead of reused
+            rb.exec("update mock_table set name = 1", vec![]).await.unwrap();
+            let metrics = rb.get_pool().unwrap().metrics();
+            assert_eq!(metrics.connections_created, 2);
+            assert_eq!(metrics.connections_closed, 1);
+            let r = rb
+                .exec_timeout(
+                    "select sleep(60)",
+                    vec![],
+                    Some(std::time::Duration::from_millis(10)),
+                )
+                .await;
+            assert!(r.unwrap_err().to_string().contains("statement timeout after 10ms"));
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let f = async move {
+            let rb = RBatis::new();
+            rb.init(MockDriver {}, "test").unwrap();
+            //the policy is shared with the clones made before it was set
+            let clone = rb.clone();
+            clone.set_retry_policy(
+                rbatis::retry::RetryPolicy::new().backoff(std::time::Duration::from_millis(1)),
+            );
+            LOST_CONNECTION.store(2, Ordering::SeqCst);
+            rb.query("select * from lost_connection", vec![]).await.unwrap();
+            assert_eq!(rb.get_pool().unwrap().metrics().connections_created, 2);
+            //writes are not retried
+            LOST_CONNECTION.store(1, Ordering::SeqCst);
+            let r = rb.exec("update lost_connection set name = 1", vec![]).await;
+            assert!(r.unwrap_err().to_string().contains("Connection reset"));
+            //neither are selects taking a sequence value
+            LOST_CONNECTION.store(1, Ordering::SeqCst);
+            let r = rb.query("select nextval('lost_connection')", vec![]).await;
+            assert!(r.unwrap_err().to_string().contains("Connection reset"));
+            LOST_CONNECTION.store(1, Ordering::SeqCst);
+            rbatis::retry::idempotent(rb.exec("update lost_connection set name = 1", vec![]))
+                .await
+                .unwrap();
+            //give up after max_attempts
+            LOST_CONNECTION.store(3, Ordering::SeqCst);
+            let r = rb.query("select * from lost_connection", vec![]).await;
+            assert!(r.is_err());
+            LOST_CONNECTION.store(0, Ordering::SeqCst);
+        };
+        block_on(f);
+    }
+
     crud!(MockTable {});
     #[test]
     fn test_insert() {
@@ -369,6 +584,46 @@ mod test {
         block_on(f);
     }
 
+    #[test]
+    fn test_insert_batch_limit() {
+        let t = MockTable {
+            id: Some("2".into()),
+            name: Some("2".into()),
+            pc_link: None,
+            h5_link: None,
+            pc_banner_img: None,
+            h5_banner_img: None,
+            sort: None,
+            status: Some(2),
+            remark: None,
+            create_time: None,
+            version: Some(1),
+            delete_flag: Some(1),
+            count: 0,
+        };
+        let ts = vec![t.clone(), t.clone(), t.clone()];
+        // 13 columns, at most 2 rows fit in 30 parameters
+        let r = MockTable::insert_batch_limit(&ts, 1000, 30).unwrap();
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].1.len(), 26);
+        assert_eq!(r[1].1.len(), 13);
+        let r = MockTable::insert_batch_limit(&ts, 1000, 2100).unwrap();
+        assert_eq!(r.len(), 1);
+        assert_eq!(r[0].1.len(), 39);
+    }
+
+    #[test]
+    fn test_delete_and_select_in_column_limit() {
+        let r = MockTable::delete_by_column_batch_limit("1", &["1", "2", "3"], 1000, 2).unwrap();
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].0, "delete from mock_table where 1 in (?,?)");
+        assert_eq!(r[1].1, vec![to_value!("3")]);
+        let r = MockTable::select_in_column_batch("1", &["1", "2", "3"], 1000, 2).unwrap();
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].1, vec![to_value!("1"), to_value!("2")]);
+        assert_eq!(r[1].0, "select id,name,pc_link,h5_link,pc_banner_img,h5_banner_img,sort,status,remark,create_time,version,delete_flag,count from mock_table  where 1 in (?)");
+    }
+
     #[test]
     fn test_update_by_column() {
         let f = async move {
@@ -977,6 +1232,88 @@ mod test {
         block_on(f);
     }
 
+    #[test]
+    fn test_exec_batch() {
+        let f = async move {
+            let mut rb = RBatis::new();
+            let queue = Arc::new(SyncVec::new());
+            rb.set_intercepts(vec![Arc::new(MockIntercept::new(queue.clone()))]);
+            rb.init(MockDriver {}, "test").unwrap();
+            let batch = vec![
+                ("update mock_table set name = ? where id = ?".to_string(), vec![to_value!("a"), to_value!("1")]),
+                ("delete from mock_table where id = ?".to_string(), vec![to_value!("2")]),
+            ];
+            let r = rb.exec_batch(batch.clone()).await.unwrap();
+            assert_eq!(r.len(), 2);
+            assert_eq!(queue.len(), 2);
+            assert_eq!(queue.get(0).unwrap().clone(), batch[0]);
+            assert_eq!(queue.get(1).unwrap().clone(), batch[1]);
+
+            let tx = rb.acquire_begin().await.unwrap();
+            let r = tx.exec_batch(batch).await.unwrap();
+            assert_eq!(r.len(), 2);
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_batch_exec() {
+        let f = async move {
+            let mut rb = RBatis::new();
+            let queue = Arc::new(SyncVec::new());
+            rb.set_intercepts(vec![Arc::new(MockIntercept::new(queue.clone()))]);
+            rb.init(MockDriver {}, "test").unwrap();
+            let t = MockTable {
+                id: Some("2".into()),
+                status: Some(2),
+                ..Default::default()
+            };
+            let mut t2 = t.clone();
+            t2.id = Some("3".into());
+            let ts = vec![t, t2];
+            MockTable::insert_batch_exec(&rb, &ts, 1).await.unwrap();
+            assert_eq!(queue.len(), 2);
+            assert_eq!(queue.get(1).unwrap().1[0], to_value!("3"));
+            MockTable::update_by_column_batch_exec(&rb, &ts, "id", 10)
+                .await
+                .unwrap();
+            assert_eq!(queue.len(), 4);
+            assert_eq!(
+                queue.get(3).unwrap().0,
+                "update mock_table set status=?,count=? where id = ?"
+            );
+            MockTable::delete_by_column_batch_exec(&rb, "id", &["2", "3"], 10)
+                .await
+                .unwrap();
+            assert_eq!(queue.len(), 5);
+            assert_eq!(queue.get(4).unwrap().0, "delete from mock_table where id in (?,?)");
+            // a plan over the limit of the driver is not run
+            let args = vec![to_value!(1); rbatis::rbdc::db::DEFAULT_MAX_PARAMS + 1];
+            let plan = vec![("insert".to_string(), args, false, None, None)];
+            assert!(rbatis::executor::exec_plan(&rb, plan).await.is_err());
+            assert_eq!(queue.len(), 5);
+        };
+        block_on(f);
+    }
+
+    #[test]
+    fn test_get_result_sets() {
+        let f = async move {
+            let rb = RBatis::new();
+            rb.init(MockDriver {}, "test").unwrap();
+            let mut conn = rb.get_pool().unwrap().get().await.unwrap();
+            let (sets, results) = conn
+                .get_result_sets("select * from mock_table", vec![])
+                .await
+                .unwrap();
+            assert_eq!(sets.len(), 1);
+            assert_eq!(sets[0].len(), 1);
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].rows_affected, 1);
+        };
+        block_on(f);
+    }
+
     #[test]
     fn test_pool_get() {
         let f = async move {
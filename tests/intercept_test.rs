@@ -4,7 +4,7 @@ mod test {
     use futures_core::future::BoxFuture;
     use log::{Log, Metadata, Record};
     use rbatis::executor::Executor;
-    use rbatis::intercept::{Intercept, ResultType};
+    use rbatis::intercept::{context, Intercept, ResultType};
     use rbatis::{Error, RBatis};
     use rbdc::db::{ConnectOptions, Connection, Driver, ExecResult, MetaData, Row};
     use rbdc::rt::block_on;
@@ -129,10 +129,18 @@ mod test {
 
         fn exec(
             &mut self,
-            _sql: &str,
+            sql: &str,
             _params: Vec<Value>,
         ) -> BoxFuture<Result<ExecResult, Error>> {
+            let slow = sql.starts_with("update slow");
+            let fail = sql.starts_with("update fail");
             Box::pin(async move {
+                if slow {
+                    rbdc::rt::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                if fail {
+                    return Err(Error::from("mock update failed"));
+                }
                 Ok(ExecResult {
                     rows_affected: 0,
                     last_insert_id: Value::Null,
@@ -229,4 +237,156 @@ mod test {
         m.inner.store(1, Ordering::SeqCst);
         assert_eq!(m.inner.load(Ordering::Relaxed), 1);
     }
+
+    #[test]
+    fn test_intercept_context() {
+        #[derive(Debug)]
+        pub struct MockIntercept {
+            pub elapsed: AtomicI64,
+        }
+
+        #[async_trait]
+        impl Intercept for MockIntercept {
+            async fn before(
+                &self,
+                task_id: i64,
+                _rb: &dyn Executor,
+                sql: &mut String,
+                _args: &mut Vec<Value>,
+                _result: ResultType<&mut Result<ExecResult, Error>, &mut Result<Vec<Value>, Error>>,
+            ) -> Result<bool, Error> {
+                let ctx = context().unwrap();
+                assert_eq!(ctx.task_id, task_id);
+                ctx.insert("sql", Value::String(sql.clone()));
+                Ok(true)
+            }
+
+            async fn after(
+                &self,
+                _task_id: i64,
+                _rb: &dyn Executor,
+                sql: &mut String,
+                _args: &mut Vec<Value>,
+                _result: ResultType<&mut Result<ExecResult, Error>, &mut Result<Vec<Value>, Error>>,
+            ) -> Result<bool, Error> {
+                let ctx = context().unwrap();
+                assert_eq!(ctx.get("sql"), Some(Value::String(sql.clone())));
+                self.elapsed
+                    .store(ctx.elapsed().as_millis() as i64, Ordering::SeqCst);
+                Ok(true)
+            }
+        }
+        let rb = RBatis::new();
+        rb.init(MockDriver {}, "test").unwrap();
+        rb.intercepts.clear();
+        rb.intercepts.push(Arc::new(MockIntercept {
+            elapsed: AtomicI64::new(0),
+        }));
+        block_on(async move {
+            rb.exec("update slow set a = 1", vec![]).await.unwrap();
+            assert!(context().is_none());
+            let m = rb.get_intercept::<MockIntercept>().unwrap();
+            assert!(m.elapsed.load(Ordering::Relaxed) >= 20);
+        });
+    }
+
+    #[test]
+    fn test_intercept_elapsed_without_lock_wait() {
+        #[derive(Debug)]
+        pub struct MockIntercept {
+            pub elapsed: AtomicI64,
+        }
+
+        #[async_trait]
+        impl Intercept for MockIntercept {
+            async fn after(
+                &self,
+                _task_id: i64,
+                _rb: &dyn Executor,
+                _sql: &mut String,
+                _args: &mut Vec<Value>,
+                _result: ResultType<&mut Result<ExecResult, Error>, &mut Result<Vec<Value>, Error>>,
+            ) -> Result<bool, Error> {
+                let ctx = context().unwrap();
+                self.elapsed
+                    .store(ctx.elapsed().as_millis() as i64, Ordering::SeqCst);
+                Ok(true)
+            }
+        }
+        let rb = RBatis::new();
+        rb.init(MockDriver {}, "test").unwrap();
+        rb.intercepts.clear();
+        rb.intercepts.push(Arc::new(MockIntercept {
+            elapsed: AtomicI64::new(0),
+        }));
+        block_on(async move {
+            let tx = rb.acquire_begin().await.unwrap();
+            //another statement of the tx holds the connection for 200ms
+            let hold = async {
+                let _conn = tx.conn.lock().await;
+                rbdc::rt::sleep(std::time::Duration::from_millis(200)).await;
+            };
+            let exec = async {
+                rbdc::rt::sleep(std::time::Duration::from_millis(10)).await;
+                tx.exec("update slow set a = 1", vec![]).await.unwrap();
+            };
+            futures::join!(hold, exec);
+            let m = rb.get_intercept::<MockIntercept>().unwrap();
+            let elapsed = m.elapsed.load(Ordering::Relaxed);
+            assert!(elapsed >= 20 && elapsed < 200, "elapsed {}", elapsed);
+        });
+    }
+
+    #[test]
+    fn test_intercept_batch_error() {
+        #[derive(Debug)]
+        pub struct MockIntercept {
+            pub results: std::sync::Mutex<Vec<String>>,
+        }
+
+        #[async_trait]
+        impl Intercept for MockIntercept {
+            async fn after(
+                &self,
+                _task_id: i64,
+                _rb: &dyn Executor,
+                _sql: &mut String,
+                _args: &mut Vec<Value>,
+                result: ResultType<&mut Result<ExecResult, Error>, &mut Result<Vec<Value>, Error>>,
+            ) -> Result<bool, Error> {
+                if let ResultType::Exec(result) = result {
+                    self.results.lock().unwrap().push(match result {
+                        Ok(_) => "ok".to_string(),
+                        Err(e) => e.to_string(),
+                    });
+                }
+                Ok(true)
+            }
+        }
+        let rb = RBatis::new();
+        rb.init(MockDriver {}, "test").unwrap();
+        rb.intercepts.clear();
+        rb.intercepts.push(Arc::new(MockIntercept {
+            results: Default::default(),
+        }));
+        block_on(async move {
+            let batch = vec![
+                ("update a set a = 1".to_string(), vec![]),
+                ("update fail set a = 1".to_string(), vec![]),
+                ("update b set a = 1".to_string(), vec![]),
+            ];
+            let tx = rb.acquire_begin().await.unwrap();
+            let e = tx.exec_batch(batch).await.unwrap_err();
+            assert_eq!(e.to_string(), "mock update failed");
+            let m = rb.get_intercept::<MockIntercept>().unwrap();
+            assert_eq!(
+                *m.results.lock().unwrap(),
+                vec![
+                    "ok".to_string(),
+                    "mock update failed".to_string(),
+                    Error::batch_aborted().to_string()
+                ]
+            );
+        });
+    }
 }